@@ -0,0 +1,5 @@
+(ns hello.core)
+
+(defn -main
+  [& _args]
+  (println "Hello from Clojure"))
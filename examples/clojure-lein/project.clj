@@ -0,0 +1,7 @@
+(defproject hello "0.1.0-SNAPSHOT"
+  :description "Hello world with Leiningen"
+  :dependencies [[org.clojure/clojure "1.11.1"]]
+  :main ^:skip-aot hello.core
+  :target-path "target/%s"
+  :profiles {:uberjar {:aot :all
+                       :jvm-opts ["-Dclojure.compiler.direct-linking=true"]}})
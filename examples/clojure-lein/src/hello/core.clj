@@ -0,0 +1,6 @@
+(ns hello.core
+  (:gen-class))
+
+(defn -main
+  [& _args]
+  (println "Hello from Clojure"))
@@ -0,0 +1 @@
+Console.WriteLine("Hello from the worker");
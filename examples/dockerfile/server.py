@@ -0,0 +1,14 @@
+import os
+from http.server import BaseHTTPRequestHandler, HTTPServer
+
+
+class Handler(BaseHTTPRequestHandler):
+    def do_GET(self):
+        self.send_response(200)
+        self.end_headers()
+        self.wfile.write(os.environ.get("GREETING", "Hello").encode())
+
+
+port = int(os.environ.get("PORT", "8000"))
+print(os.environ.get("GREETING", "Hello"))
+HTTPServer(("0.0.0.0", port), Handler).serve_forever()
@@ -0,0 +1,6 @@
+module.exports = {
+  title: "Nixpacks Docusaurus",
+  url: "https://example.org",
+  baseUrl: "/",
+  presets: [["classic", { docs: { routeBasePath: "/" }, blog: false }]],
+};
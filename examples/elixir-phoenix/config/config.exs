@@ -0,0 +1,12 @@
+import Config
+
+config :hello, HelloWeb.Endpoint, url: [host: "localhost"]
+
+config :esbuild,
+  version: "0.14.41",
+  default: [
+    args: ~w(js/app.js --bundle --target=es2017 --outdir=../priv/static/assets),
+    cd: Path.expand("../assets", __DIR__)
+  ]
+
+config :phoenix, :json_library, Jason
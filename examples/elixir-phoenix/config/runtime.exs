@@ -0,0 +1,10 @@
+import Config
+
+if config_env() == :prod do
+  port = String.to_integer(System.get_env("PORT") || "4000")
+
+  config :hello, HelloWeb.Endpoint,
+    http: [ip: {0, 0, 0, 0}, port: port],
+    secret_key_base: System.get_env("SECRET_KEY_BASE") || String.duplicate("a", 64),
+    server: System.get_env("PHX_SERVER") == "true"
+end
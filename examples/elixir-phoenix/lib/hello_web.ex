@@ -0,0 +1,12 @@
+defmodule HelloWeb.Endpoint do
+  use Phoenix.Endpoint, otp_app: :hello
+
+  plug Plug.Static, at: "/", from: :hello, gzip: false
+  plug :hello
+
+  def hello(conn, _opts) do
+    conn
+    |> Plug.Conn.put_resp_content_type("text/plain")
+    |> Plug.Conn.send_resp(200, "Hello from Phoenix")
+  end
+end
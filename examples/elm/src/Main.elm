@@ -0,0 +1,7 @@
+module Main exposing (main)
+
+import Html exposing (text)
+
+
+main =
+    text "Hello from Elm"
@@ -0,0 +1,3 @@
+module github.com/railwayapp/api
+
+go 1.18
@@ -0,0 +1,3 @@
+module github.com/railwayapp/worker
+
+go 1.18
@@ -0,0 +1 @@
+rootProject.name = "java-gradle-kotlin"
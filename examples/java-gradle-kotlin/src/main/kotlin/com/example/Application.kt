@@ -0,0 +1,18 @@
+package com.example
+
+import io.micronaut.http.annotation.Controller
+import io.micronaut.http.annotation.Get
+import io.micronaut.runtime.Micronaut.build
+
+@Controller("/")
+class HelloController {
+    @Get
+    fun index() = "Hello from Kotlin"
+}
+
+fun main(args: Array<String>) {
+    build()
+        .args(*args)
+        .packages("com.example")
+        .start()
+}
@@ -0,0 +1 @@
+module.exports = (name) => `Hello from ${name}`;
@@ -0,0 +1,3 @@
+const greeting = require("../../libs/greeting");
+
+console.log(greeting("the API"));
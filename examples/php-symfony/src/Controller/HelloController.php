@@ -0,0 +1,15 @@
+<?php
+
+namespace App\Controller;
+
+use Symfony\Component\HttpFoundation\Response;
+use Symfony\Component\Routing\Annotation\Route;
+
+class HelloController
+{
+    #[Route('/')]
+    public function index(): Response
+    {
+        return new Response('Hello from Symfony');
+    }
+}
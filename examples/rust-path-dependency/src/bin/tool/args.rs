@@ -0,0 +1,3 @@
+pub fn name() -> String {
+    std::env::args().nth(1).unwrap_or_else(|| "tool".to_string())
+}
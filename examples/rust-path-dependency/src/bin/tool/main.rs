@@ -0,0 +1,5 @@
+mod args;
+
+fn main() {
+    println!("{}", args::name());
+}
@@ -0,0 +1,9 @@
+use std::time::Instant;
+
+fn main() {
+    let start = Instant::now();
+    for i in 0..1000 {
+        shared::greeting(&i.to_string());
+    }
+    println!("greeting: {:?}", start.elapsed());
+}
@@ -0,0 +1 @@
+document.getElementById("root").textContent = `Hello from ${window.location.pathname}`;
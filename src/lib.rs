@@ -8,18 +8,31 @@ use crate::nixpacks::{
     logger::Logger,
     nix::pkg::Pkg,
     plan::{
+        diff::{self, PlanChange},
         generator::{GeneratePlanOptions, NixpacksBuildPlanGenerator},
         BuildPlan, PlanGenerator,
     },
+    runner::{DockerRunner, RunOptions},
+    shell::{self as dev_shell, DevShellOptions},
+    source::AppSource,
 };
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use providers::{
-    crystal::CrystalProvider, csharp::CSharpProvider, dart::DartProvider, deno::DenoProvider,
-    fsharp::FSharpProvider, go::GolangProvider, haskell::HaskellStackProvider, java::JavaProvider,
-    node::NodeProvider, php::PhpProvider, python::PythonProvider, ruby::RubyProvider,
-    rust::RustProvider, staticfile::StaticfileProvider, swift::SwiftProvider, zig::ZigProvider,
+    clojure::ClojureProvider, crystal::CrystalProvider, csharp::CSharpProvider, dart::DartProvider,
+    deno::DenoProvider, dockerfile::DockerfileProvider, docusaurus::DocusaurusProvider,
+    elixir::ElixirProvider, elm::ElmProvider, fsharp::FSharpProvider, gleam::GleamProvider,
+    go::GolangProvider, haskell::HaskellStackProvider, hugo::HugoProvider, java::JavaProvider,
+    jekyll::JekyllProvider, mkdocs::MkDocsProvider, node::NodeProvider, php::PhpProvider,
+    python::PythonProvider, ruby::RubyProvider, rust::RustProvider, scala::ScalaProvider,
+    staticfile::StaticfileProvider, swift::SwiftProvider, zig::ZigProvider, zola::ZolaProvider,
     Provider,
 };
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
+use uuid::Uuid;
 
 mod chain;
 #[macro_use]
@@ -28,11 +41,20 @@ pub mod providers;
 
 pub fn get_providers() -> Vec<&'static dyn Provider> {
     vec![
+        &ClojureProvider {},
         &CrystalProvider {},
         &CSharpProvider {},
         &DartProvider {},
         &DenoProvider {},
+        // Static site generators, before the languages their sites are built with
+        &DocusaurusProvider {},
+        &HugoProvider {},
+        &JekyllProvider {},
+        &MkDocsProvider {},
+        &ElixirProvider {},
+        &ElmProvider {},
         &FSharpProvider {},
+        &GleamProvider {},
         &GolangProvider {},
         &HaskellStackProvider {},
         &JavaProvider {},
@@ -41,19 +63,39 @@ pub fn get_providers() -> Vec<&'static dyn Provider> {
         &NodeProvider {},
         &PythonProvider {},
         &RustProvider {},
+        &ScalaProvider {},
+        // Before Staticfile, which would serve the sources as is
+        &ZolaProvider {},
         &StaticfileProvider {},
         &SwiftProvider {},
         &ZigProvider {},
+        // Fallback for apps that only have a Dockerfile
+        &DockerfileProvider {},
     ]
 }
 
+fn create_app(
+    path: &str,
+    environment: &Environment,
+    plan_options: &GeneratePlanOptions,
+) -> Result<App> {
+    let mut app = App::from_source(path)?;
+    if let Some(root) = &plan_options.root {
+        app.set_root(root)?;
+    }
+    if environment.is_config_variable_truthy("USE_GITIGNORE") {
+        app.use_gitignore()?;
+    }
+    Ok(app)
+}
+
 pub fn generate_build_plan(
     path: &str,
     envs: Vec<&str>,
     plan_options: &GeneratePlanOptions,
 ) -> Result<BuildPlan> {
-    let app = App::new(path)?;
-    let environment = Environment::from_envs(envs)?;
+    let environment = Environment::from_sources(&plan_options.env_files, envs)?;
+    let app = create_app(path, &environment, plan_options)?;
 
     let mut generator = NixpacksBuildPlanGenerator::new(get_providers(), plan_options.to_owned());
     let plan = generator.generate_plan(&app, &environment)?;
@@ -61,21 +103,100 @@ pub fn generate_build_plan(
     Ok(plan)
 }
 
+/// Changes from the plan of `other`, another app source or a saved plan file, to the plan of the app
+pub fn diff_build_plans(
+    path: &str,
+    other: &str,
+    envs: Vec<&str>,
+    plan_options: &GeneratePlanOptions,
+) -> Result<Vec<PlanChange>> {
+    let plan = generate_build_plan(path, envs.clone(), plan_options)?;
+
+    // Archives are app sources too, so only JSON files are read as saved plans
+    let is_plan_file = matches!(AppSource::parse(other), AppSource::Local(_))
+        && Path::new(other).is_file()
+        && Path::new(other)
+            .extension()
+            .is_some_and(|ext| ext == "json");
+    let other_plan = if is_plan_file {
+        let plan_json = fs::read_to_string(other).context("Reading build plan")?;
+        serde_json::from_str(&plan_json).context("Deserializing build plan")?
+    } else {
+        generate_build_plan(other, envs, plan_options)
+            .with_context(|| format!("Generating the plan of {}", other))?
+    };
+
+    Ok(diff::diff_plans(&other_plan, &plan))
+}
+
 pub fn create_docker_image(
     path: &str,
     envs: Vec<&str>,
     plan_options: &GeneratePlanOptions,
     build_options: &DockerBuilderOptions,
 ) -> Result<()> {
-    let app = App::new(path)?;
-    let environment = Environment::from_envs(envs)?;
+    let environment = Environment::from_sources(&plan_options.env_files, envs)?;
+    let app = create_app(path, &environment, plan_options)?;
 
     let mut generator = NixpacksBuildPlanGenerator::new(get_providers(), plan_options.to_owned());
     let plan = generator.generate_plan(&app, &environment)?;
 
     let logger = Logger::new();
     let builder = DockerBuilder::new(logger, build_options.to_owned());
-    builder.create_image(app.source.to_str().unwrap(), &plan)?;
+    builder.create_image(&app, &plan)?;
 
     Ok(())
 }
+
+/// Builds the app and runs it until it exits, returning the app's exit status
+pub fn run_app(
+    path: &str,
+    envs: Vec<&str>,
+    plan_options: &GeneratePlanOptions,
+    build_options: &DockerBuilderOptions,
+    run_options: &RunOptions,
+) -> Result<ExitStatus> {
+    let environment = Environment::from_sources(&plan_options.env_files, envs)?;
+    let app = create_app(path, &environment, plan_options)?;
+
+    let mut generator = NixpacksBuildPlanGenerator::new(get_providers(), plan_options.to_owned());
+    let plan = generator.generate_plan(&app, &environment)?;
+
+    let name = match (&build_options.name, run_options.no_build) {
+        (Some(name), _) => name.clone(),
+        (None, true) => bail!("Pass the --name of an image built before to run it with --no-build"),
+        (None, false) => Uuid::new_v4().to_string(),
+    };
+
+    if !run_options.no_build {
+        let logger = Logger::new();
+        let builder = DockerBuilder::new(
+            logger,
+            DockerBuilderOptions {
+                name: Some(name.clone()),
+                out_dir: None,
+                ..build_options.to_owned()
+            },
+        );
+        builder.create_image(&app, &plan)?;
+    }
+
+    let runner = DockerRunner::new(run_options.to_owned());
+    runner.run(&name, &plan, &Environment::clone_variables(&environment))
+}
+
+/// Writes a `shell.nix` with the app's build environment, returning the written files
+pub fn create_dev_shell(
+    path: &str,
+    envs: Vec<&str>,
+    plan_options: &GeneratePlanOptions,
+    shell_options: &DevShellOptions,
+) -> Result<Vec<PathBuf>> {
+    let environment = Environment::from_sources(&plan_options.env_files, envs)?;
+    let app = create_app(path, &environment, plan_options)?;
+
+    let mut generator = NixpacksBuildPlanGenerator::new(get_providers(), plan_options.to_owned());
+    let plan = generator.generate_plan(&app, &environment)?;
+
+    dev_shell::write_dev_shell(&app, &plan, shell_options)
+}
@@ -1,10 +1,15 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::{arg, Arg, Command};
 use nixpacks::{
-    create_docker_image, generate_build_plan,
+    create_dev_shell, create_docker_image, diff_build_plans, generate_build_plan,
     nixpacks::{
-        builder::docker::DockerBuilderOptions, nix::pkg::Pkg, plan::generator::GeneratePlanOptions,
+        builder::docker::DockerBuilderOptions,
+        nix::pkg::Pkg,
+        plan::{diff::get_diff_string, generator::GeneratePlanOptions},
+        runner::RunOptions,
+        shell::DevShellOptions,
     },
+    run_app,
 };
 
 fn main() -> Result<()> {
@@ -17,12 +22,67 @@ fn main() -> Result<()> {
         .subcommand(
             Command::new("plan")
                 .about("Generate a build plan for an app")
-                .arg(arg!(<PATH> "App source")),
+                .arg(arg!(<PATH> "App source: a directory, git URL or .tar.gz/.zip archive"))
+                .arg(
+                    Arg::new("diff")
+                        .long("diff")
+                        .help("Show how the plan changed from another app source or plan file, failing if it did")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Build an app and run it locally with Docker")
+                .arg(arg!(<PATH> "App source: a directory, git URL or .tar.gz/.zip archive"))
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .short('n')
+                        .help("Name for the built image")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .help("Port to publish the app on, the app's port by default")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("no_build")
+                        .long("no-build")
+                        .help("Run the image built previously with --name")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("buildkit")
+                        .long("buildkit")
+                        .help("Forces docker to use buildkit")
+                        .takes_value(false),
+                ),
+        )
+        .subcommand(
+            Command::new("shell")
+                .alias("dev")
+                .about("Write a shell.nix with the app's build environment for local development")
+                .arg(arg!(<PATH> "App source directory"))
+                .arg(
+                    Arg::new("direnv")
+                        .long("direnv")
+                        .help("Also write an .envrc that loads the shell with direnv")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .short('f')
+                        .help("Overwrite existing files")
+                        .takes_value(false),
+                ),
         )
         .subcommand(
             Command::new("build")
                 .about("Create a docker image for an app")
-                .arg(arg!(<PATH> "App source"))
+                .arg(arg!(<PATH> "App source: a directory, git URL or .tar.gz/.zip archive"))
                 .arg(
                     Arg::new("name")
                         .long("name")
@@ -58,6 +118,12 @@ fn main() -> Result<()> {
                         .long("buildkit")
                         .help("Forces docker to use buildkit")
                         .takes_value(false),
+                )
+                .arg(
+                    Arg::new("fresh_context")
+                        .long("fresh-context")
+                        .help("Copy the app into a new build context instead of syncing the cached one")
+                        .takes_value(false),
                 ),
         )
         .arg(
@@ -67,6 +133,21 @@ fn main() -> Result<()> {
                 .takes_value(true)
                 .global(true),
         )
+        .arg(
+            Arg::new("env_file")
+                .long("env-file")
+                .help("Load environment variables from a dotenv file")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .global(true),
+        )
+        .arg(
+            Arg::new("root")
+                .long("root")
+                .help("Subdirectory of the app source to detect and plan the app from")
+                .takes_value(true)
+                .global(true),
+        )
         .arg(
             Arg::new("install_cmd")
                 .long("install-cmd")
@@ -156,6 +237,11 @@ fn main() -> Result<()> {
     };
 
     let plan_path = matches.value_of("plan").map(|n| n.to_string());
+    let root = matches.value_of("root").map(|n| n.to_string());
+    let env_files = match matches.values_of("env_file") {
+        Some(values) => values.map(String::from).collect::<Vec<String>>(),
+        None => Vec::new(),
+    };
 
     let plan_options = &GeneratePlanOptions {
         custom_install_cmd: install_cmd,
@@ -166,12 +252,32 @@ fn main() -> Result<()> {
         custom_apt_pkgs: apt_pkgs,
         pin_pkgs,
         plan_path,
+        root,
+        env_files,
     };
 
     match &matches.subcommand() {
         Some(("plan", matches)) => {
             let path = matches.value_of("PATH").expect("required");
 
+            if let Some(other) = matches.value_of("diff") {
+                // Like diff(1), 1 means the plans differ and 2 that they couldn't be compared
+                let changes = match diff_build_plans(path, other, envs, plan_options) {
+                    Ok(changes) => changes,
+                    Err(err) => {
+                        eprintln!("Error: {:?}", err);
+                        std::process::exit(2);
+                    }
+                };
+                if changes.is_empty() {
+                    println!("No changes");
+                } else {
+                    println!("{}", get_diff_string(&changes));
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+
             let plan = generate_build_plan(path, envs, plan_options)?;
             let json = serde_json::to_string_pretty(&plan)?;
             println!("{}", json);
@@ -192,6 +298,7 @@ fn main() -> Result<()> {
                 .unwrap_or_default();
 
             let force_buildkit = matches.is_present("buildkit");
+            let fresh_context = matches.is_present("fresh_context");
 
             let build_options = &DockerBuilderOptions {
                 name,
@@ -199,11 +306,50 @@ fn main() -> Result<()> {
                 labels,
                 out_dir,
                 force_buildkit,
+                fresh_context,
                 quiet: false,
             };
 
             create_docker_image(path, envs, plan_options, build_options)?;
         }
+        Some(("run", matches)) => {
+            let path = matches.value_of("PATH").expect("required");
+            let port = match matches.value_of("port") {
+                Some(port) => Some(
+                    port.parse::<u16>()
+                        .map_err(|_| anyhow!("Invalid port {}", port))?,
+                ),
+                None => None,
+            };
+
+            let build_options = &DockerBuilderOptions {
+                name: matches.value_of("name").map(|n| n.to_string()),
+                force_buildkit: matches.is_present("buildkit"),
+                ..Default::default()
+            };
+            let run_options = &RunOptions {
+                port,
+                no_build: matches.is_present("no_build"),
+            };
+
+            let status = run_app(path, envs, plan_options, build_options, run_options)?;
+            if !status.success() {
+                std::process::exit(status.code().unwrap_or(1));
+            }
+        }
+        Some(("shell", matches)) => {
+            let path = matches.value_of("PATH").expect("required");
+            let shell_options = &DevShellOptions {
+                direnv: matches.is_present("direnv"),
+                force: matches.is_present("force"),
+            };
+
+            let paths = create_dev_shell(path, envs, plan_options, shell_options)?;
+            for path in paths {
+                println!("Wrote {}", path.display());
+            }
+            println!("\nRun `nix-shell` in the app to enter the environment");
+        }
         _ => eprintln!("Invalid command"),
     }
 
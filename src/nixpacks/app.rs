@@ -1,21 +1,36 @@
 use anyhow::anyhow;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use std::{env, fs, path::PathBuf};
 
 use anyhow::{bail, Context, Result};
-use globset::Glob;
 use regex::Regex;
 use serde::de::DeserializeOwned;
-use walkdir::WalkDir;
+use tempdir::TempDir;
+
+use super::file_index::{FileIndex, FileIndexStats};
+use super::ignore::IgnoreMatcher;
+use super::source::{self, AppSource};
 
 pub static ASSETS_DIR: &str = "/assets/";
 pub type StaticAssets = HashMap<String, String>;
 
 #[derive(Debug, Clone)]
 pub struct App {
+    /// Directory the app is detected and planned from
     pub source: PathBuf,
+    /// Directory copied into the image, the source or a directory containing it
+    pub context: PathBuf,
     pub paths: Vec<PathBuf>,
+    /// Files excluded by `.dockerignore` and `.nixpacksignore`
+    pub ignore: IgnoreMatcher,
+    /// Built on the first query, once the root and ignore rules are set
+    index: OnceLock<FileIndex>,
+    /// The git URL or archive the app was checked out from
+    pub remote: Option<String>,
+    /// Removed when the last clone of the app is dropped
+    checkout: Option<Arc<TempDir>>,
 }
 
 impl App {
@@ -26,41 +41,127 @@ impl App {
             .canonicalize()
             .context("Failed to read app source directory")?;
 
-        let dir = fs::read_dir(source.clone()).context("Failed to read app source directory")?;
-        let paths: Vec<PathBuf> = dir.map(|path| path.unwrap().path()).collect();
+        let mut app = App {
+            context: source.clone(),
+            ignore: IgnoreMatcher::from_source(&source, false)?,
+            source,
+            paths: Vec::new(),
+            index: OnceLock::new(),
+            remote: None,
+            checkout: None,
+        };
+        app.index_files()?;
+
+        Ok(app)
+    }
+
+    /// Creates an app from a local path, a git URL or an archive
+    ///
+    /// Remote sources are checked out into a temp directory first.
+    pub fn from_source(source: &str) -> Result<App> {
+        let app_source = AppSource::parse(source);
+        let (path, checkout) = source::checkout(&app_source)?;
+        let path = path
+            .to_str()
+            .context("Failed to read app source directory")?
+            .to_string();
+
+        let mut app = App::new(&path)?;
+        if app_source.is_remote() {
+            app.remote = Some(source.to_string());
+            app.checkout = checkout.map(Arc::new);
+        }
+        Ok(app)
+    }
+
+    /// Detect and plan the app from a subdirectory, e.g. a service in a monorepo
+    ///
+    /// The whole directory is still copied into the image, so the app can use
+    /// its sibling directories. Ignore files are read from the top directory.
+    pub fn set_root(&mut self, root: &str) -> Result<()> {
+        let source = self
+            .context
+            .join(root)
+            .canonicalize()
+            .with_context(|| format!("App root {} does not exist", root))?;
+        if !source.starts_with(&self.context) || !source.is_dir() {
+            bail!("App root {} is not a directory in the app source", root);
+        }
+
+        self.source = source;
+        self.index_files()
+    }
+
+    /// Path of the app root relative to the context, empty when they are the same
+    pub fn root(&self) -> PathBuf {
+        self.source
+            .strip_prefix(&self.context)
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Directory of the app root in the image, e.g. `/app/services/api` with `--root`
+    pub fn image_dir(&self) -> String {
+        let root = self.root().to_string_lossy().replace('\\', "/");
+        if root.is_empty() {
+            "/app".to_string()
+        } else {
+            format!("/app/{}", root)
+        }
+    }
+
+    /// Also exclude the files ignored by `.gitignore`
+    pub fn use_gitignore(&mut self) -> Result<()> {
+        self.ignore = IgnoreMatcher::from_source(&self.context, true)?;
+        self.index_files()
+    }
+
+    fn index_files(&mut self) -> Result<()> {
+        let dir = fs::read_dir(&self.source).context("Failed to read app source directory")?;
+        self.paths = dir
+            .map(|path| path.unwrap().path())
+            .filter(|path| !self.is_ignored(path))
+            .collect();
+        self.index = OnceLock::new();
+        Ok(())
+    }
+
+    fn index(&self) -> &FileIndex {
+        self.index
+            .get_or_init(|| FileIndex::new(&self.source, |path| self.is_ignored(path)))
+    }
 
-        Ok(App { source, paths })
+    /// Whether an absolute path in the app is excluded by the ignore files
+    ///
+    /// Ignored directories with files re-included by a `!` pattern are not
+    /// excluded, only the other files in them.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        match path.strip_prefix(&self.context) {
+            Ok(relative) if path.is_dir() => self.ignore.is_ignored_dir(relative),
+            Ok(relative) => self.ignore.is_ignored(relative),
+            Err(_) => false,
+        }
     }
 
-    /// Check if a file exists
+    /// Check if a file exists and is not excluded by the ignore files
     pub fn includes_file(&self, name: &str) -> bool {
-        self.source.join(name).is_file()
+        let path = self.source.join(name);
+        path.is_file() && !self.is_ignored(&path)
     }
 
     /// Returns a list of paths matching a glob pattern
     ///
+    /// Files in `node_modules` and `.git` are never matched.
+    ///
     /// # Errors
     /// Creating the Glob fails
     pub fn find_files(&self, pattern: &str) -> Result<Vec<PathBuf>> {
         let full_pattern = self.source.join(pattern);
 
-        let pattern_str = match full_pattern.to_str() {
-            Some(s) => s,
-            None => return Ok(Vec::new()),
-        };
-
-        let walker = WalkDir::new(&self.source);
-        let glob = Glob::new(pattern_str)?.compile_matcher();
-
-        let relative_paths = walker
-            .sort_by_file_name()
-            .into_iter()
-            .filter_map(|result| result.ok()) // remove bad ones
-            .map(|dir| dir.into_path()) // convert to paths
-            .filter(|path| glob.is_match(path) && path.is_file()) // find matches
-            .collect();
-
-        Ok(relative_paths)
+        match full_pattern.to_str() {
+            Some(pattern_str) => self.index().find_files(pattern_str),
+            None => Ok(Vec::new()),
+        }
     }
 
     /// Check if a path matching a glob exists
@@ -74,34 +175,35 @@ impl App {
     /// Read the contents of a file
     ///
     /// # Errors
-    /// This will error if the path doesn't exist, or if the contents isn't UTF-8
+    /// This will error if the path doesn't exist, is excluded by the ignore files,
+    /// or if the contents isn't UTF-8
     pub fn read_file(&self, name: &str) -> Result<String> {
-        fs::read_to_string(self.source.join(name)).map_err(|e| anyhow!(e))
+        let path = self.source.join(name);
+        if self.is_ignored(&path) {
+            bail!("{} is excluded by the ignore files", name);
+        }
+        fs::read_to_string(path).map_err(|e| anyhow!(e))
     }
 
+    /// Check if a file matching a glob pattern contains a match for the regex
     pub fn find_match(&self, re: &Regex, pattern: &str) -> Result<bool> {
-        let paths = match self.find_files(pattern) {
-            Ok(v) => v,
-            Err(_e) => return Ok(false),
-        };
-
-        for path in paths {
-            let path_buf = fs::canonicalize(path)?;
+        let full_pattern = self.source.join(pattern);
 
-            if let Some(p) = path_buf.to_str() {
-                let f = self.read_file(p)?;
-                if re.find(f.as_str()).is_some() {
-                    return Ok(true);
-                }
-            }
+        match full_pattern.to_str() {
+            Some(pattern_str) => Ok(self.index().find_match(re, pattern_str).unwrap_or(false)),
+            None => Ok(false),
         }
+    }
 
-        Ok(false)
+    /// Number of files searched by `find_files` and the time it took to index them
+    pub fn index_stats(&self) -> &FileIndexStats {
+        self.index().stats()
     }
 
-    /// Check if a directory exists
+    /// Check if a directory exists and is not excluded by the ignore files
     pub fn includes_directory(&self, name: &str) -> bool {
-        self.source.join(name).is_dir()
+        let path = self.source.join(name);
+        path.is_dir() && !self.is_ignored(&path)
     }
 
     pub fn read_json<T>(&self, name: &str) -> Result<T>
@@ -237,6 +339,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_find_files_ignored() -> Result<()> {
+        let app = App::new("./examples/node-ignore")?;
+        let files = app
+            .find_files("**/*")?
+            .iter()
+            .map(|path| app.strip_source_path(path).unwrap())
+            .collect::<Vec<_>>();
+        assert!(files.contains(&PathBuf::from("scripts/generate.js")));
+        assert!(!files.contains(&PathBuf::from(".env")));
+        assert!(!files.contains(&PathBuf::from("tmp/scratch.js")));
+        assert!(!files.contains(&PathBuf::from("debug.log")));
+        assert!(!app.paths.contains(&app.source.join("tmp")));
+        // Re-included from an ignored directory
+        assert!(files.contains(&PathBuf::from("cache/manifest.json")));
+        assert!(!files.contains(&PathBuf::from("cache/bundle.js")));
+
+        // Ignored files are not detected, like they are not in the build context
+        assert!(!app.includes_file(".env"));
+        assert!(app.read_file(".env").is_err());
+        assert!(!app.includes_directory("tmp"));
+        assert!(app.includes_file("cache/manifest.json"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_app_root() -> Result<()> {
+        let mut app = App::new("./examples/node-monorepo-root")?;
+        assert_eq!(app.root(), PathBuf::new());
+
+        app.set_root("services/api")?;
+        app.use_gitignore()?;
+        assert_eq!(app.image_dir(), "/app/services/api");
+        // Nothing was queried yet, so the files were not indexed
+        assert!(app.index.get().is_none());
+        assert_eq!(app.root(), PathBuf::from("services/api"));
+        assert!(app.includes_file("package.json"));
+        assert!(app.find_files("**/*.go")?.is_empty());
+        assert_eq!(app.context, app.source.parent().unwrap().parent().unwrap());
+
+        assert!(app.set_root("../../../node").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_find_match() -> Result<()> {
         let app = App::new("./examples/node-monorepo")?;
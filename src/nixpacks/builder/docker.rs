@@ -1,17 +1,34 @@
 use std::{
+    collections::hash_map::DefaultHasher,
+    env,
     fs::{self, File},
+    hash::{Hash, Hasher},
     io::Write,
     path::{Path, PathBuf},
     process::Command,
+    time::{Duration, SystemTime},
 };
 
 use super::Builder;
-use crate::nixpacks::{app, files, logger::Logger, nix, plan::BuildPlan, NIX_PACKS_VERSION};
+use crate::nixpacks::{
+    app::{self, App},
+    files,
+    ignore::IGNORE_FILES,
+    logger::Logger,
+    nix,
+    phase::Healthcheck,
+    plan::BuildPlan,
+    NIX_PACKS_VERSION,
+};
 use anyhow::{bail, Context, Ok, Result};
+use fs2::FileExt;
 use indoc::formatdoc;
 use tempdir::TempDir;
 use uuid::Uuid;
 
+/// Cached build contexts that were not used for longer are removed
+const CONTEXT_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
 #[derive(Clone, Default, Debug)]
 pub struct DockerBuilderOptions {
     pub name: Option<String>,
@@ -20,6 +37,8 @@ pub struct DockerBuilderOptions {
     pub labels: Vec<String>,
     pub quiet: bool,
     pub force_buildkit: bool,
+    /// Copy the app into a new temporary context instead of syncing the cached one
+    pub fresh_context: bool,
 }
 
 pub struct DockerBuilder {
@@ -28,7 +47,7 @@ pub struct DockerBuilder {
 }
 
 impl Builder for DockerBuilder {
-    fn create_image(&self, app_src: &str, plan: &BuildPlan) -> Result<()> {
+    fn create_image(&self, app: &App, plan: &BuildPlan) -> Result<()> {
         self.logger
             .log_section(format!("Building (nixpacks v{})", NIX_PACKS_VERSION).as_str());
 
@@ -36,27 +55,43 @@ impl Builder for DockerBuilder {
 
         let id = Uuid::new_v4();
 
+        // Held until the image is built, so concurrent builds don't sync the same context
+        let mut _context_lock = None;
         let dir = match &self.options.out_dir {
             Some(dir) => dir.into(),
-            None => {
+            None if self.options.fresh_context => {
                 let tmp = TempDir::new("nixpacks").context("Creating a temp directory")?;
                 tmp.into_path()
             }
+            None => {
+                let dir = get_context_dir(app);
+                _context_lock = Some(lock_context_dir(&dir)?);
+                if let Some(contexts_dir) = dir.parent() {
+                    if let Err(e) = remove_stale_contexts(contexts_dir, CONTEXT_MAX_AGE) {
+                        eprintln!("Warning: Failed to remove old build contexts: {}", e);
+                    }
+                }
+                dir
+            }
         };
         let dest = dir.to_str().context("Invalid temp directory path")?;
         let name = self.options.name.clone().unwrap_or_else(|| id.to_string());
 
         // Write everything to destination
-        self.write_app(app_src, dest).context("Writing app")?;
-        self.write_assets(plan, dest).context("Writing assets")?;
-        self.write_dockerfile(plan, dest)
-            .context("Writing Dockerfile")?;
-        self.write_nix_expression(plan, dest)
-            .context("Writing NIx expression")?;
+        self.write_app(app, dest).context("Writing app")?;
+        // The app's own Dockerfile is built from the copied sources
+        if plan.dockerfile.is_none() {
+            self.write_assets(plan, dest).context("Writing assets")?;
+            self.write_dockerfile(plan, &app.root(), dest)
+                .context("Writing Dockerfile")?;
+            self.write_nix_expression(plan, dest)
+                .context("Writing NIx expression")?;
+        }
 
         // Only build if the --out flag was not specified
         if self.options.out_dir.is_none() {
-            let mut docker_build_cmd = self.get_docker_build_cmd(plan, name.as_str(), dest);
+            let mut docker_build_cmd =
+                self.get_docker_build_cmd(plan, &app.root(), name.as_str(), dest);
 
             // Execute docker build
             let build_result = docker_build_cmd.spawn()?.wait().context("Building image")?;
@@ -83,7 +118,13 @@ impl DockerBuilder {
         DockerBuilder { logger, options }
     }
 
-    fn get_docker_build_cmd(&self, plan: &BuildPlan, name: &str, dest: &str) -> Command {
+    fn get_docker_build_cmd(
+        &self,
+        plan: &BuildPlan,
+        root: &Path,
+        name: &str,
+        dest: &str,
+    ) -> Command {
         let mut docker_build_cmd = Command::new("docker");
 
         if self.options.force_buildkit {
@@ -91,6 +132,13 @@ impl DockerBuilder {
         }
         docker_build_cmd.arg("build").arg(dest).arg("-t").arg(name);
 
+        // The app's Dockerfile is in the app root, which may be below the context
+        if let Some(dockerfile) = &plan.dockerfile {
+            docker_build_cmd
+                .arg("-f")
+                .arg(PathBuf::from(dest).join(root).join(dockerfile));
+        }
+
         if self.options.quiet {
             docker_build_cmd.arg("--quiet");
         }
@@ -113,12 +161,31 @@ impl DockerBuilder {
         docker_build_cmd
     }
 
-    fn write_app(&self, app_src: &str, dest: &str) -> Result<()> {
-        files::recursive_copy_dir(app_src, &dest)
+    fn write_app(&self, app: &App, dest: &str) -> Result<()> {
+        // Ignore files are applied here, Docker would also apply them to the generated files
+        let include = |path: &Path| {
+            let is_ignore_file = path.parent() == Some(app.context.as_path())
+                && IGNORE_FILES.iter().any(|file| path.ends_with(file));
+            !is_ignore_file && !app.is_ignored(path)
+        };
+        // Only the cached context is owned by nixpacks, files in an out dir are kept
+        let stats = if self.options.out_dir.is_some() {
+            files::copy_dir(&app.context, dest, include)?
+        } else {
+            files::sync_dir(&app.context, dest, include)?
+        };
+
+        // Generated files are removed and written again on every build, so only report app files
+        println!(
+            "Synced build context ({} copied, {} unchanged)",
+            stats.copied, stats.unchanged
+        );
+
+        Ok(())
     }
 
-    fn write_dockerfile(&self, plan: &BuildPlan, dest: &str) -> Result<()> {
-        let dockerfile = self.create_dockerfile(plan);
+    fn write_dockerfile(&self, plan: &BuildPlan, root: &Path, dest: &str) -> Result<()> {
+        let dockerfile = self.create_dockerfile(plan, root);
 
         let dockerfile_path = PathBuf::from(dest).join(PathBuf::from("Dockerfile"));
         File::create(dockerfile_path.clone()).context("Creating Dockerfile file")?;
@@ -161,8 +228,28 @@ impl DockerBuilder {
         Ok(())
     }
 
-    fn create_dockerfile(&self, plan: &BuildPlan) -> String {
-        let app_dir = "/app/";
+    /// `root` is the directory of the app in the build context
+    fn create_dockerfile(&self, plan: &BuildPlan, root: &Path) -> String {
+        let context_dir = "/app/";
+        let root = root.to_string_lossy().replace('\\', "/");
+        let app_dir = if root.is_empty() {
+            context_dir.to_string()
+        } else {
+            format!("{}{}/", context_dir, root)
+        };
+        let app_dir = app_dir.as_str();
+        // Files of the plan are relative to the app root
+        let context_files = |files: &[String]| -> Vec<String> {
+            files
+                .iter()
+                .map(|f| f.trim_start_matches("./"))
+                .map(|f| match f {
+                    "." => ".".to_string(),
+                    f if root.is_empty() => f.to_string(),
+                    f => format!("{}/{}", root, f),
+                })
+                .collect()
+        };
         let assets_dir = app::ASSETS_DIR;
 
         let setup_phase = plan.setup.clone().unwrap_or_default();
@@ -195,8 +282,8 @@ impl DockerBuilder {
 
         // -- Setup
         let mut setup_files: Vec<String> = vec!["environment.nix".to_string()];
-        if let Some(mut setup_file_deps) = setup_phase.only_include_files {
-            setup_files.append(&mut setup_file_deps);
+        if let Some(setup_file_deps) = setup_phase.only_include_files {
+            setup_files.append(&mut context_files(&setup_file_deps));
         }
         let setup_copy_cmd = format!("COPY {} {}", setup_files.join(" "), app_dir);
 
@@ -215,6 +302,12 @@ impl DockerBuilder {
             .join("\n");
 
         // -- Static Assets
+        // Static assets are also needed in a separate run image
+        let run_assets_copy_cmd = if !static_assets.is_empty() {
+            format!("COPY --from=0 {} {}", assets_dir, assets_dir)
+        } else {
+            "".to_string()
+        };
         let assets_copy_cmd = if !static_assets.is_empty() {
             static_assets
                 .into_keys()
@@ -234,14 +327,21 @@ impl DockerBuilder {
             .collect::<Vec<String>>()
             .join("\n");
 
-        let (build_path, run_path) = if let Some(paths) = install_phase.paths {
-            let joined_paths = paths.join(":");
-            (
-                format!("ENV PATH {}:$PATH", joined_paths),
-                format!("RUN printf '\\nPATH={joined_paths}:$PATH' >> /root/.profile"),
-            )
-        } else {
-            ("".to_string(), "".to_string())
+        // ENV is also set for non-root users, unlike a profile in the root home
+        // Providers put paths in /app/, which is the app root in the image
+        let path_env = match install_phase.paths {
+            Some(paths) => format!(
+                "ENV PATH {}:$PATH",
+                paths
+                    .iter()
+                    .map(|p| match p.strip_prefix(context_dir) {
+                        Some(rest) => format!("{}{}", app_dir, rest),
+                        None => p.clone(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(":")
+            ),
+            None => "".to_string(),
         };
 
         // Files to copy for install phase
@@ -270,10 +370,80 @@ impl DockerBuilder {
         });
 
         // -- Start
-        let start_cmd = start_phase
-            .cmd
-            .map(|cmd| format!("CMD {}", cmd))
-            .unwrap_or_else(|| "".to_string());
+        let start_cmd = match start_phase.cmd {
+            Some(cmd) => format!("CMD {}", cmd),
+            None => "".to_string(),
+        };
+
+        // -- Permissions
+        let mut permission_cmds = Vec::new();
+        if start_phase.read_only.unwrap_or_default() {
+            permission_cmds.push(format!("RUN chmod -R a-w {}", app_dir));
+        }
+        if let Some(user) = &start_phase.user {
+            // groupadd/useradd on Debian, addgroup/adduser on Alpine
+            permission_cmds.push(format!(
+                "RUN (groupadd --system {user} && useradd --system --gid {user} --no-create-home {user}) || (addgroup -S {user} && adduser -S -H -G {user} {user})",
+                user = user
+            ));
+        }
+        for path in start_phase.writable_paths.clone().unwrap_or_default() {
+            let path = format!("{}{}", app_dir, path.trim_start_matches("./"));
+            let owner_cmd = match &start_phase.user {
+                Some(user) => format!(
+                    "chown -R {user}:{user} {path} && chmod -R u+w {path}",
+                    user = user,
+                    path = path
+                ),
+                None => format!("chmod -R ugo+w {}", path),
+            };
+            permission_cmds.push(format!("RUN mkdir -p {} && {}", path, owner_cmd));
+        }
+        if let Some(user) = &start_phase.user {
+            permission_cmds.push(format!("USER {}", user));
+        }
+        let permissions_cmd = permission_cmds.join("\n");
+
+        let ports = start_phase.ports.clone().unwrap_or_default();
+        let expose_cmd = if ports.is_empty() {
+            "".to_string()
+        } else {
+            format!(
+                "EXPOSE {}",
+                ports
+                    .iter()
+                    .map(|port| port.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+        };
+
+        // Slim run images may have neither curl nor wget, so install curl for the HTTP check
+        let healthcheck_tool_cmd = match &start_phase.healthcheck {
+            Some(Healthcheck {
+                cmd: None,
+                path: Some(_),
+            }) => "RUN (command -v curl || command -v wget) > /dev/null || (apt-get update && apt-get install -y --no-install-recommends curl && rm -rf /var/lib/apt/lists/*) || apk add --no-cache curl".to_string(),
+            _ => "".to_string(),
+        };
+        let healthcheck_cmd = match start_phase.healthcheck.clone().unwrap_or_default() {
+            Healthcheck { cmd: Some(cmd), .. } => format!("HEALTHCHECK CMD {}", cmd),
+            Healthcheck {
+                path: Some(path), ..
+            } => {
+                let url = format!(
+                    "http://localhost:${{PORT:-{}}}{}",
+                    ports.first().unwrap_or(&80),
+                    path
+                );
+                // Slim and alpine images may only have one of curl and wget
+                format!(
+                    "HEALTHCHECK CMD curl -fs {url} > /dev/null || wget -q -O /dev/null {url} || exit 1",
+                    url = url
+                )
+            }
+            _ => "".to_string(),
+        };
 
         // If we haven't yet copied over the entire app, do that before starting
         let start_files = start_phase.only_include_files.clone();
@@ -287,16 +457,23 @@ impl DockerBuilder {
                 COPY --from=0 /etc/ssl/certs /etc/ssl/certs
                 RUN true
                 {copy_cmd}
+                {assets_copy_cmd}
+                {args_string}
+                {path_env}
             ",
                     run_image=run_image,
                     app_dir=app_dir,
-                    copy_cmd=get_copy_from_command("0", &start_files.unwrap_or_default(), app_dir)
+                    // Variables don't carry over to a new stage
+                    args_string=args_string,
+                    path_env=path_env,
+                    assets_copy_cmd=run_assets_copy_cmd,
+                    copy_cmd=get_copy_from_command("0", &start_files.unwrap_or_default(), context_dir, app_dir)
                 }
             }
             None => get_copy_command(
                 // If no files specified and no run image, copy everything in /app/ over
-                &start_files.unwrap_or_else(|| vec![".".to_string()]),
-                app_dir,
+                &context_files(&start_files.unwrap_or_else(|| vec![".".to_string()])),
+                context_dir,
             ),
         };
 
@@ -320,8 +497,7 @@ impl DockerBuilder {
           {install_copy_cmd}
           {install_cmd}
 
-          {build_path}
-          {run_path}
+          {path_env}
 
           # Build
           {build_copy_cmd}
@@ -329,27 +505,126 @@ impl DockerBuilder {
 
           # Start
           {run_image_setup}
+          {healthcheck_tool_cmd}
+          {permissions_cmd}
+          {expose_cmd}
+          {healthcheck_cmd}
           {start_cmd}
         ",
         base_image=setup_phase.base_image,
-        install_copy_cmd=get_copy_command(&install_files, app_dir),
-        build_copy_cmd=get_copy_command(&build_files, app_dir)};
+        install_copy_cmd=get_copy_command(&context_files(&install_files), context_dir),
+        build_copy_cmd=get_copy_command(&context_files(&build_files), context_dir)};
 
         dockerfile
     }
 }
 
+/// Persistent build context of an app, synced on each build
+fn get_context_dir(app: &App) -> PathBuf {
+    let cache_dir = env::var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(|_| env::temp_dir());
+
+    // Checkouts of remote sources are in a new temp directory on every build
+    let mut hasher = DefaultHasher::new();
+    match &app.remote {
+        Some(remote) => remote.hash(&mut hasher),
+        None => app.context.hash(&mut hasher),
+    }
+    let name = app
+        .context
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("app");
+
+    cache_dir
+        .join("nixpacks")
+        .join("contexts")
+        .join(format!("{}-{:016x}", name, hasher.finish()))
+}
+
+/// Locks a context dir for one build, waiting for other builds of the app to finish
+fn lock_context_dir(dir: &Path) -> Result<File> {
+    let name = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("app");
+    let lock_path = dir.with_file_name(format!("{}.lock", name));
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent).context("Creating the build context cache")?;
+    }
+    let lock = File::create(&lock_path).context("Creating the build context lock")?;
+
+    if lock.try_lock_exclusive().is_err() {
+        println!("Waiting for another build of the app to finish");
+        lock.lock_exclusive().context("Locking the build context")?;
+    }
+    // The lock's modification time is when the context was last used
+    lock.set_modified(SystemTime::now())
+        .context("Updating the build context lock")?;
+
+    Ok(lock)
+}
+
+/// Removes the cached contexts that were not used for `max_age`, skipping those in use
+fn remove_stale_contexts(contexts_dir: &Path, max_age: Duration) -> Result<()> {
+    for entry in fs::read_dir(contexts_dir)? {
+        let lock_path = entry?.path();
+        let name = match lock_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".lock"))
+        {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let modified = fs::metadata(&lock_path)?.modified()?;
+        if modified.elapsed().map_or(true, |age| age < max_age) {
+            continue;
+        }
+
+        let lock = File::open(&lock_path)?;
+        if lock.try_lock_exclusive().is_err() {
+            continue;
+        }
+        let dir = contexts_dir.join(name);
+        if dir.exists() {
+            fs::remove_dir_all(&dir).with_context(|| format!("Removing {}", dir.display()))?;
+        }
+        fs::remove_file(&lock_path)?;
+    }
+
+    Ok(())
+}
+
 fn get_copy_command(files: &[String], app_dir: &str) -> String {
     if files.is_empty() {
-        "".to_owned()
-    } else {
-        format!("COPY {} {}", files.join(" "), app_dir)
+        return "".to_owned();
+    }
+
+    // Docker flattens the sources of a COPY into the destination,
+    // so files in subdirectories are copied one by one to keep their path
+    let (nested_files, root_files): (Vec<_>, Vec<_>) = files
+        .iter()
+        .map(|f| f.trim_start_matches("./"))
+        .partition(|f| f.contains('/'));
+
+    let mut copy_cmds = Vec::new();
+    if !root_files.is_empty() {
+        copy_cmds.push(format!("COPY {} {}", root_files.join(" "), app_dir));
     }
+    for file in nested_files {
+        copy_cmds.push(format!("COPY {} {}{}", file, app_dir, file));
+    }
+
+    copy_cmds.join("\n")
 }
 
-fn get_copy_from_command(from: &str, files: &[String], app_dir: &str) -> String {
+fn get_copy_from_command(from: &str, files: &[String], context_dir: &str, app_dir: &str) -> String {
     if files.is_empty() {
-        format!("COPY --from=0 {} {}", app_dir, app_dir)
+        format!("COPY --from=0 {} {}", context_dir, context_dir)
     } else {
         format!(
             "COPY --from={} {} {}",
@@ -363,3 +638,181 @@ fn get_copy_from_command(from: &str, files: &[String], app_dir: &str) -> String
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nixpacks::phase::{InstallPhase, StartPhase};
+
+    #[test]
+    fn test_http_healthcheck_installs_curl() {
+        let mut start = StartPhase::new("./server".to_string());
+        start.run_in_image("debian:bullseye-slim".to_string());
+        start.set_healthcheck_path("/health".to_string());
+        let plan = BuildPlan {
+            version: None,
+            setup: None,
+            install: None,
+            build: None,
+            start: Some(start),
+            variables: None,
+            static_assets: None,
+            dockerfile: None,
+        };
+
+        let dockerfile = DockerBuilder::new(Logger::new(), DockerBuilderOptions::default())
+            .create_dockerfile(&plan, Path::new(""));
+        let run_stage = dockerfile
+            .split("FROM debian:bullseye-slim")
+            .nth(1)
+            .unwrap();
+        assert!(run_stage.contains("apt-get install -y --no-install-recommends curl"));
+        assert!(run_stage.contains("HEALTHCHECK CMD curl -fs http://localhost:${PORT:-80}/health"));
+    }
+
+    #[test]
+    fn test_release_cmd_not_in_start_cmd() {
+        let mut start = StartPhase::new("bundle exec rails server".to_string());
+        start.set_release_cmd("bundle exec rake db:migrate".to_string());
+        let plan = BuildPlan {
+            version: None,
+            setup: None,
+            install: None,
+            build: None,
+            start: Some(start),
+            variables: None,
+            static_assets: None,
+            dockerfile: None,
+        };
+
+        let dockerfile = DockerBuilder::new(Logger::new(), DockerBuilderOptions::default())
+            .create_dockerfile(&plan, Path::new(""));
+        assert!(dockerfile.contains("CMD bundle exec rails server"));
+        assert!(!dockerfile.contains("db:migrate"));
+    }
+
+    #[test]
+    fn test_install_paths_in_run_image() {
+        let mut install = InstallPhase::new("npm ci".to_string());
+        install.add_path("/app/node_modules/.bin".to_string());
+        let mut start = StartPhase::new("npm start".to_string());
+        start.run_in_image("node:18-slim".to_string());
+        start.run_as_user("app".to_string());
+        let plan = BuildPlan {
+            version: None,
+            setup: None,
+            install: Some(install),
+            build: None,
+            start: Some(start),
+            variables: None,
+            static_assets: None,
+            dockerfile: None,
+        };
+
+        let dockerfile = DockerBuilder::new(Logger::new(), DockerBuilderOptions::default())
+            .create_dockerfile(&plan, Path::new(""));
+        let run_stage = dockerfile.split("FROM node:18-slim").nth(1).unwrap();
+        assert!(run_stage.contains("ENV PATH /app/node_modules/.bin:$PATH"));
+        assert!(!dockerfile.contains("/root/.profile"));
+    }
+
+    #[test]
+    fn test_install_paths_in_app_root() {
+        let mut install = InstallPhase::new("npm ci".to_string());
+        install.add_path("/app/node_modules/.bin".to_string());
+        install.add_path("/root/.cargo/bin".to_string());
+        let plan = BuildPlan {
+            version: None,
+            setup: None,
+            install: Some(install),
+            build: None,
+            start: None,
+            variables: None,
+            static_assets: None,
+            dockerfile: None,
+        };
+
+        let dockerfile = DockerBuilder::new(Logger::new(), DockerBuilderOptions::default())
+            .create_dockerfile(&plan, Path::new("services/api"));
+        assert!(dockerfile
+            .contains("ENV PATH /app/services/api/node_modules/.bin:/root/.cargo/bin:$PATH"));
+    }
+
+    #[test]
+    fn test_dockerfile_in_app_root() {
+        let plan = BuildPlan {
+            version: None,
+            setup: None,
+            install: None,
+            build: None,
+            start: None,
+            variables: None,
+            static_assets: None,
+            dockerfile: Some("Dockerfile".to_string()),
+        };
+
+        let cmd = DockerBuilder::new(Logger::new(), DockerBuilderOptions::default())
+            .get_docker_build_cmd(&plan, Path::new("services/api"), "api", "/tmp/context");
+        let args = cmd.get_args().collect::<Vec<_>>();
+        assert!(args
+            .windows(2)
+            .any(|w| w[0] == "-f" && w[1] == "/tmp/context/services/api/Dockerfile"));
+    }
+
+    #[test]
+    fn test_remove_stale_contexts() -> Result<()> {
+        let contexts = TempDir::new("nixpacks-contexts")?;
+        for name in ["old-0000000000000001", "new-0000000000000002"] {
+            let dir = contexts.path().join(name);
+            fs::create_dir(&dir)?;
+            fs::write(dir.join("package.json"), "{}")?;
+            lock_context_dir(&dir)?;
+        }
+        File::open(contexts.path().join("old-0000000000000001.lock"))?
+            .set_modified(SystemTime::now() - Duration::from_secs(60 * 60))?;
+
+        remove_stale_contexts(contexts.path(), Duration::from_secs(60))?;
+
+        assert!(!contexts.path().join("old-0000000000000001").exists());
+        assert!(!contexts.path().join("old-0000000000000001.lock").exists());
+        assert!(contexts
+            .path()
+            .join("new-0000000000000002/package.json")
+            .is_file());
+        assert!(contexts.path().join("new-0000000000000002.lock").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_out_dir_keeps_existing_files() -> Result<()> {
+        let out = TempDir::new("nixpacks-out")?;
+        fs::write(out.path().join("notes.txt"), "keep")?;
+
+        let app = App::new("./examples/node")?;
+        let plan = BuildPlan {
+            version: None,
+            setup: None,
+            install: None,
+            build: None,
+            start: Some(StartPhase::new("npm start".to_string())),
+            variables: None,
+            static_assets: None,
+            dockerfile: None,
+        };
+        let builder = DockerBuilder::new(
+            Logger::new(),
+            DockerBuilderOptions {
+                out_dir: Some(out.path().to_str().unwrap().to_string()),
+                ..Default::default()
+            },
+        );
+        builder.create_image(&app, &plan)?;
+
+        assert!(out.path().join("package.json").is_file());
+        assert!(out.path().join("Dockerfile").is_file());
+        assert_eq!(fs::read_to_string(out.path().join("notes.txt"))?, "keep");
+
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
-use super::plan::BuildPlan;
+use super::{app::App, plan::BuildPlan};
 use anyhow::Result;
 
 pub mod docker;
 
 pub trait Builder {
-    fn create_image(&self, app_source: &str, plan: &BuildPlan) -> Result<()>;
+    fn create_image(&self, app: &App, plan: &BuildPlan) -> Result<()>;
 }
@@ -1,38 +1,105 @@
-use anyhow::{bail, Result};
-use std::{collections::HashMap, env};
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+};
 
 pub type EnvironmentVariables = HashMap<String, String>;
 
+/// A variable as it is defined by the user, e.g. with `--env` or in an env file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableDefinition {
+    pub name: String,
+    pub value: String,
+    /// The value is used as is, without replacing `${NAME}` references
+    pub literal: bool,
+}
+
+impl VariableDefinition {
+    pub fn new(name: &str, value: &str) -> VariableDefinition {
+        VariableDefinition {
+            name: name.to_string(),
+            value: value.to_string(),
+            literal: false,
+        }
+    }
+
+    pub fn literal(name: &str, value: &str) -> VariableDefinition {
+        VariableDefinition {
+            literal: true,
+            ..VariableDefinition::new(name, value)
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct Environment {
     variables: EnvironmentVariables,
+    /// The variables as they were defined, before references were replaced
+    definitions: Vec<VariableDefinition>,
 }
 
 impl Environment {
     pub fn new(variables: EnvironmentVariables) -> Environment {
-        Environment { variables }
+        let definitions = variables
+            .iter()
+            .map(|(name, value)| VariableDefinition::literal(name, value))
+            .collect();
+        Environment {
+            variables,
+            definitions,
+        }
     }
 
     pub fn from_envs(envs: Vec<&str>) -> Result<Environment> {
-        let mut environment = Environment::default();
+        Environment::from_sources(&[], envs)
+    }
+
+    /// Variables from env files, overridden by `NAME=value` strings
+    ///
+    /// A `NAME` without a value is pulled from the current environment.
+    pub fn from_sources(env_files: &[String], envs: Vec<&str>) -> Result<Environment> {
+        let mut definitions = Vec::new();
+        for path in env_files {
+            let contents =
+                fs::read_to_string(path).with_context(|| format!("Reading env file {}", path))?;
+            definitions.append(
+                &mut parse_env_file(&contents)
+                    .with_context(|| format!("Parsing env file {}", path))?,
+            );
+        }
+
         for env in envs {
-            let v: Vec<&str> = env.split('=').collect();
-            if v.len() == 1 {
-                // Pull the variable from the current environment
-                let name = v[0];
-                if let Ok(value) = env::var(name) {
-                    // Variable is set
-                    environment.set_variable(name.to_string(), value);
+            match env.split_once('=') {
+                Some((name, value)) => {
+                    if name.is_empty() {
+                        bail!("Unable to parse variable string {}", env);
+                    }
+                    definitions.push(VariableDefinition::new(name, value));
+                }
+                None => {
+                    // Pull the variable from the current environment
+                    if let Ok(value) = env::var(env) {
+                        definitions.push(VariableDefinition::literal(env, &value));
+                    }
                 }
-            } else if v.len() > 2 {
-                bail!("Unable to parse variable string");
-            } else {
-                // Use provided name, value pair
-                environment.set_variable(v[0].to_string(), v[1].to_string());
             }
         }
 
-        Ok(environment)
+        Ok(Environment::from_definitions(&definitions))
+    }
+
+    pub fn from_definitions(definitions: &[VariableDefinition]) -> Environment {
+        Environment {
+            variables: merge_variables(definitions),
+            definitions: definitions.to_vec(),
+        }
+    }
+
+    /// Definitions of the variables as they were given, to merge them with other
+    /// variables without replacing references twice
+    pub fn get_definitions(&self) -> Vec<VariableDefinition> {
+        self.definitions.clone()
     }
 
     pub fn get_variable(&self, name: &str) -> Option<&String> {
@@ -52,6 +119,8 @@ impl Environment {
     }
 
     pub fn set_variable(&mut self, name: String, value: String) {
+        self.definitions
+            .push(VariableDefinition::literal(&name, &value));
         self.variables.insert(name, value);
     }
 
@@ -64,9 +133,158 @@ impl Environment {
     }
 }
 
+/// Merges variable definitions, later definitions taking precedence, and replaces
+/// `${NAME}` references in their values
+///
+/// A reference resolves to the value the variable ends up with, except a reference
+/// of a variable to itself, which resolves to the value it overrides. References
+/// to unknown variables are kept as is and `$${NAME}` is a literal `${NAME}`.
+pub fn merge_variables(definitions: &[VariableDefinition]) -> EnvironmentVariables {
+    let mut resolver = Resolver {
+        definitions,
+        resolving: HashSet::new(),
+    };
+
+    let mut variables = EnvironmentVariables::new();
+    for (index, definition) in definitions.iter().enumerate() {
+        let value = resolver.resolve(index);
+        variables.insert(definition.name.clone(), value);
+    }
+    variables
+}
+
+struct Resolver<'a> {
+    definitions: &'a [VariableDefinition],
+    /// Definitions being resolved, to stop at reference cycles
+    resolving: HashSet<usize>,
+}
+
+impl<'a> Resolver<'a> {
+    fn resolve(&mut self, index: usize) -> String {
+        let definition = &self.definitions[index];
+        if definition.literal || !self.resolving.insert(index) {
+            return definition.value.clone();
+        }
+
+        let mut value = String::new();
+        let mut rest = definition.value.as_str();
+        while let Some(start) = rest.find("${") {
+            // An escaped reference is kept without the escape
+            if rest[..start].ends_with('$') {
+                value.push_str(&rest[..start - 1]);
+                value.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
+            };
+            let name = &rest[start + 2..end];
+            value.push_str(&rest[..start]);
+
+            let referenced = if name == definition.name {
+                self.find_definition(name, index)
+            } else {
+                self.find_definition(name, self.definitions.len())
+            };
+            match referenced {
+                Some(referenced) => value.push_str(&self.resolve(referenced)),
+                None => value.push_str(&rest[start..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        value.push_str(rest);
+
+        self.resolving.remove(&index);
+        value
+    }
+
+    /// The last definition of a variable before `before`
+    fn find_definition(&self, name: &str, before: usize) -> Option<usize> {
+        self.definitions[..before]
+            .iter()
+            .rposition(|definition| definition.name == name)
+    }
+}
+
+/// Parses a dotenv file of `NAME=value` lines
+///
+/// Values can be single quoted to use them as is, or double quoted to use
+/// escapes like `\n` and span multiple lines. Lines starting with `#` and
+/// ` #` after unquoted values are comments.
+pub fn parse_env_file(contents: &str) -> Result<Vec<VariableDefinition>> {
+    let mut definitions = Vec::new();
+    let mut lines = contents.lines().enumerate();
+
+    while let Some((number, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = match line.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => (name.trim(), value.trim()),
+            _ => bail!("Invalid variable on line {}: {}", number + 1, line),
+        };
+
+        if let Some(value) = value.strip_prefix('\'') {
+            match value.split_once('\'') {
+                Some((value, _)) => definitions.push(VariableDefinition::literal(name, value)),
+                None => bail!("Unterminated quote on line {}", number + 1),
+            }
+        } else if let Some(value) = value.strip_prefix('"') {
+            let mut quoted = value.to_string();
+            let value = loop {
+                if let Some(value) = parse_double_quoted(&quoted) {
+                    break value;
+                }
+                match lines.next() {
+                    Some((_, line)) => {
+                        quoted.push('\n');
+                        quoted.push_str(line);
+                    }
+                    None => bail!("Unterminated quote on line {}", number + 1),
+                }
+            };
+            definitions.push(VariableDefinition::new(name, &value));
+        } else {
+            let value = match value.find(" #") {
+                Some(comment) => value[..comment].trim_end(),
+                None => value,
+            };
+            definitions.push(VariableDefinition::new(name, value));
+        }
+    }
+
+    Ok(definitions)
+}
+
+/// The value up to the closing quote, or `None` if it is not closed yet
+fn parse_double_quoted(quoted: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c) => value.push(c),
+                None => value.push('\\'),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Environment;
+    use super::*;
+    use indoc::indoc;
 
     #[test]
     fn set_and_get_variables() {
@@ -94,8 +312,91 @@ mod tests {
         assert!(environment.get_variable("NON_EXISTANT").is_none());
     }
 
+    #[test]
+    fn test_values_with_equals_signs() {
+        let environment =
+            Environment::from_envs(vec!["SECRET=c2VjcmV0==", "DATABASE_URL=db?sslmode=require"])
+                .unwrap();
+        assert_eq!(
+            environment.get_variable("SECRET"),
+            Some(&"c2VjcmV0==".to_string())
+        );
+        assert_eq!(
+            environment.get_variable("DATABASE_URL"),
+            Some(&"db?sslmode=require".to_string())
+        );
+    }
+
     #[test]
     fn test_create_invalid_environment() {
-        assert!(Environment::from_envs(vec!["INVALID=ENV=CONFIG"]).is_err());
+        assert!(Environment::from_envs(vec!["=CONFIG"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let definitions = parse_env_file(indoc! {r#"
+            # Database
+            export HOST=localhost # inline comment
+            URL="postgres://${HOST}:5432"
+            LITERAL='${HOST} #1'
+            MULTILINE="first
+            second\tthird"
+        "#})
+        .unwrap();
+        assert_eq!(
+            definitions,
+            vec![
+                VariableDefinition::new("HOST", "localhost"),
+                VariableDefinition::new("URL", "postgres://${HOST}:5432"),
+                VariableDefinition::literal("LITERAL", "${HOST} #1"),
+                VariableDefinition::new("MULTILINE", "first\nsecond\tthird"),
+            ]
+        );
+
+        assert!(parse_env_file("NO_VALUE").is_err());
+        assert!(parse_env_file("OPEN=\"never closed").is_err());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let variables = merge_variables(&[
+            VariableDefinition::new("HOST", "localhost"),
+            VariableDefinition::new("URL", "postgres://${HOST}/${NAME}"),
+            VariableDefinition::literal("LITERAL", "${HOST}"),
+            VariableDefinition::new("ESCAPED", "$${HOST} ${HOST}"),
+            VariableDefinition::new("CYCLE", "${CYCLE_B}"),
+            VariableDefinition::new("CYCLE_B", "${CYCLE}"),
+            VariableDefinition::new("PATH", "/bin"),
+            VariableDefinition::new("PATH", "/app/bin:${PATH}"),
+            // Overrides are used by earlier references
+            VariableDefinition::new("HOST", "db"),
+        ]);
+        assert_eq!(variables["URL"], "postgres://db/${NAME}");
+        assert_eq!(variables["LITERAL"], "${HOST}");
+        assert_eq!(variables["ESCAPED"], "${HOST} db");
+        // Cycles stop at the first repeated variable
+        assert_eq!(variables["CYCLE"], "${CYCLE_B}");
+        assert_eq!(variables["PATH"], "/app/bin:/bin");
+    }
+
+    #[test]
+    fn test_definitions_are_interpolated_once() {
+        let environment =
+            Environment::from_envs(vec!["HOST=db", "URL=$${HOST}", "PATH=/app/bin:${PATH}"])
+                .unwrap();
+        assert_eq!(
+            environment.get_variable("URL"),
+            Some(&"${HOST}".to_string())
+        );
+
+        // Merged again with provider defaults, as the plan generator does
+        let variables = merge_variables(
+            &[VariableDefinition::literal("PATH", "/bin")]
+                .into_iter()
+                .chain(environment.get_definitions())
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(variables["URL"], "${HOST}");
+        assert_eq!(variables["PATH"], "/app/bin:/bin");
     }
 }
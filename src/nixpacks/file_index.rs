@@ -0,0 +1,189 @@
+use anyhow::Result;
+use globset::Glob;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use walkdir::WalkDir;
+
+/// Directories that never contain files used for detection
+pub const SKIPPED_DIRS: &[&str] = &["node_modules", ".git"];
+
+/// Files indexed before the rest of the tree is skipped
+pub const MAX_INDEXED_FILES: usize = 100_000;
+
+/// Larger files are not searched for content matches
+pub const MAX_SEARCHED_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileIndexStats {
+    pub files: usize,
+    /// The tree had more than `MAX_INDEXED_FILES` files
+    pub truncated: bool,
+    pub index_time: Duration,
+}
+
+/// All files of an app, walked once and shared by every glob and content query
+///
+/// Query results and file contents are cached, so providers can check the same
+/// patterns during detection without walking or reading the tree again.
+#[derive(Debug, Clone, Default)]
+pub struct FileIndex {
+    files: Vec<PathBuf>,
+    stats: FileIndexStats,
+    matches: Arc<Mutex<HashMap<String, Arc<Vec<PathBuf>>>>>,
+    contents: Arc<Mutex<HashMap<PathBuf, Option<Arc<String>>>>>,
+}
+
+impl FileIndex {
+    pub fn new<F>(source: &Path, is_ignored: F) -> FileIndex
+    where
+        F: Fn(&Path) -> bool,
+    {
+        let start = Instant::now();
+        let mut files = Vec::new();
+        let mut truncated = false;
+
+        let walker = WalkDir::new(source)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                let skipped_dir = entry.file_type().is_dir()
+                    && entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| SKIPPED_DIRS.contains(&name));
+                !skipped_dir && !is_ignored(entry.path())
+            })
+            .filter_map(|entry| entry.ok());
+
+        for entry in walker {
+            // Symlinks to files are included, like they are in the build context
+            if !entry.path().is_file() {
+                continue;
+            }
+
+            if files.len() == MAX_INDEXED_FILES {
+                eprintln!(
+                    "Warning: The app has more than {} files, only the first were checked",
+                    MAX_INDEXED_FILES
+                );
+                truncated = true;
+                break;
+            }
+
+            files.push(entry.into_path());
+        }
+
+        FileIndex {
+            stats: FileIndexStats {
+                files: files.len(),
+                truncated,
+                index_time: start.elapsed(),
+            },
+            files,
+            ..Default::default()
+        }
+    }
+
+    pub fn stats(&self) -> &FileIndexStats {
+        &self.stats
+    }
+
+    /// Absolute paths of the files matching an absolute glob pattern
+    pub fn find_files(&self, pattern: &str) -> Result<Vec<PathBuf>> {
+        if let Some(paths) = self.matches.lock().unwrap().get(pattern) {
+            return Ok(paths.to_vec());
+        }
+
+        let glob = Glob::new(pattern)?.compile_matcher();
+        let paths = self
+            .files
+            .iter()
+            .filter(|path| glob.is_match(path))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.matches
+            .lock()
+            .unwrap()
+            .insert(pattern.to_string(), Arc::new(paths.clone()));
+
+        Ok(paths)
+    }
+
+    /// Whether any file matching an absolute glob pattern contains a match for `re`
+    ///
+    /// Files that are too large or not UTF-8 are skipped.
+    pub fn find_match(&self, re: &Regex, pattern: &str) -> Result<bool> {
+        for path in self.find_files(pattern)? {
+            if let Some(contents) = self.read_file(&path) {
+                if re.is_match(&contents) {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn read_file(&self, path: &Path) -> Option<Arc<String>> {
+        if let Some(contents) = self.contents.lock().unwrap().get(path) {
+            return contents.clone();
+        }
+
+        let contents = match fs::metadata(path) {
+            Ok(metadata) if metadata.len() <= MAX_SEARCHED_FILE_SIZE => {
+                fs::read_to_string(path).ok().map(Arc::new)
+            }
+            _ => None,
+        };
+
+        self.contents
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), contents.clone());
+
+        contents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_skips_dependency_directories() -> Result<()> {
+        let source = env::current_dir()?.join("examples/node-monorepo");
+        let index = FileIndex::new(&source, |_| false);
+        let pattern = source.join("**/*").to_str().unwrap().to_string();
+
+        let files = index.find_files(&pattern)?;
+        assert!(!files.is_empty());
+        assert!(files.iter().all(|path| !path
+            .components()
+            .any(|c| c.as_os_str() == "node_modules" || c.as_os_str() == ".git")));
+        assert_eq!(index.stats().files, files.len());
+        assert!(!index.stats().truncated);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_matches() -> Result<()> {
+        let source = env::current_dir()?.join("examples/node-monorepo");
+        let index = FileIndex::new(&source, |_| false);
+        let pattern = source.join("**/*.tsx").to_str().unwrap().to_string();
+
+        assert_eq!(index.find_files(&pattern)?, index.find_files(&pattern)?);
+        assert!(index.find_match(&Regex::new("className")?, &pattern)?);
+        assert!(!index.find_match(&Regex::new("not in the app")?, &pattern)?);
+
+        Ok(())
+    }
+}
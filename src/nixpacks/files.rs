@@ -1,28 +1,302 @@
-use anyhow::{Ok, Result};
-use std::{fs, io, path::Path};
+use anyhow::{Context, Ok, Result};
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    fs::{self, File, Metadata},
+    hash::Hasher,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
 use walkdir::WalkDir;
 
-pub fn recursive_copy_dir<T: AsRef<Path>, Q: AsRef<Path>>(source: T, dest: Q) -> Result<()> {
-    let walker = WalkDir::new(&source).follow_links(true);
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyncStats {
+    pub copied: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Makes `dest` a copy of the `source` directory, skipping the paths (and the
+/// contents of directories) `include` rejects
+///
+/// Only files whose contents changed are copied and files no longer in the
+/// source are removed, so the same destination can be synced on every build.
+/// Files with the same size and modification time are not read again.
+/// Symlinks are copied as links and permissions and modification times are preserved.
+pub fn sync_dir<T, Q, F>(source: T, dest: Q, include: F) -> Result<SyncStats>
+where
+    T: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+{
+    copy_changed(source.as_ref(), dest.as_ref(), include, true)
+}
+
+/// Copies the `source` directory into `dest` like `sync_dir`, but keeps the
+/// files that are only in `dest`
+pub fn copy_dir<T, Q, F>(source: T, dest: Q, include: F) -> Result<SyncStats>
+where
+    T: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+{
+    copy_changed(source.as_ref(), dest.as_ref(), include, false)
+}
+
+fn copy_changed<F>(source: &Path, dest: &Path, include: F, remove_stale: bool) -> Result<SyncStats>
+where
+    F: Fn(&Path) -> bool,
+{
+    fs::create_dir_all(dest).context("Creating destination directory")?;
+
+    let mut stats = SyncStats::default();
+    let mut synced = HashSet::new();
+
+    let walker = WalkDir::new(source)
+        .follow_links(false)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| include(entry.path()));
     for entry in walker {
         let entry = entry?;
-
+        let relative = entry.path().strip_prefix(source)?.to_path_buf();
         let from = entry.path();
-        let to = dest.as_ref().join(from.strip_prefix(&source)?);
+        let to = dest.join(&relative);
+        let metadata = entry.metadata()?;
 
-        // create directories
         if entry.file_type().is_dir() {
-            if let Err(e) = fs::create_dir(to) {
-                match e.kind() {
-                    io::ErrorKind::AlreadyExists => {}
-                    _ => return Err(e.into()),
+            if !fs::symlink_metadata(&to).is_ok_and(|m| m.is_dir()) {
+                remove_path(&to)?;
+                fs::create_dir(&to)?;
+            }
+            fs::set_permissions(&to, metadata.permissions())?;
+        } else if entry.file_type().is_symlink() {
+            let target = fs::read_link(from)?;
+            if fs::read_link(&to).ok().as_ref() == Some(&target) {
+                stats.unchanged += 1;
+            } else {
+                remove_path(&to)?;
+                create_symlink(&target, &to)
+                    .with_context(|| format!("Creating symlink {}", to.display()))?;
+                stats.copied += 1;
+            }
+        } else if entry.file_type().is_file() {
+            if is_same_file(from, &to, &metadata)? {
+                if fs::metadata(&to)?.permissions() != metadata.permissions() {
+                    fs::set_permissions(&to, metadata.permissions())?;
                 }
+                stats.unchanged += 1;
+            } else {
+                remove_path(&to)?;
+                // Also copies the permissions
+                fs::copy(from, &to).with_context(|| format!("Copying {}", from.display()))?;
+                stats.copied += 1;
             }
+            set_modified(&to, &metadata)?;
         }
-        // copy files
-        else if entry.file_type().is_file() {
-            fs::copy(from, to)?;
+
+        synced.insert(relative);
+    }
+
+    if !remove_stale {
+        return Ok(stats);
+    }
+
+    // Remove what is no longer in the source, contents before their directories
+    let stale = WalkDir::new(dest)
+        .follow_links(false)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .path()
+                .strip_prefix(dest)
+                .is_ok_and(|relative| !synced.contains(relative))
+        })
+        .map(|entry| (entry.path().to_path_buf(), entry.file_type().is_dir()))
+        .collect::<Vec<(PathBuf, bool)>>();
+    for (path, is_dir) in stale {
+        remove_path(&path)?;
+        if !is_dir {
+            stats.removed += 1;
+        }
+    }
+
+    Ok(stats)
+}
+
+fn is_same_file(from: &Path, to: &Path, from_metadata: &Metadata) -> Result<bool> {
+    match fs::symlink_metadata(to) {
+        io::Result::Ok(metadata) if metadata.is_file() && metadata.len() == from_metadata.len() => {
+            // Copies get the modification time of their source
+            if metadata
+                .modified()
+                .ok()
+                .is_some_and(|modified| from_metadata.modified().ok() == Some(modified))
+            {
+                return Ok(true);
+            }
+            Ok(hash_file(from)? == hash_file(to)?)
         }
+        _ => Ok(false),
+    }
+}
+
+/// Gives a copied file the modification time of its source, so it isn't hashed on the next sync
+fn set_modified(path: &Path, from_metadata: &Metadata) -> Result<()> {
+    let modified = match from_metadata.modified() {
+        io::Result::Ok(modified) => modified,
+        // Not supported on this platform, the contents are compared instead
+        Err(_) => return Ok(()),
+    };
+    let file = File::open(path)?;
+    if file.metadata()?.modified().ok() != Some(modified) {
+        // Read-only files can't always be changed, they are hashed on the next sync
+        file.set_modified(modified).ok();
     }
     Ok(())
 }
+
+fn hash_file(path: &Path) -> Result<u64> {
+    let mut file = File::open(path).with_context(|| format!("Reading {}", path.display()))?;
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
+    Ok(hasher.finish())
+}
+
+/// Removes a file, symlink or directory if it exists
+fn remove_path(path: &Path) -> Result<()> {
+    match fs::symlink_metadata(path) {
+        io::Result::Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path)?,
+        io::Result::Ok(_) => fs::remove_file(path)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let resolved = link.parent().unwrap_or(link).join(target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_sync_dir() -> Result<()> {
+        let source = TempDir::new("nixpacks-source")?;
+        let dest = TempDir::new("nixpacks-dest")?;
+        fs::create_dir(source.path().join("src"))?;
+        fs::write(source.path().join("src/main.rs"), "fn main() {}")?;
+        fs::write(source.path().join("secret.env"), "SECRET=1")?;
+        fs::write(dest.path().join("stale.txt"), "stale")?;
+
+        let stats = sync_dir(source.path(), dest.path(), |path| {
+            !path.ends_with("secret.env")
+        })?;
+        assert_eq!(
+            stats,
+            SyncStats {
+                copied: 1,
+                unchanged: 0,
+                removed: 1
+            }
+        );
+        assert!(dest.path().join("src/main.rs").is_file());
+        assert!(!dest.path().join("secret.env").exists());
+        assert!(!dest.path().join("stale.txt").exists());
+
+        // Nothing changed
+        let stats = sync_dir(source.path(), dest.path(), |_| true)?;
+        assert_eq!(stats.copied, 1);
+        assert_eq!(stats.unchanged, 1);
+
+        // Same size and modification time, the contents are not compared
+        let modified = fs::metadata(source.path().join("src/main.rs"))?.modified()?;
+        assert_eq!(
+            fs::metadata(dest.path().join("src/main.rs"))?.modified()?,
+            modified
+        );
+
+        fs::write(source.path().join("src/main.rs"), "fn main() { }")?;
+        let stats = sync_dir(source.path(), dest.path(), |_| true)?;
+        assert_eq!(stats.copied, 1);
+        assert_eq!(
+            fs::read_to_string(dest.path().join("src/main.rs"))?,
+            "fn main() { }"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_dir_keeps_existing_files() -> Result<()> {
+        let source = TempDir::new("nixpacks-source")?;
+        let dest = TempDir::new("nixpacks-dest")?;
+        fs::write(source.path().join("index.js"), "console.log()")?;
+        fs::create_dir(dest.path().join("notes"))?;
+        fs::write(dest.path().join("notes/todo.txt"), "keep")?;
+
+        let stats = copy_dir(source.path(), dest.path(), |_| true)?;
+        assert_eq!(stats.copied, 1);
+        assert_eq!(stats.removed, 0);
+        assert!(dest.path().join("index.js").is_file());
+        assert_eq!(
+            fs::read_to_string(dest.path().join("notes/todo.txt"))?,
+            "keep"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sync_dir_preserves_symlinks_and_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let source = TempDir::new("nixpacks-source")?;
+        let dest = TempDir::new("nixpacks-dest")?;
+        fs::write(source.path().join("start.sh"), "echo hello")?;
+        fs::set_permissions(
+            source.path().join("start.sh"),
+            fs::Permissions::from_mode(0o755),
+        )?;
+        std::os::unix::fs::symlink("start.sh", source.path().join("run.sh"))?;
+
+        sync_dir(source.path(), dest.path(), |_| true)?;
+        assert_eq!(
+            fs::read_link(dest.path().join("run.sh"))?,
+            PathBuf::from("start.sh")
+        );
+        assert_eq!(
+            fs::metadata(dest.path().join("start.sh"))?
+                .permissions()
+                .mode()
+                & 0o777,
+            0o755
+        );
+
+        Ok(())
+    }
+}
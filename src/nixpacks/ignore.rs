@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Ignore files read from the app root, later files take precedence
+pub const IGNORE_FILES: &[&str] = &[".dockerignore", ".nixpacksignore"];
+
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    pattern: String,
+    matcher: GlobMatcher,
+    negated: bool,
+}
+
+/// Excludes app files with `.dockerignore` patterns
+///
+/// Patterns are relative to the app root and excluding a directory excludes
+/// its contents. The last matching pattern wins, so `!` re-includes files.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreMatcher {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreMatcher {
+    /// Patterns from the ignore files in the app root, and optionally `.gitignore`
+    pub fn from_source(source: &Path, use_gitignore: bool) -> Result<IgnoreMatcher> {
+        let mut matcher = IgnoreMatcher::default();
+
+        if use_gitignore {
+            let path = source.join(".gitignore");
+            if path.is_file() {
+                let contents = fs::read_to_string(&path).context("Reading .gitignore")?;
+                for line in contents.lines() {
+                    if let Some(pattern) = gitignore_to_dockerignore(line) {
+                        matcher.add_pattern(&pattern)?;
+                    }
+                }
+            }
+        }
+
+        for file in IGNORE_FILES {
+            let path = source.join(file);
+            if path.is_file() {
+                let contents =
+                    fs::read_to_string(&path).with_context(|| format!("Reading {}", file))?;
+                for line in contents.lines() {
+                    matcher.add_pattern(line)?;
+                }
+            }
+        }
+
+        Ok(matcher)
+    }
+
+    pub fn add_pattern(&mut self, line: &str) -> Result<()> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern.trim()),
+            None => (false, line),
+        };
+        let pattern = pattern
+            .trim_start_matches("./")
+            .trim_start_matches('/')
+            .trim_end_matches('/');
+        if pattern.is_empty() {
+            return Ok(());
+        }
+
+        let matcher = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid ignore pattern {}", line))?
+            .compile_matcher();
+        self.patterns.push(IgnorePattern {
+            pattern: pattern.to_string(),
+            matcher,
+            negated,
+        });
+
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether a path relative to the app root is excluded
+    pub fn is_ignored<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        if self.patterns.is_empty() || path.as_os_str().is_empty() {
+            return false;
+        }
+
+        // The path itself and the directories containing it
+        let candidates = path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+
+        let mut ignored = false;
+        for pattern in &self.patterns {
+            if candidates.iter().any(|p| pattern.matcher.is_match(p)) {
+                ignored = !pattern.negated;
+            }
+        }
+        ignored
+    }
+
+    /// Whether a directory relative to the app root is excluded with everything in it
+    ///
+    /// An ignored directory still has to be walked when a `!` pattern may
+    /// re-include something in it, like `!dir/keep` after `dir`.
+    pub fn is_ignored_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        if !self.is_ignored(path) {
+            return false;
+        }
+
+        let dir = format!("{}/", path.to_string_lossy().replace('\\', "/"));
+        !self
+            .patterns
+            .iter()
+            .filter(|pattern| pattern.negated)
+            .any(|pattern| {
+                // Only the part before the first wildcard can rule out a directory
+                let prefix = match pattern.pattern.find(['*', '?', '[', '{']) {
+                    Some(index) => &pattern.pattern[..index],
+                    None => pattern.pattern.as_str(),
+                };
+                prefix.starts_with(&dir) || dir.starts_with(prefix)
+            })
+    }
+}
+
+/// `.gitignore` patterns without a slash match at any depth, unlike `.dockerignore`
+fn gitignore_to_dockerignore(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (prefix, pattern) = match line.strip_prefix('!') {
+        Some(pattern) => ("!", pattern),
+        None => ("", line),
+    };
+
+    if pattern.trim_end_matches('/').contains('/') {
+        Some(format!("{}{}", prefix, pattern))
+    } else {
+        Some(format!("{}**/{}", prefix, pattern))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&str]) -> IgnoreMatcher {
+        let mut matcher = IgnoreMatcher::default();
+        for pattern in patterns {
+            matcher.add_pattern(pattern).unwrap();
+        }
+        matcher
+    }
+
+    #[test]
+    fn test_directories_and_contents() {
+        let matcher = matcher(&["node_modules", "/target/", "*.log"]);
+        assert!(matcher.is_ignored("node_modules"));
+        assert!(matcher.is_ignored("node_modules/react/index.js"));
+        assert!(matcher.is_ignored("target/release/app"));
+        assert!(matcher.is_ignored("debug.log"));
+        // Patterns are anchored to the root
+        assert!(!matcher.is_ignored("packages/web/node_modules"));
+        assert!(!matcher.is_ignored("logs/debug.log"));
+        assert!(!matcher.is_ignored("src/main.rs"));
+    }
+
+    #[test]
+    fn test_negation() {
+        let matcher = matcher(&["*.env", "!production.env", "**/secret*"]);
+        assert!(matcher.is_ignored(".env"));
+        assert!(matcher.is_ignored("local.env"));
+        assert!(!matcher.is_ignored("production.env"));
+        assert!(matcher.is_ignored("config/secrets.yml"));
+    }
+
+    #[test]
+    fn test_negation_in_ignored_dir() {
+        let ignore = matcher(&["cache", "!cache/manifest.json", "tmp"]);
+        assert!(!ignore.is_ignored("cache/manifest.json"));
+        assert!(ignore.is_ignored("cache/bundle.js"));
+        // Walked to find the re-included file
+        assert!(!ignore.is_ignored_dir("cache"));
+        assert!(ignore.is_ignored_dir("tmp"));
+
+        let ignore = matcher(&["vendor", "!**/LICENSE"]);
+        assert!(!ignore.is_ignored_dir("vendor"));
+    }
+
+    #[test]
+    fn test_gitignore_patterns() {
+        let mut matcher = IgnoreMatcher::default();
+        for line in ["node_modules/", "/dist", "# comment", "!keep.log"] {
+            if let Some(pattern) = gitignore_to_dockerignore(line) {
+                matcher.add_pattern(&pattern).unwrap();
+            }
+        }
+        assert!(matcher.is_ignored("packages/web/node_modules/react"));
+        assert!(matcher.is_ignored("dist/index.js"));
+        assert!(!matcher.is_ignored("packages/web/dist"));
+    }
+}
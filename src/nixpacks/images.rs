@@ -1,3 +1,8 @@
 pub static DEFAULT_BASE_IMAGE: &str = "ghcr.io/railwayapp/nixpacks:debian-1655126806";
 
 pub static DEBIAN_SLIM_IMAGE: &str = "debian:bullseye-slim";
+
+pub static NGINX_IMAGE: &str = "nginx:stable-alpine";
+
+/// Unprivileged user created in images that don't run as root
+pub static APP_USER: &str = "app";
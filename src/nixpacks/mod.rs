@@ -1,12 +1,17 @@
 pub mod app;
 pub mod builder;
 pub mod environment;
+pub mod file_index;
 mod files;
+pub mod ignore;
 pub mod images;
 pub mod logger;
 pub mod nix;
 pub mod phase;
 pub mod plan;
+pub mod runner;
+pub mod shell;
+pub mod source;
 #[macro_use]
 pub mod static_assets;
 
@@ -15,14 +15,7 @@ pub fn create_nix_expression(plan: &BuildPlan) -> String {
 
     let libraries = setup_phase.libraries.unwrap_or_default().join(" ");
 
-    let nix_archive = setup_phase.archive.clone();
-    let pkg_import = match nix_archive {
-        Some(archive) => format!(
-            "import (fetchTarball \"https://github.com/NixOS/nixpkgs/archive/{}.tar.gz\")",
-            archive
-        ),
-        None => "import <nixpkgs>".to_string(),
-    };
+    let pkg_import = get_pkg_import(plan);
 
     let mut overlays: Vec<String> = Vec::new();
     for pkg in &setup_phase.pkgs {
@@ -62,3 +55,14 @@ pub fn create_nix_expression(plan: &BuildPlan) -> String {
 
     nix_expression
 }
+
+/// Imports the nixpkgs the plan is pinned to, or the channel's
+pub fn get_pkg_import(plan: &BuildPlan) -> String {
+    match plan.setup.clone().unwrap_or_default().archive {
+        Some(archive) => format!(
+            "import (fetchTarball \"https://github.com/NixOS/nixpkgs/archive/{}.tar.gz\")",
+            archive
+        ),
+        None => "import <nixpkgs>".to_string(),
+    }
+}
@@ -185,6 +185,33 @@ pub struct StartPhase {
 
     #[serde(rename = "onlyIncludeFiles")]
     pub only_include_files: Option<Vec<String>>,
+
+    #[serde(rename = "releaseCmd")]
+    pub release_cmd: Option<String>,
+
+    /// Ports the app listens on when `$PORT` is not set
+    pub ports: Option<Vec<u16>>,
+
+    pub healthcheck: Option<Healthcheck>,
+
+    /// Unprivileged user the app runs as, root when unset
+    pub user: Option<String>,
+
+    /// Paths relative to the app that the app writes to at runtime
+    #[serde(rename = "writablePaths")]
+    pub writable_paths: Option<Vec<String>>,
+
+    /// Remove write permissions from the rest of the app directory
+    #[serde(rename = "readOnly")]
+    pub read_only: Option<bool>,
+}
+
+/// Checks that the running app is healthy, with either a command or an HTTP path
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct Healthcheck {
+    pub cmd: Option<String>,
+    pub path: Option<String>,
 }
 
 impl StartPhase {
@@ -193,7 +220,55 @@ impl StartPhase {
             cmd: Some(cmd),
             run_image: None,
             only_include_files: None,
+            release_cmd: None,
+            ports: None,
+            healthcheck: None,
+            user: None,
+            writable_paths: None,
+            read_only: None,
+        }
+    }
+
+    pub fn run_as_user(&mut self, user: String) {
+        self.user = Some(user);
+    }
+
+    pub fn add_writable_path(&mut self, path: String) {
+        let mut paths = self.writable_paths.clone().unwrap_or_default();
+        if !paths.contains(&path) {
+            paths.push(path);
         }
+        self.writable_paths = Some(paths);
+    }
+
+    pub fn add_port(&mut self, port: u16) {
+        let mut ports = self.ports.clone().unwrap_or_default();
+        if !ports.contains(&port) {
+            ports.push(port);
+        }
+        self.ports = Some(ports);
+    }
+
+    pub fn set_healthcheck_cmd(&mut self, cmd: String) {
+        self.healthcheck = Some(Healthcheck {
+            cmd: Some(cmd),
+            path: None,
+        });
+    }
+
+    /// Request a path on the first port, e.g. `/health`
+    pub fn set_healthcheck_path(&mut self, path: String) {
+        self.healthcheck = Some(Healthcheck {
+            cmd: None,
+            path: Some(path),
+        });
+    }
+
+    /// Set a command to run once per deploy before the new version starts (e.g. migrations)
+    ///
+    /// It is part of the plan for the platform to run, the image doesn't run it.
+    pub fn set_release_cmd(&mut self, cmd: String) {
+        self.release_cmd = Some(cmd);
     }
 
     pub fn run_in_image(&mut self, image_name: String) {
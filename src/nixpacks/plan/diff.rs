@@ -0,0 +1,396 @@
+use std::{collections::BTreeSet, fmt};
+
+use super::BuildPlan;
+use crate::nixpacks::phase::Healthcheck;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(String),
+    Removed(String),
+    Changed(String, String),
+    /// Changed contents too long to show, e.g. of static assets
+    Modified(String),
+}
+
+/// A difference between two plans in one field of a phase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanChange {
+    pub phase: &'static str,
+    pub field: String,
+    pub change: Change,
+}
+
+impl fmt::Display for PlanChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.change {
+            Change::Added(value) => write!(f, "+ {}: {}", self.field, value),
+            Change::Removed(value) => write!(f, "- {}: {}", self.field, value),
+            Change::Changed(old, new) => write!(f, "~ {}: {} -> {}", self.field, old, new),
+            Change::Modified(value) => write!(f, "~ {}: {}", self.field, value),
+        }
+    }
+}
+
+/// The changes from the `old` to the `new` plan, grouped by phase
+///
+/// Lists like packages are compared as sets, except commands, whose order matters.
+/// Switching to or from building the app's Dockerfile is a change of the `dockerfile` field.
+/// The nixpacks version the plans were generated with is not compared.
+pub fn diff_plans(old: &BuildPlan, new: &BuildPlan) -> Vec<PlanChange> {
+    let mut diff = PlanDiff::default();
+
+    let (old_setup, new_setup) = (
+        old.setup.clone().unwrap_or_default(),
+        new.setup.clone().unwrap_or_default(),
+    );
+    diff.phase = "Setup";
+    diff.list(
+        "pkgs",
+        old_setup
+            .pkgs
+            .iter()
+            .map(|pkg| pkg.to_nix_string())
+            .collect(),
+        new_setup
+            .pkgs
+            .iter()
+            .map(|pkg| pkg.to_nix_string())
+            .collect(),
+        false,
+    );
+    diff.list(
+        "libraries",
+        old_setup.libraries.unwrap_or_default(),
+        new_setup.libraries.unwrap_or_default(),
+        false,
+    );
+    diff.list(
+        "aptPkgs",
+        old_setup.apt_pkgs.unwrap_or_default(),
+        new_setup.apt_pkgs.unwrap_or_default(),
+        false,
+    );
+    diff.list(
+        "cmds",
+        old_setup.cmds.unwrap_or_default(),
+        new_setup.cmds.unwrap_or_default(),
+        true,
+    );
+    diff.value("archive", old_setup.archive, new_setup.archive);
+    diff.value(
+        "baseImage",
+        Some(old_setup.base_image),
+        Some(new_setup.base_image),
+    );
+    diff.list(
+        "onlyIncludeFiles",
+        old_setup.only_include_files.unwrap_or_default(),
+        new_setup.only_include_files.unwrap_or_default(),
+        false,
+    );
+
+    let (old_install, new_install) = (
+        old.install.clone().unwrap_or_default(),
+        new.install.clone().unwrap_or_default(),
+    );
+    diff.phase = "Install";
+    diff.list(
+        "cmds",
+        old_install.cmds.unwrap_or_default(),
+        new_install.cmds.unwrap_or_default(),
+        true,
+    );
+    diff.list(
+        "paths",
+        old_install.paths.unwrap_or_default(),
+        new_install.paths.unwrap_or_default(),
+        true,
+    );
+    diff.list(
+        "onlyIncludeFiles",
+        old_install.only_include_files.unwrap_or_default(),
+        new_install.only_include_files.unwrap_or_default(),
+        false,
+    );
+
+    let (old_build, new_build) = (
+        old.build.clone().unwrap_or_default(),
+        new.build.clone().unwrap_or_default(),
+    );
+    diff.phase = "Build";
+    diff.list(
+        "cmds",
+        old_build.cmds.unwrap_or_default(),
+        new_build.cmds.unwrap_or_default(),
+        true,
+    );
+    diff.list(
+        "onlyIncludeFiles",
+        old_build.only_include_files.unwrap_or_default(),
+        new_build.only_include_files.unwrap_or_default(),
+        false,
+    );
+
+    let (old_start, new_start) = (
+        old.start.clone().unwrap_or_default(),
+        new.start.clone().unwrap_or_default(),
+    );
+    diff.phase = "Start";
+    diff.value("cmd", old_start.cmd, new_start.cmd);
+    diff.value("releaseCmd", old_start.release_cmd, new_start.release_cmd);
+    diff.value("runImage", old_start.run_image, new_start.run_image);
+    diff.list(
+        "ports",
+        to_strings(old_start.ports.unwrap_or_default()),
+        to_strings(new_start.ports.unwrap_or_default()),
+        false,
+    );
+    diff.value(
+        "healthcheck",
+        old_start.healthcheck.map(get_healthcheck_string),
+        new_start.healthcheck.map(get_healthcheck_string),
+    );
+    diff.value("user", old_start.user, new_start.user);
+    diff.list(
+        "writablePaths",
+        old_start.writable_paths.unwrap_or_default(),
+        new_start.writable_paths.unwrap_or_default(),
+        false,
+    );
+    diff.value(
+        "readOnly",
+        old_start.read_only.map(|read_only| read_only.to_string()),
+        new_start.read_only.map(|read_only| read_only.to_string()),
+    );
+    diff.list(
+        "onlyIncludeFiles",
+        old_start.only_include_files.unwrap_or_default(),
+        new_start.only_include_files.unwrap_or_default(),
+        false,
+    );
+
+    diff.phase = "Variables";
+    let (old_variables, new_variables) = (
+        old.variables.clone().unwrap_or_default(),
+        new.variables.clone().unwrap_or_default(),
+    );
+    let names = old_variables
+        .keys()
+        .chain(new_variables.keys())
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        diff.value(
+            name,
+            old_variables.get(name).cloned(),
+            new_variables.get(name).cloned(),
+        );
+    }
+
+    diff.phase = "Static Assets";
+    let (old_assets, new_assets) = (
+        old.static_assets.clone().unwrap_or_default(),
+        new.static_assets.clone().unwrap_or_default(),
+    );
+    let names = old_assets
+        .keys()
+        .chain(new_assets.keys())
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        let change = match (old_assets.get(name), new_assets.get(name)) {
+            (None, Some(_)) => Change::Added(name.clone()),
+            (Some(_), None) => Change::Removed(name.clone()),
+            (Some(old), Some(new)) if old != new => Change::Modified(name.clone()),
+            _ => continue,
+        };
+        diff.push("assets", change);
+    }
+
+    // Set instead of the phases when the app's Dockerfile is built as is
+    diff.phase = "Dockerfile";
+    diff.value("dockerfile", old.dockerfile.clone(), new.dockerfile.clone());
+
+    diff.changes
+}
+
+/// The changes as lines under the phases they are in
+pub fn get_diff_string(changes: &[PlanChange]) -> String {
+    let mut lines = Vec::new();
+    let mut phase = "";
+    for change in changes {
+        if change.phase != phase {
+            phase = change.phase;
+            lines.push(format!("=> {}", phase));
+        }
+        lines.push(format!("    {}", change));
+    }
+    lines.join("\n")
+}
+
+#[derive(Default)]
+struct PlanDiff {
+    phase: &'static str,
+    changes: Vec<PlanChange>,
+}
+
+impl PlanDiff {
+    fn push(&mut self, field: &str, change: Change) {
+        self.changes.push(PlanChange {
+            phase: self.phase,
+            field: field.to_string(),
+            change,
+        });
+    }
+
+    fn value(&mut self, field: &str, old: Option<String>, new: Option<String>) {
+        let change = match (old, new) {
+            (None, Some(new)) => Change::Added(new),
+            (Some(old), None) => Change::Removed(old),
+            (Some(old), Some(new)) if old != new => Change::Changed(old, new),
+            _ => return,
+        };
+        self.push(field, change);
+    }
+
+    fn list(&mut self, field: &str, old: Vec<String>, new: Vec<String>, ordered: bool) {
+        let removed = old
+            .iter()
+            .filter(|item| !new.contains(item))
+            .collect::<Vec<_>>();
+        let added = new
+            .iter()
+            .filter(|item| !old.contains(item))
+            .collect::<Vec<_>>();
+
+        if removed.is_empty() && added.is_empty() {
+            if ordered && old != new {
+                self.push(field, Change::Changed(old.join(", "), new.join(", ")));
+            }
+            return;
+        }
+
+        for item in removed {
+            self.push(field, Change::Removed(item.clone()));
+        }
+        for item in added {
+            self.push(field, Change::Added(item.clone()));
+        }
+    }
+}
+
+fn to_strings<T: ToString>(values: Vec<T>) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+fn get_healthcheck_string(healthcheck: Healthcheck) -> String {
+    match healthcheck {
+        Healthcheck { cmd: Some(cmd), .. } => cmd,
+        Healthcheck {
+            path: Some(path), ..
+        } => path,
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nixpacks::{
+        environment::EnvironmentVariables,
+        nix::pkg::Pkg,
+        phase::{BuildPhase, SetupPhase, StartPhase},
+    };
+
+    fn plan(pkgs: Vec<&str>, build_cmds: Vec<&str>, start: StartPhase) -> BuildPlan {
+        BuildPlan {
+            version: None,
+            setup: Some(SetupPhase::new(pkgs.into_iter().map(Pkg::new).collect())),
+            install: None,
+            build: Some(BuildPhase {
+                cmds: Some(build_cmds.into_iter().map(String::from).collect()),
+                ..Default::default()
+            }),
+            start: Some(start),
+            variables: Some(EnvironmentVariables::from([(
+                "NODE_ENV".to_string(),
+                "production".to_string(),
+            )])),
+            static_assets: None,
+            dockerfile: None,
+        }
+    }
+
+    #[test]
+    fn test_same_plans() {
+        let start = StartPhase::new("npm start".to_string());
+        let old = plan(vec!["nodejs"], vec!["npm run build"], start.clone());
+        let new = plan(vec!["nodejs"], vec!["npm run build"], start);
+        assert!(diff_plans(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_changed_plans() {
+        let old = plan(
+            vec!["nodejs", "yarn"],
+            vec!["npm run lint", "npm run build"],
+            StartPhase::new("npm start".to_string()),
+        );
+        let mut new_start = StartPhase::new("node dist/index.js".to_string());
+        new_start.run_in_image("node:18-slim".to_string());
+        let mut new = plan(
+            vec!["nodejs-18_x", "yarn"],
+            vec!["npm run build", "npm run lint"],
+            new_start,
+        );
+        new.variables = Some(EnvironmentVariables::from([(
+            "NODE_ENV".to_string(),
+            "development".to_string(),
+        )]));
+
+        let changes = diff_plans(&old, &new);
+        assert_eq!(
+            get_diff_string(&changes),
+            "=> Setup\n    \
+             - pkgs: nodejs\n    \
+             + pkgs: nodejs-18_x\n\
+             => Build\n    \
+             ~ cmds: npm run lint, npm run build -> npm run build, npm run lint\n\
+             => Start\n    \
+             ~ cmd: npm start -> node dist/index.js\n    \
+             + runImage: node:18-slim\n\
+             => Variables\n    \
+             ~ NODE_ENV: production -> development"
+        );
+    }
+
+    #[test]
+    fn test_dockerfile_plans() {
+        let start = StartPhase::new("python server.py".to_string());
+        let old = plan(vec![], vec![], start.clone());
+        let dockerfile_plan = |dockerfile: &str| BuildPlan {
+            setup: None,
+            build: None,
+            start: None,
+            dockerfile: Some(dockerfile.to_string()),
+            ..plan(vec![], vec![], start.clone())
+        };
+        let new = dockerfile_plan("Dockerfile");
+
+        assert_eq!(
+            diff_plans(&old, &new).last(),
+            Some(&PlanChange {
+                phase: "Dockerfile",
+                field: "dockerfile".to_string(),
+                change: Change::Added("Dockerfile".to_string()),
+            })
+        );
+        assert_eq!(
+            diff_plans(&new, &old).last().map(|change| &change.change),
+            Some(&Change::Removed("Dockerfile".to_string()))
+        );
+        assert!(diff_plans(&new, &dockerfile_plan("Dockerfile")).is_empty());
+        assert_eq!(
+            get_diff_string(&diff_plans(&new, &dockerfile_plan("Dockerfile.prod"))),
+            "=> Dockerfile\n    ~ dockerfile: Dockerfile -> Dockerfile.prod"
+        );
+    }
+}
@@ -1,17 +1,29 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::HashMap,
+    fs,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
 
 use super::{BuildPlan, PlanGenerator};
 use crate::{
     nixpacks::{
         app::{App, StaticAssets},
-        environment::{Environment, EnvironmentVariables},
+        environment::{merge_variables, Environment, EnvironmentVariables, VariableDefinition},
+        images::{APP_USER, DEBIAN_SLIM_IMAGE},
         nix::pkg::Pkg,
         phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
         NIX_PACKS_VERSION,
     },
-    providers::Provider,
+    providers::{dockerfile::DOCKERFILE, Provider},
 };
-use anyhow::{bail, Context, Ok, Result};
+use anyhow::{anyhow, bail, Context, Ok, Result};
+use indoc::formatdoc;
 
 // https://status.nixos.org/
 static NIXPKGS_ARCHIVE: &str = "41cc1d5d9584103be4108c1815c350e07c807036";
@@ -26,6 +38,10 @@ pub struct GeneratePlanOptions {
     pub custom_apt_pkgs: Vec<String>,
     pub pin_pkgs: bool,
     pub plan_path: Option<String>,
+    /// Subdirectory of the app source the app is planned from
+    pub root: Option<String>,
+    /// Dotenv files with variables, overridden by the variables passed separately
+    pub env_files: Vec<String>,
 }
 
 pub struct NixpacksBuildPlanGenerator<'a> {
@@ -44,6 +60,13 @@ impl<'a> PlanGenerator for NixpacksBuildPlanGenerator<'a> {
             return Ok(plan);
         }
 
+        if environment
+            .get_config_variable("DOCKERFILE")
+            .is_some_and(|mode| mode == "build")
+        {
+            return self.get_dockerfile_plan(app, environment);
+        }
+
         self.detect(app, environment)?;
 
         let setup_phase = self
@@ -55,9 +78,17 @@ impl<'a> PlanGenerator for NixpacksBuildPlanGenerator<'a> {
         let build_phase = self
             .get_build_phase(app, environment)
             .context("Generating build phase")?;
-        let start_phase = self
+        let mut start_phase = self
             .get_start_phase(app, environment)
             .context("Generating start phase")?;
+
+        // Otherwise the image is built without a CMD and only fails when it is run
+        if self.matched_provider.is_none()
+            && start_phase.cmd.is_none()
+            && !environment.is_config_variable_truthy("NO_START_CMD")
+        {
+            bail!(self.get_no_provider_error(app));
+        }
         let variables = self
             .get_variables(app, environment)
             .context("Getting plan variables")?;
@@ -65,6 +96,13 @@ impl<'a> PlanGenerator for NixpacksBuildPlanGenerator<'a> {
             .get_static_assets(app, environment)
             .context("Getting provider assets")?;
 
+        // Default to the port set by the provider or the user
+        if start_phase.ports.is_none() {
+            if let Some(port) = variables.get("PORT").and_then(|port| port.parse().ok()) {
+                start_phase.add_port(port);
+            }
+        }
+
         let plan = BuildPlan {
             version: Some(NIX_PACKS_VERSION.to_string()),
             setup: Some(setup_phase),
@@ -73,6 +111,7 @@ impl<'a> PlanGenerator for NixpacksBuildPlanGenerator<'a> {
             start: Some(start_phase),
             variables: Some(variables),
             static_assets: Some(static_assets),
+            dockerfile: None,
         };
 
         Ok(plan)
@@ -83,7 +122,7 @@ impl<'a> NixpacksBuildPlanGenerator<'a> {
     pub fn new(
         providers: Vec<&'a dyn Provider>,
         options: GeneratePlanOptions,
-    ) -> NixpacksBuildPlanGenerator {
+    ) -> NixpacksBuildPlanGenerator<'a> {
         NixpacksBuildPlanGenerator {
             providers,
             matched_provider: None,
@@ -91,18 +130,156 @@ impl<'a> NixpacksBuildPlanGenerator<'a> {
         }
     }
 
+    /// Defer to the app's Dockerfile, only passing the variables as build args
+    fn get_dockerfile_plan(&self, app: &App, environment: &Environment) -> Result<BuildPlan> {
+        if !app.includes_file(DOCKERFILE) {
+            bail!("NIXPACKS_DOCKERFILE is set to build, but the app has no Dockerfile");
+        }
+
+        Ok(BuildPlan {
+            version: Some(NIX_PACKS_VERSION.to_string()),
+            setup: None,
+            install: None,
+            build: None,
+            start: None,
+            variables: Some(Environment::clone_variables(environment)),
+            static_assets: None,
+            dockerfile: Some(DOCKERFILE.to_string()),
+        })
+    }
+
     fn detect(&mut self, app: &App, environment: &Environment) -> Result<()> {
-        for provider in self.providers.clone() {
-            let matches = provider.detect(app, environment)?;
+        let start = Instant::now();
+
+        // Providers share the app's file index, so a few can be checked at once.
+        // Workers take providers in order and stop after the first match, since
+        // only earlier providers can still win.
+        let providers = &self.providers;
+        let workers = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(providers.len());
+        let next = AtomicUsize::new(0);
+        let first_match = AtomicUsize::new(usize::MAX);
+        let results = providers
+            .iter()
+            .map(|_| Mutex::new(None))
+            .collect::<Vec<Mutex<Option<Result<bool>>>>>();
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    if index >= providers.len() || index > first_match.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    let provider = providers[index];
+                    let result =
+                        panic::catch_unwind(AssertUnwindSafe(|| provider.detect(app, environment)))
+                            .unwrap_or_else(|_| {
+                                Err(anyhow!("Detecting {} failed", provider.name()))
+                            });
+                    if matches!(result, Result::Ok(true)) {
+                        first_match.fetch_min(index, Ordering::SeqCst);
+                    }
+                    *results[index].lock().unwrap() = Some(result);
+                });
+            }
+        });
+
+        // The first provider in order wins
+        for (provider, result) in self.providers.clone().into_iter().zip(results) {
+            // Only providers after the match are not checked
+            let matches = match result.into_inner().unwrap() {
+                Some(matches) => matches?,
+                None => break,
+            };
             if matches {
                 self.matched_provider = Some(provider);
                 break;
             }
         }
 
+        if environment.is_config_variable_truthy("TIMINGS") {
+            let stats = app.index_stats();
+            eprintln!(
+                "Indexed {} files in {:.2?}{}, detected {} in {:.2?}",
+                stats.files,
+                stats.index_time,
+                if stats.truncated { " (truncated)" } else { "" },
+                self.matched_provider
+                    .map_or("no provider", |provider| provider.name()),
+                start.elapsed()
+            );
+        }
+
         Ok(())
     }
 
+    fn get_no_provider_error(&self, app: &App) -> String {
+        let detect_files = self
+            .providers
+            .iter()
+            .filter(|provider| !provider.detect_files().is_empty())
+            .map(|provider| {
+                format!(
+                    "  {}: {}",
+                    provider.name(),
+                    provider.detect_files().join(", ")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let closest_provider = self
+            .get_closest_provider(app)
+            .map(|(provider, count)| {
+                format!(
+                    "\n\nThe app looks closest to {} ({} source files), which needs one of {}.",
+                    provider.name(),
+                    count,
+                    provider.detect_files().join(", ")
+                )
+            })
+            .unwrap_or_default();
+
+        formatdoc! {"
+            No provider matched the app and no start command was found.
+
+            Providers look for:
+            {detect_files}{closest_provider}
+
+            To build it anyway, set a start command with
+              - the NIXPACKS_START_CMD variable or the --start-cmd flag
+              - a Procfile with a `web: <cmd>` process
+
+            For images that only provide tools, set NIXPACKS_NO_START_CMD=1 to build without a start command.",
+            detect_files=detect_files,
+            closest_provider=closest_provider,
+        }
+    }
+
+    /// The provider with the most source files of its language in the app
+    fn get_closest_provider(&self, app: &App) -> Option<(&'a dyn Provider, usize)> {
+        // Reversed so that ties go to the provider listed first
+        self.providers
+            .iter()
+            .rev()
+            .map(|provider| {
+                let count = provider
+                    .source_files()
+                    .iter()
+                    .map(|pattern| {
+                        app.find_files(pattern)
+                            .map(|files| files.len())
+                            .unwrap_or(0)
+                    })
+                    .sum::<usize>();
+                (*provider, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .max_by_key(|(_, count)| *count)
+    }
+
     fn get_setup_phase(&self, app: &App, environment: &Environment) -> Result<SetupPhase> {
         let mut setup_phase: SetupPhase = match self.matched_provider {
             Some(provider) => provider.setup(app, environment)?.unwrap_or_default(),
@@ -201,14 +378,6 @@ impl<'a> NixpacksBuildPlanGenerator<'a> {
             .or(env_build_cmd)
             .or(build_phase.cmds);
 
-        // Release process type
-        if let Some(release_cmd) = self.get_procfile_release_cmd(app)? {
-            build_phase
-                .cmds
-                .clone()
-                .unwrap_or_default()
-                .push(release_cmd);
-        }
         Ok(build_phase)
     }
 
@@ -233,35 +402,100 @@ impl<'a> NixpacksBuildPlanGenerator<'a> {
             .clone()
             .or_else(|| env_start_cmd.or_else(|| procfile_cmd.or(start_phase.cmd)));
 
+        // Release command priority
+        // - procfile release process type
+        // - provider
+        if let Some(release_cmd) = self.get_procfile_release_cmd(app)? {
+            start_phase.release_cmd = Some(release_cmd);
+        }
+
         // Allow the user to override the run image with an environment variable
         if let Some(env_run_image) = environment.get_config_variable("RUN_IMAGE") {
             // If the env var is "falsy", then unset the run image on the start phase
             start_phase.run_image = match env_run_image.as_str() {
-                "0" | "false" => None,
-                img if img.is_empty() => None,
+                "0" | "false" | "" => None,
                 img => Some(img.to_owned()),
             };
         }
 
+        // Slim run images only contain the built app, so it runs without root by default
+        match environment.get_config_variable("NON_ROOT") {
+            Some(_) if environment.is_config_variable_truthy("NON_ROOT") => {
+                start_phase.run_as_user(APP_USER.to_string());
+            }
+            Some(_) => start_phase.user = None,
+            None if start_phase.run_image.as_deref() == Some(DEBIAN_SLIM_IMAGE) => {
+                start_phase.run_as_user(APP_USER.to_string());
+            }
+            None => {}
+        }
+
+        if let Some(env_writable_paths) = environment.get_config_variable("WRITABLE_PATHS") {
+            for path in env_writable_paths.split_whitespace() {
+                start_phase.add_writable_path(path.trim_start_matches("./").to_string());
+            }
+        }
+
+        if environment.is_config_variable_truthy("READ_ONLY_APP") {
+            start_phase.read_only = Some(true);
+        }
+
+        if let Some(env_ports) = environment.get_config_variable("PORTS") {
+            start_phase.ports = None;
+            for port in env_ports
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|port| !port.is_empty())
+            {
+                let port = port
+                    .parse()
+                    .with_context(|| format!("Invalid port {} in NIXPACKS_PORTS", port))?;
+                start_phase.add_port(port);
+            }
+        }
+
+        // An HTTP path such as `/health`, a command, or a falsy value to disable the healthcheck
+        if let Some(env_healthcheck) = environment.get_config_variable("HEALTHCHECK") {
+            match env_healthcheck.as_str() {
+                "0" | "false" | "" => start_phase.healthcheck = None,
+                path if path.starts_with('/') => start_phase.set_healthcheck_path(path.to_owned()),
+                cmd => start_phase.set_healthcheck_cmd(cmd.to_owned()),
+            }
+        }
+
+        // Unprivileged users can't bind ports below 1024 without extra capabilities
+        if start_phase.user.is_some() {
+            if let Some(port) = start_phase
+                .ports
+                .iter()
+                .flatten()
+                .find(|port| **port < 1024)
+            {
+                eprintln!(
+                    "Warning: The app runs as an unprivileged user, which can't listen on port {}. Use a port above 1023 or set NIXPACKS_NON_ROOT=0",
+                    port
+                );
+            }
+        }
+
         Ok(start_phase)
     }
 
     fn get_variables(&self, app: &App, environment: &Environment) -> Result<EnvironmentVariables> {
-        // Get a copy of the variables in the environment
-        let variables = Environment::clone_variables(environment);
-
-        let new_variables = match self.matched_provider {
-            Some(provider) => {
-                // Merge provider variables
-                let provider_variables = provider
-                    .environment_variables(app, environment)?
-                    .unwrap_or_default();
-                provider_variables.into_iter().chain(variables).collect()
-            }
-            None => variables,
+        let provider_variables = match self.matched_provider {
+            Some(provider) => provider
+                .environment_variables(app, environment)?
+                .unwrap_or_default(),
+            None => EnvironmentVariables::new(),
         };
 
-        Ok(new_variables)
+        // Provider variables are defaults the user's variables override and can reference
+        let definitions = provider_variables
+            .iter()
+            .map(|(name, value)| VariableDefinition::literal(name, value))
+            .chain(environment.get_definitions())
+            .collect::<Vec<_>>();
+
+        Ok(merge_variables(&definitions))
     }
 
     fn get_static_assets(&self, app: &App, environment: &Environment) -> Result<StaticAssets> {
@@ -306,3 +540,60 @@ impl<'a> NixpacksBuildPlanGenerator<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    struct TestProvider {
+        matches: bool,
+        checked: AtomicUsize,
+    }
+
+    impl Provider for TestProvider {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn detect(&self, _app: &App, _env: &Environment) -> Result<bool> {
+            self.checked.fetch_add(1, Ordering::SeqCst);
+            if !self.matches {
+                thread::sleep(Duration::from_millis(5));
+            }
+            Ok(self.matches)
+        }
+    }
+
+    #[test]
+    fn test_detect_stops_after_match() -> Result<()> {
+        let providers = (0..200)
+            .map(|index| TestProvider {
+                matches: index == 1 || index == 150,
+                checked: AtomicUsize::new(0),
+            })
+            .collect::<Vec<_>>();
+        let mut generator = NixpacksBuildPlanGenerator::new(
+            providers
+                .iter()
+                .map(|provider| provider as &dyn Provider)
+                .collect(),
+            GeneratePlanOptions::default(),
+        );
+        generator.detect(&App::new("./examples/node")?, &Environment::default())?;
+
+        assert!(generator.matched_provider.is_some());
+        // Providers before the match are always checked
+        assert_eq!(providers[0].checked.load(Ordering::SeqCst), 1);
+        assert_eq!(providers[150].checked.load(Ordering::SeqCst), 0);
+        assert!(
+            providers
+                .iter()
+                .filter(|provider| provider.checked.load(Ordering::SeqCst) > 0)
+                .count()
+                < providers.len()
+        );
+
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use anyhow::Result;
 use indoc::formatdoc;
 use serde::{Deserialize, Serialize};
 
+pub mod diff;
 pub mod generator;
 
 #[serde_with::skip_serializing_none]
@@ -19,6 +20,8 @@ pub struct BuildPlan {
     pub start: Option<StartPhase>,
     pub variables: Option<EnvironmentVariables>,
     pub static_assets: Option<StaticAssets>,
+    /// The app's own Dockerfile, built as is instead of the phases
+    pub dockerfile: Option<String>,
 }
 
 pub trait PlanGenerator {
@@ -27,6 +30,10 @@ pub trait PlanGenerator {
 
 impl BuildPlan {
     pub fn get_build_string(&self) -> String {
+        if let Some(dockerfile) = &self.dockerfile {
+            return get_phase_string("Dockerfile", Some(dockerfile.clone()));
+        }
+
         let setup_phase = self.setup.clone();
         let nix_pkgs = setup_phase
             .clone()
@@ -51,17 +58,23 @@ impl BuildPlan {
         );
 
         let start_phase = self.start.clone();
+        // Only shown when there is one, the image doesn't run it
+        let release_string = match start_phase.clone().and_then(|start| start.release_cmd) {
+            Some(release_cmd) => format!("\n{}", get_phase_string("Release", Some(release_cmd))),
+            None => "".to_string(),
+        };
         let start_string = get_phase_string("Start", start_phase.and_then(|start| start.cmd));
 
-        return formatdoc! {"
+        formatdoc! {"
           {packages_string}
           {install_string}
-          {build_string}
+          {build_string}{release_string}
           {start_string}",
             packages_string=packages_string,
             install_string=install_string,
             build_string=build_string,
-        start_string=start_string};
+            release_string=release_string,
+        start_string=start_string}
     }
 }
 
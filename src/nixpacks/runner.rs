@@ -0,0 +1,205 @@
+use std::{
+    io::{self, IsTerminal},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use super::{environment::EnvironmentVariables, plan::BuildPlan};
+use anyhow::{Context, Result};
+use uuid::Uuid;
+
+/// Port the app is expected to listen on when the plan has none
+pub const DEFAULT_PORT: u16 = 8080;
+
+#[derive(Clone, Default, Debug)]
+pub struct RunOptions {
+    /// Port published on the host, the app's port by default
+    pub port: Option<u16>,
+    /// Run the image from a previous build instead of building the app
+    pub no_build: bool,
+}
+
+pub struct DockerRunner {
+    options: RunOptions,
+}
+
+impl DockerRunner {
+    pub fn new(options: RunOptions) -> DockerRunner {
+        DockerRunner { options }
+    }
+
+    /// Runs the image in the foreground until the app exits or is interrupted
+    ///
+    /// Signals are forwarded to the app by Docker and the container is removed
+    /// afterwards, however the app exits.
+    pub fn run(
+        &self,
+        image: &str,
+        plan: &BuildPlan,
+        variables: &EnvironmentVariables,
+    ) -> Result<ExitStatus> {
+        let container = format!("nixpacks-{}", Uuid::new_v4());
+        let _container = ContainerGuard(container.clone());
+
+        // Docker forwards Ctrl+C to the app, keep running until it has stopped
+        match ctrlc::set_handler(|| {}) {
+            Ok(()) | Err(ctrlc::Error::MultipleHandlers) => {}
+            Err(e) => return Err(e).context("Setting the interrupt handler"),
+        }
+
+        let port = get_port(plan);
+        println!(
+            "Running {} on http://localhost:{}",
+            image,
+            self.options.port.unwrap_or(port)
+        );
+
+        let mut run_cmd = self.get_docker_run_cmd(image, &container, port, variables);
+        run_cmd
+            .spawn()
+            .context("Running docker")?
+            .wait()
+            .context("Running image")
+    }
+
+    fn get_docker_run_cmd(
+        &self,
+        image: &str,
+        container: &str,
+        port: u16,
+        variables: &EnvironmentVariables,
+    ) -> Command {
+        let mut docker_run_cmd = Command::new("docker");
+        docker_run_cmd
+            .arg("run")
+            .arg("--rm")
+            // Shell start commands don't pass signals on to the app without an init process
+            .arg("--init")
+            .arg("--name")
+            .arg(container);
+
+        if io::stdin().is_terminal() && io::stdout().is_terminal() {
+            docker_run_cmd.arg("-it");
+        }
+
+        docker_run_cmd
+            .arg("-p")
+            .arg(format!("{}:{}", self.options.port.unwrap_or(port), port))
+            .arg("-e")
+            .arg(format!("PORT={}", port));
+
+        // Sorted to keep the command stable
+        let mut variables = variables.iter().collect::<Vec<_>>();
+        variables.sort();
+        for (name, value) in variables {
+            if name != "PORT" {
+                docker_run_cmd.arg("-e").arg(format!("{}={}", name, value));
+            }
+        }
+
+        docker_run_cmd.arg(image);
+        docker_run_cmd
+    }
+}
+
+/// The port the app listens on, from the start phase or the `PORT` variable
+pub fn get_port(plan: &BuildPlan) -> u16 {
+    plan.start
+        .as_ref()
+        .and_then(|start| start.ports.as_ref())
+        .and_then(|ports| ports.first().copied())
+        .or_else(|| {
+            plan.variables
+                .as_ref()
+                .and_then(|variables| variables.get("PORT"))
+                .and_then(|port| port.parse().ok())
+        })
+        .unwrap_or(DEFAULT_PORT)
+}
+
+/// Force removes the container when the run ends, in case Docker didn't
+struct ContainerGuard(String);
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        let _ = Command::new("docker")
+            .arg("rm")
+            .arg("-f")
+            .arg(&self.0)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nixpacks::phase::StartPhase;
+
+    fn plan(start: Option<StartPhase>, variables: EnvironmentVariables) -> BuildPlan {
+        BuildPlan {
+            version: None,
+            setup: None,
+            install: None,
+            build: None,
+            start,
+            variables: Some(variables),
+            static_assets: None,
+            dockerfile: None,
+        }
+    }
+
+    #[test]
+    fn test_get_port() {
+        let mut start = StartPhase::new("npm start".to_string());
+        start.add_port(3000);
+        assert_eq!(
+            get_port(&plan(Some(start), EnvironmentVariables::new())),
+            3000
+        );
+
+        let variables = EnvironmentVariables::from([("PORT".to_string(), "4000".to_string())]);
+        assert_eq!(get_port(&plan(None, variables)), 4000);
+
+        assert_eq!(
+            get_port(&plan(None, EnvironmentVariables::new())),
+            DEFAULT_PORT
+        );
+    }
+
+    #[test]
+    fn test_docker_run_cmd() {
+        let runner = DockerRunner::new(RunOptions {
+            port: Some(8000),
+            ..Default::default()
+        });
+        let variables = EnvironmentVariables::from([
+            ("SECRET".to_string(), "a=b".to_string()),
+            ("PORT".to_string(), "1".to_string()),
+        ]);
+
+        let cmd = runner.get_docker_run_cmd("my-app", "nixpacks-test", 3000, &variables);
+        let args = cmd
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .filter(|arg| *arg != "-it")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            args,
+            vec![
+                "run",
+                "--rm",
+                "--init",
+                "--name",
+                "nixpacks-test",
+                "-p",
+                "8000:3000",
+                "-e",
+                "PORT=3000",
+                "-e",
+                "SECRET=a=b",
+                "my-app"
+            ]
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{bail, Context, Result};
+use indoc::formatdoc;
+use std::{fs, path::PathBuf};
+
+use super::{app::App, nix, plan::BuildPlan};
+
+pub const SHELL_NIX: &str = "shell.nix";
+pub const ENVRC: &str = ".envrc";
+
+/// Directory of the app in the image, which is the app root in a local shell
+const APP_DIR: &str = "/app/";
+
+#[derive(Clone, Default, Debug)]
+pub struct DevShellOptions {
+    /// Also write an `.envrc` that loads the shell with direnv
+    pub direnv: bool,
+    /// Overwrite existing files
+    pub force: bool,
+}
+
+/// A `shell.nix` with the packages, libraries and paths the app is built with
+pub fn create_shell_expression(plan: &BuildPlan) -> String {
+    let install_phase = plan.install.clone().unwrap_or_default();
+
+    let path = install_phase
+        .paths
+        .unwrap_or_default()
+        .iter()
+        .map(|path| match path.strip_prefix(APP_DIR) {
+            Some(relative) => format!("${{toString ./.}}/{}", relative),
+            None => path.to_string(),
+        })
+        .map(|path| format!("\n    export PATH=\"{}:$PATH\"", path))
+        .collect::<String>();
+
+    formatdoc! {"
+        # Generated by nixpacks from the app's build plan
+        {{ }}:
+
+        let
+          pkgs = {pkg_import} {{ }};
+          env = (
+        {environment}
+          ) {{ }};
+        in
+        pkgs.mkShell {{
+          buildInputs = [ env ];
+          shellHook = ''
+            source ${{env}}/etc/profile.d/libraries.sh{path}
+          '';
+        }}
+    ",
+    pkg_import=nix::get_pkg_import(plan),
+    environment=nix::create_nix_expression(plan)
+        .trim_end()
+        .lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("    {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n"),
+    path=path}
+}
+
+/// Writes the dev shell files to the app root, returning their paths
+pub fn write_dev_shell(
+    app: &App,
+    plan: &BuildPlan,
+    options: &DevShellOptions,
+) -> Result<Vec<PathBuf>> {
+    if let Some(remote) = &app.remote {
+        bail!(
+            "A dev shell can only be written to a local app, not {}",
+            remote
+        );
+    }
+
+    let mut files = vec![(SHELL_NIX, create_shell_expression(plan))];
+    if options.direnv {
+        files.push((ENVRC, "use nix\n".to_string()));
+    }
+
+    if !options.force {
+        let existing = files
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| app.source.join(name).exists())
+            .collect::<Vec<_>>();
+        if !existing.is_empty() {
+            bail!(
+                "{} already exists in the app, use --force to overwrite it",
+                existing.join(" and ")
+            );
+        }
+    }
+
+    let mut paths = Vec::new();
+    for (name, contents) in files {
+        let path = app.source.join(name);
+        fs::write(&path, contents).with_context(|| format!("Writing {}", name))?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nixpacks::{
+        nix::pkg::Pkg,
+        phase::{InstallPhase, SetupPhase},
+    };
+    use tempdir::TempDir;
+
+    fn node_plan() -> BuildPlan {
+        let mut setup = SetupPhase::new(vec![Pkg::new("nodejs")]);
+        setup.add_libraries(vec!["libuuid".to_string()]);
+        let mut install = InstallPhase::new("npm ci".to_string());
+        install.add_path("/app/node_modules/.bin".to_string());
+
+        BuildPlan {
+            version: None,
+            setup: Some(setup),
+            install: Some(install),
+            build: None,
+            start: None,
+            variables: None,
+            static_assets: None,
+            dockerfile: None,
+        }
+    }
+
+    #[test]
+    fn test_shell_expression() {
+        let expression = create_shell_expression(&node_plan());
+        assert!(expression.contains("pkgs = import <nixpkgs> { };"));
+        assert!(expression.contains("nodejs"));
+        assert!(expression.contains("lib.makeLibraryPath [ libuuid ]"));
+        assert!(expression.contains("source ${env}/etc/profile.d/libraries.sh"));
+        assert!(expression.contains("export PATH=\"${toString ./.}/node_modules/.bin:$PATH\""));
+    }
+
+    #[test]
+    fn test_write_dev_shell() -> Result<()> {
+        let dir = TempDir::new("nixpacks-shell")?;
+        let app = App::new(dir.path().to_str().unwrap())?;
+        let options = DevShellOptions {
+            direnv: true,
+            force: false,
+        };
+
+        let paths = write_dev_shell(&app, &node_plan(), &options)?;
+        assert_eq!(
+            paths,
+            vec![app.source.join(SHELL_NIX), app.source.join(ENVRC)]
+        );
+        assert_eq!(fs::read_to_string(app.source.join(ENVRC))?, "use nix\n");
+
+        // Existing files are only overwritten when forced
+        assert!(write_dev_shell(&app, &node_plan(), &options).is_err());
+        write_dev_shell(
+            &app,
+            &node_plan(),
+            &DevShellOptions {
+                force: true,
+                ..options
+            },
+        )?;
+
+        Ok(())
+    }
+}
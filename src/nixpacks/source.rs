@@ -0,0 +1,366 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempdir::TempDir;
+
+const ARCHIVE_EXTENSIONS: &[&str] = &[".tar.gz", ".tgz", ".zip"];
+
+/// Marks a URL as a git repo when it doesn't end in `.git`, e.g. `git+file:///srv/repo`
+const GIT_PREFIX: &str = "git+";
+
+/// Where the app is read from
+///
+/// Git URLs and archives follow the `docker build` context syntax, with the
+/// ref and subdirectory after a `#`, e.g. `https://github.com/org/repo.git#main:web`.
+/// Other URLs are cloned with a `git+` prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppSource {
+    Local(String),
+    Git {
+        url: String,
+        git_ref: Option<String>,
+        subdir: Option<String>,
+    },
+    Archive {
+        location: String,
+        subdir: Option<String>,
+    },
+}
+
+impl AppSource {
+    pub fn parse(source: &str) -> AppSource {
+        let (location, fragment) = match source.split_once('#') {
+            Some((location, fragment)) => (location, Some(fragment)),
+            None => (source, None),
+        };
+
+        let git_url = location.strip_prefix(GIT_PREFIX);
+        if git_url.is_some() || is_git_url(location) {
+            let (git_ref, subdir) = match fragment.and_then(|f| f.split_once(':')) {
+                Some((git_ref, subdir)) => (git_ref, Some(subdir)),
+                None => (fragment.unwrap_or_default(), None),
+            };
+            return AppSource::Git {
+                url: git_url.unwrap_or(location).to_string(),
+                git_ref: non_empty(git_ref),
+                subdir: subdir.and_then(non_empty),
+            };
+        }
+
+        if ARCHIVE_EXTENSIONS
+            .iter()
+            .any(|extension| location.ends_with(extension))
+        {
+            return AppSource::Archive {
+                location: location.to_string(),
+                subdir: fragment.and_then(non_empty),
+            };
+        }
+
+        AppSource::Local(source.to_string())
+    }
+
+    pub fn is_remote(&self) -> bool {
+        !matches!(self, AppSource::Local(_))
+    }
+
+    /// Checks out or extracts the app into `dir`, returning the app directory in it
+    ///
+    /// Local sources are returned as is.
+    pub fn materialize(&self, dir: &Path) -> Result<PathBuf> {
+        match self {
+            AppSource::Local(path) => Ok(PathBuf::from(path)),
+            AppSource::Git {
+                url,
+                git_ref,
+                subdir,
+            } => {
+                let checkout = dir.join("checkout");
+                clone_repo(url, git_ref.as_deref(), &checkout)?;
+                get_subdir(&checkout, subdir.as_deref())
+            }
+            AppSource::Archive { location, subdir } => {
+                let archive = if is_url(location) {
+                    let name = location.rsplit('/').next().unwrap_or("archive");
+                    let archive = dir.join(name);
+                    run(
+                        Command::new("curl")
+                            .args(["-fsSL", "-o"])
+                            .arg(&archive)
+                            .arg(location),
+                        &format!("Downloading {}", location),
+                    )?;
+                    archive
+                } else {
+                    PathBuf::from(location)
+                };
+
+                let extracted = dir.join("extracted");
+                fs::create_dir(&extracted)?;
+                extract_archive(&archive, &extracted)?;
+                get_subdir(&get_archive_root(&extracted)?, subdir.as_deref())
+            }
+        }
+    }
+}
+
+/// Materializes a source in a temp directory that lives as long as the app
+pub fn checkout(source: &AppSource) -> Result<(PathBuf, Option<TempDir>)> {
+    if !source.is_remote() {
+        return Ok((source.materialize(Path::new(""))?, None));
+    }
+
+    let dir = TempDir::new("nixpacks-source").context("Creating a temp directory")?;
+    let path = source.materialize(dir.path())?;
+    Ok((path, Some(dir)))
+}
+
+fn is_url(location: &str) -> bool {
+    ["http://", "https://", "file://"]
+        .iter()
+        .any(|scheme| location.starts_with(scheme))
+}
+
+fn is_git_url(location: &str) -> bool {
+    ["git://", "git@", "ssh://"]
+        .iter()
+        .any(|prefix| location.starts_with(prefix))
+        || (is_url(location) && location.trim_end_matches('/').ends_with(".git"))
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn clone_repo(url: &str, git_ref: Option<&str>, dest: &Path) -> Result<()> {
+    let mut clone = Command::new("git");
+    clone.args(["clone", "--quiet", "--depth", "1"]);
+    let git_ref = match git_ref {
+        Some(git_ref) => git_ref,
+        None => return run(clone.arg(url).arg(dest), &format!("Cloning {}", url)),
+    };
+
+    clone.args(["--branch", git_ref]).arg(url).arg(dest);
+    if run(&mut clone, &format!("Cloning {}", url)).is_ok() {
+        return Ok(());
+    }
+
+    // Commits can't be cloned with `--branch`, so check them out from a full clone
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    run(
+        Command::new("git")
+            .args(["clone", "--quiet"])
+            .arg(url)
+            .arg(dest),
+        &format!("Cloning {}", url),
+    )?;
+    run(
+        Command::new("git")
+            .arg("-C")
+            .arg(dest)
+            .args(["checkout", "--quiet", git_ref]),
+        &format!("Checking out {}", git_ref),
+    )
+}
+
+fn extract_archive(archive: &Path, dest: &Path) -> Result<()> {
+    let is_zip = archive
+        .extension()
+        .is_some_and(|extension| extension == "zip");
+    let mut command = if is_zip {
+        let mut command = Command::new("unzip");
+        command.arg("-q").arg(archive).arg("-d").arg(dest);
+        command
+    } else {
+        let mut command = Command::new("tar");
+        command.arg("-xzf").arg(archive).arg("-C").arg(dest);
+        command
+    };
+
+    run(&mut command, &format!("Extracting {}", archive.display()))
+}
+
+/// Archives of a single directory (e.g. GitHub tarballs) contain the app in that directory
+fn get_archive_root(extracted: &Path) -> Result<PathBuf> {
+    let entries = fs::read_dir(extracted)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match entries.as_slice() {
+        [dir] if dir.is_dir() => Ok(dir.clone()),
+        _ => Ok(extracted.to_path_buf()),
+    }
+}
+
+fn get_subdir(root: &Path, subdir: Option<&str>) -> Result<PathBuf> {
+    let subdir = match subdir {
+        Some(subdir) => subdir.trim_matches('/'),
+        None => return Ok(root.to_path_buf()),
+    };
+
+    let path = root.join(subdir);
+    if subdir.split('/').any(|part| part == "..") || !path.is_dir() {
+        bail!("Directory {} does not exist in the source", subdir);
+    }
+    Ok(path)
+}
+
+fn run(command: &mut Command, action: &str) -> Result<()> {
+    let output = command
+        .output()
+        .with_context(|| format!("{}: failed to run {:?}", action, command.get_program()))?;
+
+    if !output.status.success() {
+        bail!(
+            "{} failed\n{}",
+            action,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "nixpacks")
+            .env("GIT_AUTHOR_EMAIL", "nixpacks@example.com")
+            .env("GIT_COMMITTER_NAME", "nixpacks")
+            .env("GIT_COMMITTER_EMAIL", "nixpacks@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    /// A bare repo at `repo.git` with the node example in `web` on a `release` branch
+    fn create_bare_repo(dir: &Path) -> String {
+        let work = dir.join("work");
+        fs::create_dir_all(work.join("web")).unwrap();
+        fs::write(work.join("README.md"), "# Monorepo").unwrap();
+        for file in ["package.json", "index.js"] {
+            fs::copy(
+                env::current_dir().unwrap().join("examples/node").join(file),
+                work.join("web").join(file),
+            )
+            .unwrap();
+        }
+
+        git(&work, &["init", "--quiet", "--initial-branch", "main"]);
+        git(&work, &["add", "-A"]);
+        git(&work, &["commit", "--quiet", "-m", "Initial commit"]);
+        git(&work, &["checkout", "--quiet", "-b", "release"]);
+        fs::write(work.join("web/RELEASE"), "1.0.0").unwrap();
+        git(&work, &["add", "-A"]);
+        git(&work, &["commit", "--quiet", "-m", "Release"]);
+        git(&work, &["checkout", "--quiet", "main"]);
+
+        let bare = dir.join("repo.git");
+        let status = Command::new("git")
+            .args(["clone", "--quiet", "--bare"])
+            .arg(&work)
+            .arg(&bare)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        format!("file://{}", bare.display())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            AppSource::parse("./examples/node"),
+            AppSource::Local("./examples/node".to_string())
+        );
+        assert_eq!(
+            AppSource::parse("https://github.com/org/repo.git#main:apps/web"),
+            AppSource::Git {
+                url: "https://github.com/org/repo.git".to_string(),
+                git_ref: Some("main".to_string()),
+                subdir: Some("apps/web".to_string()),
+            }
+        );
+        assert_eq!(
+            AppSource::parse("git@github.com:org/repo#:web"),
+            AppSource::Git {
+                url: "git@github.com:org/repo".to_string(),
+                git_ref: None,
+                subdir: Some("web".to_string()),
+            }
+        );
+        assert_eq!(
+            AppSource::parse("git+file:///srv/repo#main"),
+            AppSource::Git {
+                url: "file:///srv/repo".to_string(),
+                git_ref: Some("main".to_string()),
+                subdir: None,
+            }
+        );
+        assert_eq!(
+            AppSource::parse("https://example.com/app.tar.gz#web"),
+            AppSource::Archive {
+                location: "https://example.com/app.tar.gz".to_string(),
+                subdir: Some("web".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_git_source() -> Result<()> {
+        let dir = TempDir::new("nixpacks-repo")?;
+        let url = create_bare_repo(dir.path());
+
+        let (path, _checkout) = checkout(&AppSource::parse(&url))?;
+        assert!(path.join("README.md").is_file());
+
+        let (path, _checkout) = checkout(&AppSource::parse(&format!("{}#release:web", url)))?;
+        assert!(path.join("package.json").is_file());
+        assert!(path.join("RELEASE").is_file());
+
+        assert!(checkout(&AppSource::parse(&format!("{}#main:missing", url))).is_err());
+
+        // Repos without the `.git` suffix are cloned with the prefix
+        fs::rename(dir.path().join("repo.git"), dir.path().join("repo"))?;
+        let url = format!("git+{}", url.trim_end_matches(".git"));
+        let (path, _checkout) = checkout(&AppSource::parse(&format!("{}#release:web", url)))?;
+        assert!(path.join("RELEASE").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_source() -> Result<()> {
+        let dir = TempDir::new("nixpacks-archive")?;
+        let archive = dir.path().join("app.tar.gz");
+        let status = Command::new("tar")
+            .arg("-czf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(env::current_dir()?.join("examples"))
+            .arg("node")
+            .status()?;
+        assert!(status.success());
+
+        // The single top level directory is the app
+        let (path, _checkout) = checkout(&AppSource::parse(archive.to_str().unwrap()))?;
+        assert!(path.join("package.json").is_file());
+
+        Ok(())
+    }
+}
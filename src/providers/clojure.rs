@@ -0,0 +1,85 @@
+use super::{java::JavaProvider, Provider};
+use crate::nixpacks::{
+    app::App,
+    environment::Environment,
+    nix::pkg::Pkg,
+    phase::{BuildPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+use regex::Regex;
+
+pub struct ClojureProvider {}
+
+impl Provider for ClojureProvider {
+    fn name(&self) -> &str {
+        "clojure"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["project.clj", "deps.edn"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.clj"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        Ok(app.includes_file("project.clj") || app.includes_file("deps.edn"))
+    }
+
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        let jdk_pkg = JavaProvider::get_jdk_pkg(app, env)?;
+        let is_default_jdk = jdk_pkg.name == JavaProvider::default_jdk_pkg_name();
+
+        let mut build_tool = if ClojureProvider::is_leiningen(app) {
+            Pkg::new("leiningen")
+        } else {
+            Pkg::new("clojure")
+        };
+        if !is_default_jdk {
+            build_tool = build_tool.set_override("jdk", &jdk_pkg.name);
+        }
+
+        Ok(Some(SetupPhase::new(vec![build_tool, jdk_pkg])))
+    }
+
+    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        if ClojureProvider::is_leiningen(app) {
+            return Ok(Some(BuildPhase::new("lein uberjar".to_string())));
+        }
+
+        // tools.build
+        if app.includes_file("build.clj") {
+            return Ok(Some(BuildPhase::new("clojure -T:build uber".to_string())));
+        }
+
+        Ok(None)
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        if ClojureProvider::is_leiningen(app) {
+            return Ok(Some(StartPhase::new(
+                "java $JAVA_OPTS -jar target/uberjar/*standalone.jar".to_string(),
+            )));
+        }
+
+        if app.includes_file("build.clj") {
+            return Ok(Some(StartPhase::new(
+                "java $JAVA_OPTS -jar target/*standalone.jar".to_string(),
+            )));
+        }
+
+        // Run the main namespace from source
+        let re = Regex::new(r#""-m"\s+"([^"]+)""#)?;
+        let deps_edn = app.read_file("deps.edn")?;
+        Ok(re
+            .captures(&deps_edn)
+            .map(|captures| StartPhase::new(format!("clojure -M -m {}", &captures[1]))))
+    }
+}
+
+impl ClojureProvider {
+    fn is_leiningen(app: &App) -> bool {
+        app.includes_file("project.clj")
+    }
+}
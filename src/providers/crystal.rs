@@ -24,6 +24,14 @@ impl Provider for CrystalProvider {
         "crystal"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["shard.yml"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.cr"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("shard.yml"))
     }
@@ -49,7 +57,7 @@ impl Provider for CrystalProvider {
         let start_phase = StartPhase::new(format!(
             "./bin/{}",
             target_names
-                .get(0)
+                .first()
                 .ok_or_else(|| anyhow::anyhow!("Unable to get executable name"))?
         ));
 
@@ -1,72 +1,51 @@
-use super::Provider;
+use super::{dotnet, Provider};
 use crate::nixpacks::{
     app::App,
     environment::{Environment, EnvironmentVariables},
-    nix::pkg::Pkg,
     phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
 };
-use anyhow::{Context, Result};
+use anyhow::Result;
 
 pub struct CSharpProvider {}
 
-pub const ARTIFACT_DIR: &'static &str = &"out";
-
 impl Provider for CSharpProvider {
     fn name(&self) -> &str {
         "csharp"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["*.csproj"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.cs"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
-        Ok(!app.find_files("*.csproj")?.is_empty())
+        dotnet::detect(app, "csproj")
     }
 
-    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
-        Ok(Some(SetupPhase::new(vec![Pkg::new("dotnet-sdk")])))
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        dotnet::setup(app, env, "csproj")
     }
 
-    fn install(&self, _app: &App, _env: &Environment) -> Result<Option<InstallPhase>> {
-        Ok(Some(InstallPhase::new("dotnet restore".to_string())))
+    fn install(&self, app: &App, env: &Environment) -> Result<Option<InstallPhase>> {
+        dotnet::install(app, env, "csproj")
     }
 
-    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
-        Ok(Some(BuildPhase::new(format!(
-            "dotnet publish --no-restore -c Release -o {}",
-            ARTIFACT_DIR
-        ))))
+    fn build(&self, app: &App, env: &Environment) -> Result<Option<BuildPhase>> {
+        dotnet::build(app, env, "csproj")
     }
 
-    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
-        let csproj = &app.find_files("*.csproj")?[0].with_extension("");
-        let project_name = csproj
-            .file_name()
-            .context("Invalid file_name")?
-            .to_str()
-            .context("Invalid project_name")?;
-        Ok(Some(StartPhase::new(format!(
-            "./{}/{}",
-            ARTIFACT_DIR, project_name
-        ))))
+    fn start(&self, app: &App, env: &Environment) -> Result<Option<StartPhase>> {
+        dotnet::start(app, env, "csproj")
     }
 
     fn environment_variables(
         &self,
         _app: &App,
-        _env: &Environment,
+        env: &Environment,
     ) -> Result<Option<EnvironmentVariables>> {
-        let env_vars = EnvironmentVariables::from([
-            (
-                "ASPNETCORE_ENVIRONMENT".to_string(),
-                "Production".to_string(),
-            ),
-            (
-                "ASPNETCORE_URLS".to_string(),
-                "http://0.0.0.0:3000".to_string(),
-            ),
-            (
-                "DOTNET_ROOT".to_string(),
-                "/nix/var/nix/profiles/default/".to_string(),
-            ),
-        ]);
-        Ok(Some(env_vars))
+        dotnet::environment_variables(env)
     }
 }
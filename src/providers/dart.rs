@@ -23,6 +23,14 @@ impl Provider for DartProvider {
         "dart"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["pubspec.yaml"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.dart"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("pubspec.yaml"))
     }
@@ -17,6 +17,10 @@ impl Provider for DenoProvider {
         "deno"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["deno.json", "deno.jsonc", "*.ts importing deno.land"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         let re = Regex::new(r##"(?m)^import .+ from "https://deno.land/[^"]+\.ts";?$"##).unwrap();
         Ok(app.includes_file("deno.json")
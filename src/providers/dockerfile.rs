@@ -0,0 +1,507 @@
+use super::Provider;
+use crate::nixpacks::{
+    app::App,
+    environment::{Environment, EnvironmentVariables},
+    nix::pkg::Pkg,
+    phase::{BuildPhase, SetupPhase, StartPhase},
+};
+use anyhow::{Context, Result};
+
+pub const DOCKERFILE: &str = "Dockerfile";
+
+// Images without a language runtime to translate
+const OS_IMAGES: &[&str] = &[
+    "alpine", "busybox", "centos", "debian", "fedora", "scratch", "ubuntu",
+];
+
+/// Directives of a Dockerfile that can be translated into a build plan
+#[derive(Debug, Default, PartialEq)]
+pub struct DockerfileImport {
+    pub images: Vec<String>,
+    pub pkgs: Vec<Pkg>,
+    pub apt_pkgs: Vec<String>,
+    pub cmds: Vec<String>,
+    pub start_cmd: Option<String>,
+    pub variables: EnvironmentVariables,
+    pub ports: Vec<String>,
+    pub healthcheck: Option<String>,
+    /// Parts of the Dockerfile that could not be translated
+    pub warnings: Vec<String>,
+}
+
+/// Fallback for apps with a Dockerfile that no other provider recognizes
+///
+/// Common directives are translated into a plan built with Nix. Setting
+/// `NIXPACKS_DOCKERFILE=build` builds the Dockerfile as is instead.
+pub struct DockerfileProvider {}
+
+impl Provider for DockerfileProvider {
+    fn name(&self) -> &str {
+        "dockerfile"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["Dockerfile"]
+    }
+
+    fn detect(&self, app: &App, env: &Environment) -> Result<bool> {
+        let ignored = matches!(
+            env.get_config_variable("DOCKERFILE").map(|v| v.as_str()),
+            Some("0" | "false")
+        );
+        Ok(app.includes_file(DOCKERFILE) && !ignored)
+    }
+
+    fn setup(&self, app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
+        let dockerfile = DockerfileProvider::import(app)?;
+        // The other phases import the Dockerfile again, so only warn here
+        for warning in &dockerfile.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        let mut setup_phase = SetupPhase::new(dockerfile.pkgs);
+        setup_phase.add_apt_pkgs(dockerfile.apt_pkgs);
+        Ok(Some(setup_phase))
+    }
+
+    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        let dockerfile = DockerfileProvider::import(app)?;
+        if dockerfile.cmds.is_empty() {
+            return Ok(None);
+        }
+
+        let mut build_phase = BuildPhase::default();
+        for cmd in dockerfile.cmds {
+            build_phase.add_cmd(cmd);
+        }
+        Ok(Some(build_phase))
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let dockerfile = DockerfileProvider::import(app)?;
+        let mut start_phase = match dockerfile.start_cmd {
+            Some(cmd) => StartPhase::new(cmd),
+            None => return Ok(None),
+        };
+
+        for port in dockerfile.ports {
+            if let Ok(port) = port.parse() {
+                start_phase.add_port(port);
+            }
+        }
+        if let Some(healthcheck) = dockerfile.healthcheck {
+            start_phase.set_healthcheck_cmd(healthcheck);
+        }
+
+        Ok(Some(start_phase))
+    }
+
+    fn environment_variables(
+        &self,
+        app: &App,
+        _env: &Environment,
+    ) -> Result<Option<EnvironmentVariables>> {
+        let dockerfile = DockerfileProvider::import(app)?;
+        let mut variables = dockerfile.variables;
+
+        // Apps are expected to listen on $PORT
+        if let Some(port) = dockerfile.ports.first() {
+            variables
+                .entry("PORT".to_string())
+                .or_insert_with(|| port.clone());
+        }
+
+        Ok(Some(variables))
+    }
+}
+
+impl DockerfileProvider {
+    pub fn import(app: &App) -> Result<DockerfileImport> {
+        let contents = app.read_file(DOCKERFILE).context("Reading Dockerfile")?;
+        Ok(parse_dockerfile(&contents))
+    }
+}
+
+pub fn parse_dockerfile(contents: &str) -> DockerfileImport {
+    let mut dockerfile = DockerfileImport::default();
+    let mut entrypoint: Option<String> = None;
+    let mut cmd: Option<String> = None;
+    let mut skipped = Vec::new();
+
+    for instruction in get_instructions(contents) {
+        let (directive, args) = instruction
+            .split_once(char::is_whitespace)
+            .map(|(directive, args)| (directive, args.trim()))
+            .unwrap_or((instruction.as_str(), ""));
+
+        match directive.to_uppercase().as_str() {
+            "FROM" => {
+                // e.g. `FROM --platform=linux/amd64 node:18 AS build`
+                let image = match args.split_whitespace().find(|arg| !arg.starts_with("--")) {
+                    Some(image) => image.to_string(),
+                    None => continue,
+                };
+                let mut pkgs = match get_image_pkgs(&image) {
+                    Some(pkgs) => pkgs,
+                    None => {
+                        dockerfile.warnings.push(format!(
+                            "No Nix packages known for the {} image, set NIXPACKS_PKGS to add the runtime",
+                            image
+                        ));
+                        Vec::new()
+                    }
+                };
+                dockerfile.pkgs.retain(|pkg| !pkgs.contains(pkg));
+                dockerfile.pkgs.append(&mut pkgs);
+                dockerfile.images.push(image);
+                // The runtime of each stage is installed, but only the final command is kept
+                entrypoint = None;
+                cmd = None;
+            }
+            "RUN" => {
+                if let Some(run_cmd) = translate_run(&parse_cmd(args), &mut dockerfile.apt_pkgs) {
+                    dockerfile.cmds.push(run_cmd);
+                }
+            }
+            "CMD" => cmd = Some(parse_cmd(args)),
+            "ENTRYPOINT" => entrypoint = Some(parse_cmd(args)),
+            "ENV" => dockerfile.variables.extend(parse_env(args)),
+            "EXPOSE" => dockerfile.ports.extend(
+                args.split_whitespace()
+                    // `8080/tcp`
+                    .map(|port| port.split('/').next().unwrap_or_default().to_string()),
+            ),
+            "HEALTHCHECK" => {
+                // Options such as `--interval` are dropped
+                dockerfile.healthcheck = args
+                    .split_once("CMD")
+                    .map(|(_, cmd)| parse_cmd(cmd.trim()))
+                    .filter(|cmd| !cmd.is_empty());
+            }
+            "ARG" | "LABEL" | "MAINTAINER" => {}
+            other => {
+                if !skipped.contains(&other.to_string()) {
+                    skipped.push(other.to_string());
+                }
+            }
+        }
+    }
+
+    dockerfile.start_cmd = match (entrypoint, cmd) {
+        (Some(entrypoint), Some(cmd)) => Some(format!("{} {}", entrypoint, cmd)),
+        (entrypoint, cmd) => entrypoint.or(cmd),
+    };
+
+    if !skipped.is_empty() {
+        dockerfile.warnings.push(format!(
+            "Dockerfile directives {} were not imported",
+            skipped.join(", ")
+        ));
+    }
+
+    dockerfile
+}
+
+/// Join line continuations and drop comments and blank lines
+fn get_instructions(contents: &str) -> Vec<String> {
+    let mut instructions = Vec::new();
+    let mut current = String::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || (line.is_empty() && current.is_empty()) {
+            continue;
+        }
+
+        match line.strip_suffix('\\') {
+            Some(line) => {
+                current.push_str(line.trim());
+                current.push(' ');
+            }
+            None => {
+                current.push_str(line);
+                if !current.trim().is_empty() {
+                    instructions.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+        }
+    }
+
+    if !current.trim().is_empty() {
+        instructions.push(current.trim().to_string());
+    }
+
+    instructions
+}
+
+/// Exec form (`["npm", "start"]`) or shell form (`npm start`) as a shell command
+fn parse_cmd(args: &str) -> String {
+    match serde_json::from_str::<Vec<String>>(args) {
+        Ok(parts) => parts
+            .iter()
+            .map(|part| {
+                if part.is_empty() || part.contains(char::is_whitespace) {
+                    format!("'{}'", part.replace('\'', "'\\''"))
+                } else {
+                    part.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        Err(_) => args.to_string(),
+    }
+}
+
+/// `ENV A=1 B="two words"` or the legacy `ENV A 1`
+fn parse_env(args: &str) -> EnvironmentVariables {
+    let mut variables = EnvironmentVariables::new();
+
+    if !args
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .contains('=')
+    {
+        if let Some((name, value)) = args.split_once(char::is_whitespace) {
+            variables.insert(name.to_string(), unquote(value.trim()));
+        }
+        return variables;
+    }
+
+    let mut pairs = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    for c in args.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    pairs.push(current.clone());
+                    current.clear();
+                }
+            }
+            (c, _) => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        pairs.push(current);
+    }
+
+    for pair in pairs {
+        if let Some((name, value)) = pair.split_once('=') {
+            variables.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    variables
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// Moves `apt-get install` packages to the setup phase and returns the rest of the command
+fn translate_run(cmd: &str, apt_pkgs: &mut Vec<String>) -> Option<String> {
+    let mut remaining = Vec::new();
+
+    for part in cmd.split("&&").map(|part| part.trim()) {
+        let words = part.split_whitespace().collect::<Vec<_>>();
+        let apt_args = match words.as_slice() {
+            ["apt-get" | "apt", "install", args @ ..] => Some(args),
+            ["apt-get" | "apt", "update", ..] => Some(&[][..]),
+            ["rm", "-rf", "/var/lib/apt/lists/*"] => Some(&[][..]),
+            _ => None,
+        };
+
+        match apt_args {
+            Some(args) => {
+                for pkg in args.iter().filter(|arg| !arg.starts_with('-')) {
+                    if !apt_pkgs.contains(&pkg.to_string()) {
+                        apt_pkgs.push(pkg.to_string());
+                    }
+                }
+            }
+            None => remaining.push(part),
+        }
+    }
+
+    if remaining.is_empty() {
+        None
+    } else {
+        Some(remaining.join(" && "))
+    }
+}
+
+/// Nix packages providing the runtime of an official language image, e.g. `node:18-alpine`
+/// Packages with the runtime of an image, `None` for unknown images
+fn get_image_pkgs(image: &str) -> Option<Vec<Pkg>> {
+    // Strip the registry and `library/` namespace
+    let (name, tag) = image.rsplit_once(':').unwrap_or((image, ""));
+    let name = name.rsplit('/').next().unwrap_or(name);
+    // `3.10-slim-bullseye` -> `3.10`
+    let version = tag.split('-').next().unwrap_or_default();
+    let mut parts = version.split('.');
+    let major = parts.next().unwrap_or_default();
+    let minor = parts.next().unwrap_or_default();
+
+    let versioned = |pkg: String, available: &[&str], default: &str| {
+        if available.contains(&version) || available.contains(&major) {
+            Pkg::new(&pkg)
+        } else {
+            Pkg::new(default)
+        }
+    };
+    let minor_version = format!("{}.{}", major, minor);
+
+    let pkgs = match name {
+        "node" => vec![versioned(
+            format!("nodejs-{}_x", major),
+            &["10", "12", "14", "16", "18"],
+            "nodejs",
+        )],
+        "python" => vec![if ["2.7", "3.7", "3.8", "3.9", "3.10", "3.11"]
+            .contains(&minor_version.as_str())
+        {
+            Pkg::new(&format!("python{}{}", major, minor))
+        } else {
+            Pkg::new("python3")
+        }],
+        "golang" => vec![if ["1.17", "1.18"].contains(&minor_version.as_str()) {
+            Pkg::new(&format!("go_{}_{}", major, minor))
+        } else {
+            Pkg::new("go")
+        }],
+        "ruby" => vec![if ["2.7", "3.0", "3.1"].contains(&minor_version.as_str()) {
+            Pkg::new(&format!("ruby_{}_{}", major, minor))
+        } else {
+            Pkg::new("ruby")
+        }],
+        "php" => vec![if ["7.4", "8.0", "8.1"].contains(&minor_version.as_str()) {
+            Pkg::new(&format!("php{}{}", major, minor))
+        } else {
+            Pkg::new("php")
+        }],
+        "openjdk" | "eclipse-temurin" | "amazoncorretto" => vec![versioned(
+            format!("jdk{}", major),
+            &["8", "11", "17", "18"],
+            "jdk",
+        )],
+        "maven" => vec![Pkg::new("maven")],
+        "gradle" => vec![Pkg::new("gradle")],
+        "rust" => vec![Pkg::new("rustc"), Pkg::new("cargo")],
+        "deno" => vec![Pkg::new("deno")],
+        "elixir" => vec![Pkg::new("elixir")],
+        "erlang" => vec![Pkg::new("erlang")],
+        "crystal" => vec![Pkg::new("crystal")],
+        "dart" => vec![Pkg::new("dart")],
+        "swift" => vec![Pkg::new("swift")],
+        "nginx" => vec![Pkg::new("nginx")],
+        name if OS_IMAGES.contains(&name) => vec![],
+        _ => return None,
+    };
+
+    Some(pkgs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_image_pkgs() {
+        assert_eq!(
+            get_image_pkgs("node:18-alpine"),
+            Some(vec![Pkg::new("nodejs-18_x")])
+        );
+        assert_eq!(get_image_pkgs("node"), Some(vec![Pkg::new("nodejs")]));
+        assert_eq!(
+            get_image_pkgs("docker.io/library/python:3.10-slim"),
+            Some(vec![Pkg::new("python310")])
+        );
+        assert_eq!(
+            get_image_pkgs("golang:1.18"),
+            Some(vec![Pkg::new("go_1_18")])
+        );
+        // Not in the pinned nixpkgs
+        assert_eq!(get_image_pkgs("golang:1.20"), Some(vec![Pkg::new("go")]));
+        assert_eq!(
+            get_image_pkgs("eclipse-temurin:17-jre"),
+            Some(vec![Pkg::new("jdk17")])
+        );
+        assert_eq!(get_image_pkgs("debian:bullseye"), Some(vec![]));
+        assert_eq!(get_image_pkgs("example/custom-runtime"), None);
+    }
+
+    #[test]
+    fn test_translate_run() {
+        let mut apt_pkgs = Vec::new();
+        assert_eq!(
+            translate_run(
+                "apt-get update && apt-get install -y --no-install-recommends curl git && rm -rf /var/lib/apt/lists/*",
+                &mut apt_pkgs
+            ),
+            None
+        );
+        assert_eq!(apt_pkgs, vec!["curl".to_string(), "git".to_string()]);
+
+        assert_eq!(
+            translate_run("apt-get update && make install", &mut apt_pkgs),
+            Some("make install".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_env() {
+        assert_eq!(
+            parse_env(r#"A=1 B="two words""#),
+            EnvironmentVariables::from([
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "two words".to_string())
+            ])
+        );
+        assert_eq!(
+            parse_env("LEGACY some value"),
+            EnvironmentVariables::from([("LEGACY".to_string(), "some value".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_parse_dockerfile() {
+        let dockerfile = parse_dockerfile(
+            r#"
+            # syntax=docker/dockerfile:1
+            FROM golang:1.18 AS build
+            RUN go build -o /bin/app .
+
+            FROM debian:bullseye-slim
+            ENV MODE=production
+            EXPOSE 8080/tcp
+            ENTRYPOINT ["/bin/app"]
+            CMD ["--listen", ":8080"]
+            "#,
+        );
+
+        assert_eq!(dockerfile.pkgs, vec![Pkg::new("go_1_18")]);
+        assert_eq!(dockerfile.cmds, vec!["go build -o /bin/app .".to_string()]);
+        assert_eq!(
+            dockerfile.start_cmd,
+            Some("/bin/app --listen :8080".to_string())
+        );
+        assert_eq!(dockerfile.ports, vec!["8080".to_string()]);
+        assert!(dockerfile.warnings.is_empty());
+
+        let dockerfile = parse_dockerfile("FROM example/runtime\nUSER app\nCMD ./app");
+        assert_eq!(
+            dockerfile.warnings,
+            vec![
+                "No Nix packages known for the example/runtime image, set NIXPACKS_PKGS to add the runtime".to_string(),
+                "Dockerfile directives USER were not imported".to_string()
+            ]
+        );
+    }
+}
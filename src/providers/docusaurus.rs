@@ -0,0 +1,63 @@
+use super::{node::NodeProvider, staticfile::StaticfileProvider, Provider};
+use crate::nixpacks::{
+    app::{App, StaticAssets},
+    environment::{Environment, EnvironmentVariables},
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+
+const BUILD_DIR: &str = "build";
+
+pub struct DocusaurusProvider {}
+
+impl Provider for DocusaurusProvider {
+    fn name(&self) -> &str {
+        "docusaurus"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["package.json with @docusaurus/core"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        Ok(app.includes_file("package.json")
+            && app
+                .read_file("package.json")?
+                .contains("\"@docusaurus/core\""))
+    }
+
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        NodeProvider {}.setup(app, env)
+    }
+
+    fn install(&self, app: &App, env: &Environment) -> Result<Option<InstallPhase>> {
+        NodeProvider {}.install(app, env)
+    }
+
+    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        let pkg_manager = NodeProvider::get_package_manager(app);
+        if NodeProvider::has_script(app, "build")? {
+            Ok(Some(BuildPhase::new(format!("{} run build", pkg_manager))))
+        } else {
+            Ok(Some(BuildPhase::new("npx docusaurus build".to_string())))
+        }
+    }
+
+    fn static_assets(&self, app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        StaticfileProvider::get_static_site_assets(app, env)
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        Ok(Some(StaticfileProvider::get_static_site_start_phase(
+            app, BUILD_DIR,
+        )))
+    }
+
+    fn environment_variables(
+        &self,
+        _app: &App,
+        _env: &Environment,
+    ) -> Result<Option<EnvironmentVariables>> {
+        Ok(Some(NodeProvider::get_node_environment_variables()))
+    }
+}
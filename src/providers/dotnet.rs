@@ -0,0 +1,363 @@
+//! Logic shared by the C# and F# providers
+use crate::nixpacks::{
+    app::App,
+    environment::{Environment, EnvironmentVariables},
+    nix::pkg::Pkg,
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
+};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+pub const ARTIFACT_DIR: &'static &str = &"out";
+
+const DEFAULT_DOTNET_PKG_NAME: &str = "dotnet-sdk";
+const DEFAULT_DOTNET_VERSION: &str = "6.0";
+const AVAILABLE_DOTNET_VERSIONS: &[(&str, &str)] = &[
+    ("3.1", "dotnet-sdk_3"),
+    ("5.0", "dotnet-sdk_5"),
+    ("6.0", "dotnet-sdk_6"),
+    ("7.0", "dotnet-sdk_7"),
+];
+
+const ASPNET_RUNTIME_IMAGE: &str = "mcr.microsoft.com/dotnet/aspnet";
+const DOTNET_RUNTIME_IMAGE: &str = "mcr.microsoft.com/dotnet/runtime";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GlobalJsonSdk {
+    version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct GlobalJson {
+    sdk: Option<GlobalJsonSdk>,
+}
+
+/// A `.csproj` or `.fsproj` project file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotnetProject {
+    /// Path relative to the app root
+    pub path: String,
+    pub assembly_name: String,
+    pub is_web: bool,
+    pub is_executable: bool,
+    /// e.g. `6.0` for `net6.0`
+    pub framework_version: Option<String>,
+}
+
+impl DotnetProject {
+    fn from_file(app: &App, path: &str) -> Result<DotnetProject> {
+        let contents = app
+            .read_file(path)
+            .with_context(|| format!("Reading {}", path))?;
+
+        let assembly_name =
+            match capture(r"<AssemblyName>\s*([^<\s]+)\s*</AssemblyName>", &contents)? {
+                Some(name) => name,
+                None => path
+                    .rsplit('/')
+                    .next()
+                    .and_then(|file| file.rsplit_once('.'))
+                    .map(|(name, _)| name.to_string())
+                    .context("Invalid project file name")?,
+            };
+
+        let is_web = contents.contains("Sdk=\"Microsoft.NET.Sdk.Web\"");
+        let is_executable = is_web
+            || capture(r"<OutputType>\s*(\w+)\s*</OutputType>", &contents)?
+                .is_some_and(|output_type| output_type.ends_with("Exe"));
+
+        // With several target frameworks the newest is used
+        let framework_version = capture(
+            r"<TargetFrameworks?>\s*([^<]+)\s*</TargetFrameworks?>",
+            &contents,
+        )?
+        .and_then(|frameworks| {
+            frameworks
+                .split(';')
+                .filter_map(parse_target_framework)
+                .max_by(|a, b| compare_versions(a, b))
+        });
+
+        Ok(DotnetProject {
+            path: path.to_string(),
+            assembly_name,
+            is_web,
+            is_executable,
+            framework_version,
+        })
+    }
+}
+
+pub fn detect(app: &App, project_ext: &str) -> Result<bool> {
+    Ok(!app.find_files(&format!("*.{}", project_ext))?.is_empty())
+}
+
+pub fn setup(app: &App, env: &Environment, project_ext: &str) -> Result<Option<SetupPhase>> {
+    let project = get_project(app, env, project_ext)?;
+    let pkg = match get_sdk_version(app, &project)? {
+        Some(version) => match AVAILABLE_DOTNET_VERSIONS
+            .iter()
+            .find(|(v, _)| *v == version)
+        {
+            Some((_, pkg)) => Pkg::new(pkg),
+            None => {
+                eprintln!(
+                    "Warning: .NET {} is not available, using the default SDK",
+                    version
+                );
+                Pkg::new(DEFAULT_DOTNET_PKG_NAME)
+            }
+        },
+        None => Pkg::new(DEFAULT_DOTNET_PKG_NAME),
+    };
+
+    Ok(Some(SetupPhase::new(vec![pkg])))
+}
+
+pub fn install(app: &App, env: &Environment, project_ext: &str) -> Result<Option<InstallPhase>> {
+    let project = get_project(app, env, project_ext)?;
+    Ok(Some(InstallPhase::new(format!(
+        "dotnet restore {}",
+        project.path
+    ))))
+}
+
+pub fn build(app: &App, env: &Environment, project_ext: &str) -> Result<Option<BuildPhase>> {
+    let project = get_project(app, env, project_ext)?;
+    Ok(Some(BuildPhase::new(format!(
+        "dotnet publish {} --no-restore -c Release -o {}",
+        project.path, ARTIFACT_DIR
+    ))))
+}
+
+pub fn start(app: &App, env: &Environment, project_ext: &str) -> Result<Option<StartPhase>> {
+    let project = get_project(app, env, project_ext)?;
+    let runtime_version = project
+        .framework_version
+        .clone()
+        .unwrap_or_else(|| DEFAULT_DOTNET_VERSION.to_string());
+
+    let mut start_phase = if is_run_image_disabled(env) {
+        // The app runs from the build stage, where the output is in its directory
+        StartPhase::new(format!(
+            "dotnet {}/{}.dll",
+            ARTIFACT_DIR, project.assembly_name
+        ))
+    } else {
+        // Only the published output is copied into an image with the runtime, without the SDK
+        let mut start_phase = StartPhase::new(format!("dotnet {}.dll", project.assembly_name));
+        let runtime_image = if project.is_web {
+            ASPNET_RUNTIME_IMAGE
+        } else {
+            DOTNET_RUNTIME_IMAGE
+        };
+        start_phase.run_in_image(format!("{}:{}", runtime_image, runtime_version));
+        start_phase.add_file_dependency(format!("./{}", ARTIFACT_DIR));
+        start_phase
+    };
+    if project.is_web {
+        // See ASPNETCORE_URLS
+        start_phase.add_port(3000);
+    }
+
+    // The other phases publish the same project, so only warn once
+    let executables = get_executable_projects(app, project_ext)?;
+    if env.get_config_variable("CSPROJ").is_none() && executables.len() > 1 {
+        eprintln!(
+            "Warning: Found multiple projects ({}), publishing {}. Set NIXPACKS_CSPROJ to choose another.",
+            executables
+                .iter()
+                .map(|project| project.path.clone())
+                .collect::<Vec<_>>()
+                .join(", "),
+            project.path
+        );
+    }
+
+    Ok(Some(start_phase))
+}
+
+pub fn environment_variables(env: &Environment) -> Result<Option<EnvironmentVariables>> {
+    let mut env_vars = EnvironmentVariables::from([
+        (
+            "ASPNETCORE_ENVIRONMENT".to_string(),
+            "Production".to_string(),
+        ),
+        (
+            "ASPNETCORE_URLS".to_string(),
+            "http://0.0.0.0:3000".to_string(),
+        ),
+    ]);
+
+    // The runtime images have their own .NET, the Nix one is only used without a run image
+    if is_run_image_disabled(env) {
+        env_vars.insert(
+            "DOTNET_ROOT".to_string(),
+            "/nix/var/nix/profiles/default/".to_string(),
+        );
+    }
+
+    Ok(Some(env_vars))
+}
+
+/// Whether the run image is turned off with `NIXPACKS_RUN_IMAGE`
+fn is_run_image_disabled(env: &Environment) -> bool {
+    matches!(
+        env.get_config_variable("RUN_IMAGE").map(String::as_str),
+        Some("0" | "false" | "")
+    )
+}
+
+/// Returns the project to publish
+///
+/// Taken from `NIXPACKS_CSPROJ`, otherwise the executable projects of the
+/// solution (or the app) are considered, preferring web projects.
+pub fn get_project(app: &App, env: &Environment, project_ext: &str) -> Result<DotnetProject> {
+    if let Some(path) = env.get_config_variable("CSPROJ") {
+        let path = path.trim_start_matches("./");
+        if !app.includes_file(path) {
+            bail!(
+                "Project file {} set in NIXPACKS_CSPROJ does not exist",
+                path
+            );
+        }
+        return DotnetProject::from_file(app, path);
+    }
+
+    let projects = get_projects(app, project_ext)?;
+    let executables = projects
+        .iter()
+        .filter(|project| project.is_executable)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    executables
+        .iter()
+        .find(|project| project.is_web)
+        .or_else(|| executables.first())
+        .or_else(|| projects.first())
+        .cloned()
+        .with_context(|| format!("No .{} project found", project_ext))
+}
+
+fn get_projects(app: &App, project_ext: &str) -> Result<Vec<DotnetProject>> {
+    get_project_paths(app, project_ext)?
+        .iter()
+        .map(|path| DotnetProject::from_file(app, path))
+        .collect()
+}
+
+fn get_executable_projects(app: &App, project_ext: &str) -> Result<Vec<DotnetProject>> {
+    Ok(get_projects(app, project_ext)?
+        .into_iter()
+        .filter(|project| project.is_executable)
+        .collect())
+}
+
+/// Projects listed in the solution file, or all the project files in the app
+fn get_project_paths(app: &App, project_ext: &str) -> Result<Vec<String>> {
+    if let Some(solution) = app.find_files("*.sln")?.first() {
+        let solution = app.strip_source_path(solution)?;
+        let solution_dir = solution
+            .parent()
+            .and_then(|dir| dir.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let contents = app.read_file(solution.to_str().unwrap_or_default())?;
+
+        let re = Regex::new(r#"(?m)^Project\("[^"]*"\)\s*=\s*"[^"]*",\s*"([^"]+\.[cf]sproj)""#)?;
+        let paths = re
+            .captures_iter(&contents)
+            .map(|captures| {
+                let path = captures[1].replace('\\', "/");
+                if solution_dir.is_empty() {
+                    path
+                } else {
+                    format!("{}/{}", solution_dir, path)
+                }
+            })
+            .filter(|path| app.includes_file(path))
+            .collect::<Vec<_>>();
+
+        if !paths.is_empty() {
+            return Ok(paths);
+        }
+    }
+
+    app.find_files(&format!("*.{}", project_ext))?
+        .iter()
+        .map(|path| {
+            app.strip_source_path(path)?
+                .to_str()
+                .map(|path| path.to_string())
+                .context("Invalid project path")
+        })
+        .collect()
+}
+
+/// The SDK version pinned in `global.json`, falling back to the project's target framework
+fn get_sdk_version(app: &App, project: &DotnetProject) -> Result<Option<String>> {
+    if app.includes_file("global.json") {
+        let global_json: GlobalJson = app.read_json("global.json")?;
+        if let Some(version) = global_json.sdk.and_then(|sdk| sdk.version) {
+            let minor_version = version.splitn(3, '.').take(2).collect::<Vec<_>>().join(".");
+            return Ok(Some(minor_version));
+        }
+    }
+
+    Ok(project.framework_version.clone())
+}
+
+/// `net6.0` -> `6.0`, `netcoreapp3.1` -> `3.1`
+///
+/// .NET Framework and .NET Standard targets are ignored
+fn parse_target_framework(framework: &str) -> Option<String> {
+    let framework = framework.trim();
+    let version = framework
+        .strip_prefix("netcoreapp")
+        .or_else(|| framework.strip_prefix("net"))?;
+
+    // e.g. `net6.0-windows`
+    let version = version.split('-').next()?;
+    if !version.contains('.') || version.parse::<f32>().is_err() {
+        return None;
+    }
+    Some(version.to_string())
+}
+
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |v: &str| {
+        v.split('.')
+            .map(|part| part.parse::<u32>().unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+    parse(a).cmp(&parse(b))
+}
+
+fn capture(re: &str, contents: &str) -> Result<Option<String>> {
+    Ok(Regex::new(re)?
+        .captures(contents)
+        .and_then(|captures| captures.get(1))
+        .map(|m| m.as_str().trim().to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_target_framework() {
+        assert_eq!(parse_target_framework("net6.0"), Some("6.0".to_string()));
+        assert_eq!(
+            parse_target_framework("netcoreapp3.1"),
+            Some("3.1".to_string())
+        );
+        assert_eq!(
+            parse_target_framework("net7.0-windows"),
+            Some("7.0".to_string())
+        );
+        assert_eq!(parse_target_framework("net48"), None);
+        assert_eq!(parse_target_framework("netstandard2.0"), None);
+    }
+}
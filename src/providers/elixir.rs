@@ -0,0 +1,201 @@
+use super::{node::NodeProvider, Provider};
+use crate::nixpacks::{
+    app::App,
+    environment::{Environment, EnvironmentVariables},
+    nix::pkg::Pkg,
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
+};
+use anyhow::{Context, Result};
+use regex::Regex;
+
+const DEFAULT_ELIXIR_PKG_NAME: &str = "elixir";
+// Only versions packaged in the pinned nixpkgs archive
+const AVAILABLE_ELIXIR_VERSIONS: &[(&str, &str)] =
+    &[("1.12", "elixir_1_12"), ("1.13", "elixir_1_13")];
+
+pub struct ElixirProvider {}
+
+impl Provider for ElixirProvider {
+    fn name(&self) -> &str {
+        "elixir"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["mix.exs"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.ex", "**/*.exs"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        Ok(app.includes_file("mix.exs"))
+    }
+
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        let mut pkgs = vec![ElixirProvider::get_elixir_pkg(app, env)?];
+
+        // Phoenix apps that bundle assets with npm
+        if app.includes_file("assets/package.json") {
+            pkgs.push(Pkg::new("nodejs"));
+        }
+
+        Ok(Some(SetupPhase::new(pkgs)))
+    }
+
+    fn install(&self, app: &App, _env: &Environment) -> Result<Option<InstallPhase>> {
+        let mut install_phase = InstallPhase::new(
+            "mix local.hex --force && mix local.rebar --force && mix deps.get --only prod"
+                .to_string(),
+        );
+
+        if app.includes_file("assets/package.json") {
+            let pkg_manager = if app.includes_file("assets/yarn.lock") {
+                "yarn"
+            } else {
+                "npm"
+            };
+            install_phase.add_cmd(format!("{} install --prefix assets", pkg_manager));
+        }
+
+        Ok(Some(install_phase))
+    }
+
+    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        let mut build_phase = BuildPhase::new("mix compile".to_string());
+
+        if ElixirProvider::is_phoenix(app)? {
+            // Phoenix 1.6+ generates an alias that builds and digests the assets
+            if ElixirProvider::read_mix_exs(app)?.contains("\"assets.deploy\"") {
+                build_phase.add_cmd("mix assets.deploy".to_string());
+            } else if app.includes_directory("priv/static") {
+                build_phase.add_cmd("mix phx.digest".to_string());
+            }
+        }
+
+        build_phase.add_cmd("mix release".to_string());
+
+        Ok(Some(build_phase))
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let app_name = ElixirProvider::get_app_name(app)?;
+        let release_dir = format!("_build/prod/rel/{}/bin", app_name);
+
+        let mut start_phase = StartPhase::new(format!("{}/{} start", release_dir, app_name));
+
+        // Created by `mix phx.gen.release` to run the Ecto migrations
+        if app.includes_file("rel/overlays/bin/migrate") {
+            start_phase.set_release_cmd(format!("{}/migrate", release_dir));
+        }
+
+        Ok(Some(start_phase))
+    }
+
+    fn environment_variables(
+        &self,
+        app: &App,
+        _env: &Environment,
+    ) -> Result<Option<EnvironmentVariables>> {
+        let mut variables = EnvironmentVariables::from([
+            ("MIX_ENV".to_string(), "prod".to_string()),
+            // The VM needs a UTF-8 locale
+            ("LANG".to_string(), "C.UTF-8".to_string()),
+        ]);
+
+        if ElixirProvider::is_phoenix(app)? {
+            // Releases only start the endpoint when asked to
+            variables.insert("PHX_SERVER".to_string(), "true".to_string());
+        }
+        if app.includes_file("assets/package.json") {
+            variables.extend(NodeProvider::get_node_environment_variables());
+        }
+
+        Ok(Some(variables))
+    }
+}
+
+impl ElixirProvider {
+    fn read_mix_exs(app: &App) -> Result<String> {
+        app.read_file("mix.exs").context("Reading mix.exs")
+    }
+
+    fn is_phoenix(app: &App) -> Result<bool> {
+        Ok(ElixirProvider::read_mix_exs(app)?.contains("{:phoenix,"))
+    }
+
+    /// The OTP application name, e.g. `hello` for `app: :hello`
+    fn get_app_name(app: &App) -> Result<String> {
+        let re = Regex::new(r"app:\s*:(\w+)")?;
+        re.captures(&ElixirProvider::read_mix_exs(app)?)
+            .map(|captures| captures[1].to_string())
+            .context("Could not find the app name in mix.exs")
+    }
+
+    /// Elixir version from `NIXPACKS_ELIXIR_VERSION` or the `elixir` requirement in `mix.exs`
+    fn get_elixir_pkg(app: &App, env: &Environment) -> Result<Pkg> {
+        let version = match env.get_config_variable("ELIXIR_VERSION") {
+            Some(version) => Some(version.to_string()),
+            None => {
+                let re = Regex::new(r#"elixir:\s*"[~>=\s]*(\d+\.\d+)"#)?;
+                re.captures(&ElixirProvider::read_mix_exs(app)?)
+                    .map(|captures| captures[1].to_string())
+            }
+        };
+
+        let version = match version {
+            Some(version) => version,
+            None => return Ok(Pkg::new(DEFAULT_ELIXIR_PKG_NAME)),
+        };
+
+        let minor_version = version.splitn(3, '.').take(2).collect::<Vec<_>>().join(".");
+        match AVAILABLE_ELIXIR_VERSIONS
+            .iter()
+            .find(|(v, _)| *v == minor_version)
+        {
+            Some((_, pkg)) => Ok(Pkg::new(pkg)),
+            None => {
+                eprintln!(
+                    "Warning: Elixir {} is not available, using the default version",
+                    version
+                );
+                Ok(Pkg::new(DEFAULT_ELIXIR_PKG_NAME))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_version_from_mix_exs() -> Result<()> {
+        assert_eq!(
+            ElixirProvider::get_elixir_pkg(
+                &App::new("./examples/elixir-phoenix")?,
+                &Environment::default()
+            )?,
+            Pkg::new("elixir_1_13")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_from_environment_variable() -> Result<()> {
+        assert_eq!(
+            ElixirProvider::get_elixir_pkg(
+                &App::new("./examples/elixir-phoenix")?,
+                &Environment::new(HashMap::from([(
+                    "NIXPACKS_ELIXIR_VERSION".to_string(),
+                    "1.12".to_string()
+                )]))
+            )?,
+            Pkg::new("elixir_1_12")
+        );
+
+        Ok(())
+    }
+}
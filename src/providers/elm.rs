@@ -0,0 +1,95 @@
+use super::{staticfile::StaticfileProvider, Provider};
+use crate::nixpacks::{
+    app::{App, StaticAssets},
+    environment::Environment,
+    nix::pkg::Pkg,
+    phase::{BuildPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+const OUTPUT_DIR: &str = "public";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct ElmJson {
+    #[serde(rename = "type")]
+    project_type: String,
+    source_directories: Option<Vec<String>>,
+}
+
+pub struct ElmProvider {}
+
+impl Provider for ElmProvider {
+    fn name(&self) -> &str {
+        "elm"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["elm.json"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.elm"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        // Packages are libraries, only applications can be served
+        if !app.includes_file("elm.json") {
+            return Ok(false);
+        }
+        let elm_json: ElmJson = app.read_json("elm.json")?;
+        Ok(elm_json.project_type == "application")
+    }
+
+    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
+        Ok(Some(SetupPhase::new(vec![Pkg::new("elmPackages.elm")])))
+    }
+
+    fn build(&self, app: &App, env: &Environment) -> Result<Option<BuildPhase>> {
+        let main = ElmProvider::get_main_module(app, env)?;
+
+        // A custom page embeds the compiled script, otherwise Elm generates the page
+        let cmd = if app.includes_file("index.html") {
+            format!(
+                "mkdir -p {dir} && cp index.html {dir}/ && elm make {main} --optimize --output={dir}/elm.js",
+                dir = OUTPUT_DIR,
+                main = main
+            )
+        } else {
+            format!(
+                "elm make {} --optimize --output={}/index.html",
+                main, OUTPUT_DIR
+            )
+        };
+
+        Ok(Some(BuildPhase::new(cmd)))
+    }
+
+    fn static_assets(&self, app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        StaticfileProvider::get_static_site_assets(app, env)
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        Ok(Some(StaticfileProvider::get_static_site_start_phase(
+            app, OUTPUT_DIR,
+        )))
+    }
+}
+
+impl ElmProvider {
+    /// `NIXPACKS_ELM_MAIN`, or `Main.elm` in the first source directory
+    fn get_main_module(app: &App, env: &Environment) -> Result<String> {
+        if let Some(main) = env.get_config_variable("ELM_MAIN") {
+            return Ok(main.to_string());
+        }
+
+        let elm_json: ElmJson = app.read_json("elm.json")?;
+        let source_dir = elm_json
+            .source_directories
+            .and_then(|dirs| dirs.first().cloned())
+            .unwrap_or_else(|| "src".to_string());
+
+        Ok(format!("{}/Main.elm", source_dir.trim_end_matches('/')))
+    }
+}
@@ -1,72 +1,51 @@
-use super::Provider;
+use super::{dotnet, Provider};
 use crate::nixpacks::{
     app::App,
     environment::{Environment, EnvironmentVariables},
-    nix::pkg::Pkg,
     phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
 };
-use anyhow::{Context, Result};
+use anyhow::Result;
 
 pub struct FSharpProvider {}
 
-pub const ARTIFACT_DIR: &'static &str = &"out";
-
 impl Provider for FSharpProvider {
     fn name(&self) -> &str {
         "fsharp"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["*.fsproj"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.fs"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
-        Ok(!app.find_files("*.fsproj")?.is_empty())
+        dotnet::detect(app, "fsproj")
     }
 
-    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
-        Ok(Some(SetupPhase::new(vec![Pkg::new("dotnet-sdk")])))
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        dotnet::setup(app, env, "fsproj")
     }
 
-    fn install(&self, _app: &App, _env: &Environment) -> Result<Option<InstallPhase>> {
-        Ok(Some(InstallPhase::new("dotnet restore".to_string())))
+    fn install(&self, app: &App, env: &Environment) -> Result<Option<InstallPhase>> {
+        dotnet::install(app, env, "fsproj")
     }
 
-    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
-        Ok(Some(BuildPhase::new(format!(
-            "dotnet publish --no-restore -c Release -o {}",
-            ARTIFACT_DIR
-        ))))
+    fn build(&self, app: &App, env: &Environment) -> Result<Option<BuildPhase>> {
+        dotnet::build(app, env, "fsproj")
     }
 
-    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
-        let fsproj = &app.find_files("*.fsproj")?[0].with_extension("");
-        let project_name = fsproj
-            .file_name()
-            .context("Invalid file_name")?
-            .to_str()
-            .context("Invalid project_name")?;
-        Ok(Some(StartPhase::new(format!(
-            "./{}/{}",
-            ARTIFACT_DIR, project_name
-        ))))
+    fn start(&self, app: &App, env: &Environment) -> Result<Option<StartPhase>> {
+        dotnet::start(app, env, "fsproj")
     }
 
     fn environment_variables(
         &self,
         _app: &App,
-        _env: &Environment,
+        env: &Environment,
     ) -> Result<Option<EnvironmentVariables>> {
-        let env_vars = EnvironmentVariables::from([
-            (
-                "ASPNETCORE_ENVIRONMENT".to_string(),
-                "Production".to_string(),
-            ),
-            (
-                "ASPNETCORE_URLS".to_string(),
-                "http://0.0.0.0:3000".to_string(),
-            ),
-            (
-                "DOTNET_ROOT".to_string(),
-                "/nix/var/nix/profiles/default/".to_string(),
-            ),
-        ]);
-        Ok(Some(env_vars))
+        dotnet::environment_variables(env)
     }
 }
@@ -0,0 +1,57 @@
+use super::Provider;
+use crate::nixpacks::{
+    app::App,
+    environment::Environment,
+    nix::pkg::Pkg,
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+
+pub struct GleamProvider {}
+
+impl Provider for GleamProvider {
+    fn name(&self) -> &str {
+        "gleam"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["gleam.toml"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.gleam"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        Ok(app.includes_file("gleam.toml"))
+    }
+
+    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
+        Ok(Some(SetupPhase::new(vec![
+            Pkg::new("gleam"),
+            Pkg::new("erlang"),
+            Pkg::new("rebar3"),
+        ])))
+    }
+
+    fn install(&self, app: &App, _env: &Environment) -> Result<Option<InstallPhase>> {
+        let mut install_phase = InstallPhase::new("gleam deps download".to_string());
+        install_phase.add_file_dependency("gleam.toml".to_string());
+        if app.includes_file("manifest.toml") {
+            install_phase.add_file_dependency("manifest.toml".to_string());
+        }
+        Ok(Some(install_phase))
+    }
+
+    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        Ok(Some(BuildPhase::new(
+            "gleam export erlang-shipment".to_string(),
+        )))
+    }
+
+    fn start(&self, _app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        Ok(Some(StartPhase::new(
+            "./build/erlang-shipment/entrypoint.sh run".to_string(),
+        )))
+    }
+}
@@ -1,3 +1,5 @@
+use std::fs;
+
 use super::Provider;
 use crate::nixpacks::{
     app::App,
@@ -5,49 +7,76 @@ use crate::nixpacks::{
     nix::pkg::Pkg,
     phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 pub struct GolangProvider {}
 
 pub const BINARY_NAME: &'static &str = &"out";
-const AVAILABLE_GO_VERSIONS: &[(&str, &str)] = &[("1.17", "go"), ("1.18", "go_1_18")];
+// Only versions packaged in the pinned nixpkgs archive, others use the default `go`
+const AVAILABLE_GO_VERSIONS: &[(&str, &str)] = &[("1.17", "go_1_17"), ("1.18", "go_1_18")];
 pub const DEFAULT_GO_PKG_NAME: &'static &str = &"go";
 
+/// A main package that can be built into a binary
+#[derive(Debug, PartialEq, Eq)]
+pub struct GoBinary {
+    pub name: String,
+    /// Package path passed to `go build`
+    pub target: String,
+}
+
 impl Provider for GolangProvider {
     fn name(&self) -> &str {
         "golang"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["main.go", "go.mod", "go.work"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.go"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
-        Ok(app.includes_file("main.go") || app.includes_file("go.mod"))
+        Ok(app.includes_file("main.go")
+            || app.includes_file("go.mod")
+            || app.includes_file("go.work"))
     }
 
-    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
-        let go_mod = self.read_go_mod_if_exists(_app)?;
+    fn setup(&self, app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
+        let go_mod = self.read_go_mod_if_exists(app)?;
         let nix_pkg = GolangProvider::get_nix_golang_pkg(go_mod)?;
 
         Ok(Some(SetupPhase::new(vec![Pkg::new(&nix_pkg)])))
     }
 
     fn install(&self, app: &App, _env: &Environment) -> Result<Option<InstallPhase>> {
-        if app.includes_file("go.mod") {
+        // Vendored dependencies are already in the source tree
+        if app.includes_file("go.mod") && !GolangProvider::is_vendored(app) {
             return Ok(Some(InstallPhase::new("go get".to_string())));
         }
         Ok(None)
     }
 
-    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
-        if app.includes_file("go.mod") {
-            Ok(Some(BuildPhase::new(format!(
-                "go build -o {}",
-                BINARY_NAME
-            ))))
-        } else {
-            Ok(Some(BuildPhase::new(format!(
-                "go build -o {} main.go",
-                BINARY_NAME
-            ))))
+    fn build(&self, app: &App, env: &Environment) -> Result<Option<BuildPhase>> {
+        let mut flags = String::new();
+        if GolangProvider::is_vendored(app) {
+            flags.push_str(" -mod=vendor");
+        }
+        if let Some(ldflags) = env.get_config_variable("GO_LDFLAGS") {
+            flags.push_str(&format!(" -ldflags=\"{}\"", ldflags));
         }
+
+        let target = match GolangProvider::get_binary(app, env)? {
+            Some(binary) => format!(" {}", binary.target),
+            None if app.includes_file("go.mod") => "".to_string(),
+            None => " main.go".to_string(),
+        };
+
+        Ok(Some(BuildPhase::new(format!(
+            "go build{} -o {}{}",
+            flags, BINARY_NAME, target
+        ))))
     }
 
     fn start(&self, _app: &App, env: &Environment) -> Result<Option<StartPhase>> {
@@ -82,31 +111,194 @@ impl GolangProvider {
     pub fn read_go_mod_if_exists(&self, app: &App) -> Result<Option<String>> {
         if app.includes_file("go.mod") {
             Ok(Some(app.read_file("go.mod")?))
+        } else if app.includes_file("go.work") {
+            // The workspace file declares the go version in the same way as go.mod
+            Ok(Some(app.read_file("go.work")?))
         } else {
             Ok(None)
         }
     }
 
     pub fn get_nix_golang_pkg(go_mod_contents: Option<String>) -> Result<String> {
-        if go_mod_contents.is_some() {
-            let mut lines = go_mod_contents.as_ref().unwrap().lines();
-            let go_version_line = lines.find(|line| line.trim().starts_with("go"));
+        if let Some(go_mod_contents) = go_mod_contents {
+            let mut lines = go_mod_contents.lines();
+            let go_version_line = lines.find(|line| line.trim().starts_with("go "));
 
             if let Some(go_version_line) = go_version_line {
-                let go_version = go_version_line.trim().split_whitespace().nth(1).unwrap();
+                let go_version = go_version_line
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default();
 
                 if let Some(nix_pkg) = version_number_to_pkg(go_version)? {
                     return Ok(nix_pkg);
                 }
+                eprintln!(
+                    "Warning: Go {} is not available, using the default {}",
+                    go_version, DEFAULT_GO_PKG_NAME
+                );
             }
         }
 
         Ok(DEFAULT_GO_PKG_NAME.to_string())
     }
+
+    fn is_vendored(app: &App) -> bool {
+        app.includes_file("go.mod") && app.includes_file("vendor/modules.txt")
+    }
+
+    /// Returns the binary to build, selected with `NIXPACKS_GO_BIN` when there are several
+    ///
+    /// `None` means the main package is at the root of the app
+    pub fn get_binary(app: &App, env: &Environment) -> Result<Option<GoBinary>> {
+        let binaries = GolangProvider::find_binaries(app)?;
+
+        if let Some(name) = env.get_config_variable("GO_BIN") {
+            return match binaries.into_iter().find(|binary| &binary.name == name) {
+                Some(binary) => Ok(Some(binary)),
+                None => bail!("No main package found for NIXPACKS_GO_BIN={}", name),
+            };
+        }
+
+        // A main package at the root takes precedence over `cmd/`
+        if app.includes_file("main.go") || binaries.is_empty() {
+            return Ok(None);
+        }
+
+        if binaries.len() > 1 {
+            // Prefer the binary named after the module, e.g. `cmd/api` for `example.com/api`
+            let module_name = GolangProvider::get_module_name(app);
+            if let Some(index) = binaries
+                .iter()
+                .position(|binary| Some(&binary.name) == module_name.as_ref())
+            {
+                return Ok(binaries.into_iter().nth(index));
+            }
+
+            eprintln!(
+                "Warning: Found multiple binaries ({}), building {}. Set NIXPACKS_GO_BIN to choose another.",
+                binaries
+                    .iter()
+                    .map(|binary| binary.name.clone())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                binaries[0].name
+            );
+        }
+
+        Ok(binaries.into_iter().next())
+    }
+
+    /// Find all `cmd/<name>/main.go` packages, including those in `go.work` modules
+    fn find_binaries(app: &App) -> Result<Vec<GoBinary>> {
+        let mut binaries = GolangProvider::find_cmd_binaries(app, ".")?;
+
+        for module in GolangProvider::get_workspace_modules(app)? {
+            if module == "." {
+                continue;
+            }
+
+            if app.includes_file(&format!("{}/main.go", module)) {
+                binaries.push(GoBinary {
+                    name: module
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or(module.as_str())
+                        .to_string(),
+                    target: format!("./{}", module),
+                });
+            }
+            binaries.append(&mut GolangProvider::find_cmd_binaries(app, &module)?);
+        }
+
+        Ok(binaries)
+    }
+
+    fn find_cmd_binaries(app: &App, module: &str) -> Result<Vec<GoBinary>> {
+        let cmd_dir = format!("{}/cmd", module);
+        if !app.includes_directory(&cmd_dir) {
+            return Ok(Vec::new());
+        }
+
+        let mut names = fs::read_dir(app.source.join(&cmd_dir))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join("main.go").is_file())
+            .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+            .collect::<Vec<_>>();
+        names.sort();
+
+        Ok(names
+            .into_iter()
+            .map(|name| GoBinary {
+                target: format!("./{}/{}", cmd_dir.trim_start_matches("./"), name),
+                name,
+            })
+            .collect())
+    }
+
+    /// Module directories listed in the `use` directives of `go.work`
+    fn get_workspace_modules(app: &App) -> Result<Vec<String>> {
+        if !app.includes_file("go.work") {
+            return Ok(Vec::new());
+        }
+
+        let go_work = app.read_file("go.work")?;
+        let mut modules = Vec::new();
+        let mut in_use_block = false;
+        for line in go_work.lines().map(|line| line.trim()) {
+            let dir = if in_use_block {
+                if line == ")" {
+                    in_use_block = false;
+                    continue;
+                }
+                line
+            } else if line == "use (" {
+                in_use_block = true;
+                continue;
+            } else if let Some(dir) = line.strip_prefix("use ") {
+                dir
+            } else {
+                continue;
+            };
+
+            let dir = dir.split("//").next().unwrap_or_default().trim();
+            if !dir.is_empty() {
+                modules.push(
+                    dir.trim_start_matches("./")
+                        .trim_end_matches('/')
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(modules
+            .into_iter()
+            .map(|module| {
+                if module.is_empty() {
+                    ".".to_string()
+                } else {
+                    module
+                }
+            })
+            .collect())
+    }
+
+    fn get_module_name(app: &App) -> Option<String> {
+        let go_mod = app.read_file("go.mod").ok()?;
+        let module_line = go_mod
+            .lines()
+            .find(|line| line.trim().starts_with("module "))?;
+        let module = module_line.split_whitespace().nth(1)?;
+        module.rsplit('/').next().map(|name| name.to_string())
+    }
 }
 
 fn version_number_to_pkg(version: &str) -> Result<Option<String>> {
-    let matched_version = AVAILABLE_GO_VERSIONS.iter().find(|(v, _)| v == &version);
+    // go.mod may specify a patch version, e.g. `go 1.20.3`
+    let minor_version = version.splitn(3, '.').take(2).collect::<Vec<_>>().join(".");
+    let matched_version = AVAILABLE_GO_VERSIONS
+        .iter()
+        .find(|(v, _)| v == &minor_version);
 
     match matched_version {
         Some((_, pkg)) => Ok(Some(pkg.to_string())),
@@ -117,6 +309,7 @@ fn version_number_to_pkg(version: &str) -> Result<Option<String>> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_no_go_mod() -> Result<()> {
@@ -142,6 +335,34 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_with_patch_version() -> Result<()> {
+        let go_mod_contents = r#"
+            go 1.18.3
+        "#;
+
+        assert_eq!(
+            GolangProvider::get_nix_golang_pkg(Some(go_mod_contents.to_string()))?,
+            "go_1_18".to_string()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_on_unpackaged_version() -> Result<()> {
+        let go_mod_contents = r#"
+            go 1.20
+        "#;
+
+        assert_eq!(
+            GolangProvider::get_nix_golang_pkg(Some(go_mod_contents.to_string()))?,
+            DEFAULT_GO_PKG_NAME.to_string()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_fallback_on_invalid_version() -> Result<()> {
         let go_mod_contents = r#"
@@ -155,4 +376,37 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_binary_from_environment_variable() -> Result<()> {
+        assert_eq!(
+            GolangProvider::get_binary(
+                &App::new("./examples/go-cmd")?,
+                &Environment::new(HashMap::from([(
+                    "NIXPACKS_GO_BIN".to_string(),
+                    "worker".to_string()
+                )]))
+            )?,
+            Some(GoBinary {
+                name: "worker".to_string(),
+                target: "./cmd/worker".to_string()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_binary() -> Result<()> {
+        assert!(GolangProvider::get_binary(
+            &App::new("./examples/go-cmd")?,
+            &Environment::new(HashMap::from([(
+                "NIXPACKS_GO_BIN".to_string(),
+                "nope".to_string()
+            )]))
+        )
+        .is_err());
+
+        Ok(())
+    }
 }
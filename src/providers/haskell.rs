@@ -19,6 +19,14 @@ impl Provider for HaskellStackProvider {
         "haskell_stack"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["package.yaml with *.hs"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.hs"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("package.yaml") && app.has_match("**/*.hs"))
     }
@@ -48,7 +56,7 @@ impl Provider for HaskellStackProvider {
         Ok(Some(StartPhase::new(format!(
             "stack exec {}",
             exe_names
-                .get(0)
+                .first()
                 .ok_or_else(|| anyhow::anyhow!("Failed to get executable name"))?
         ))))
     }
@@ -0,0 +1,143 @@
+use super::{staticfile::StaticfileProvider, zola::ZolaProvider, Provider};
+use crate::nixpacks::{
+    app::{App, StaticAssets},
+    environment::{Environment, EnvironmentVariables},
+    nix::pkg::Pkg,
+    phase::{BuildPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+use regex::Regex;
+
+const CONFIG_FILES: &[&str] = &[
+    "hugo.toml",
+    "hugo.yaml",
+    "hugo.json",
+    "config.toml",
+    "config.yaml",
+    "config.yml",
+    "config.json",
+];
+const DEFAULT_PUBLISH_DIR: &str = "public";
+
+pub struct HugoProvider {}
+
+impl Provider for HugoProvider {
+    fn name(&self) -> &str {
+        "hugo"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &[
+            "hugo.toml",
+            "hugo.yaml",
+            "hugo.json",
+            "config.toml with layouts/",
+        ]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        if ["hugo.toml", "hugo.yaml", "hugo.json"]
+            .iter()
+            .any(|file| app.includes_file(file))
+        {
+            return Ok(true);
+        }
+
+        // `config.*` files are too common on their own, so also look for Hugo settings
+        // and the Hugo directory layout
+        let has_hugo_config = match HugoProvider::get_config_file(app) {
+            Some(file) => {
+                let re = Regex::new(
+                    r#"(?m)^\s*"?(baseURL|baseurl|languageCode|defaultContentLanguage|theme)"?\s*[=:]"#,
+                )?;
+                re.is_match(&app.read_file(file)?)
+            }
+            None => false,
+        };
+        Ok(has_hugo_config
+            && (app.includes_directory("archetypes")
+                || app.includes_directory("layouts")
+                || app.includes_directory("themes"))
+            && !ZolaProvider::is_zola(app))
+    }
+
+    fn setup(&self, app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
+        let mut pkgs = vec![Pkg::new("hugo")];
+        // Hugo modules are fetched with Go and git
+        if app.includes_file("go.mod") {
+            pkgs.append(&mut vec![Pkg::new("go"), Pkg::new("git")]);
+        }
+        Ok(Some(SetupPhase::new(pkgs)))
+    }
+
+    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        Ok(Some(BuildPhase::new("hugo --minify".to_string())))
+    }
+
+    fn static_assets(&self, app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        StaticfileProvider::get_static_site_assets(app, env)
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let publish_dir = HugoProvider::get_publish_dir(app)?;
+        Ok(Some(StaticfileProvider::get_static_site_start_phase(
+            app,
+            &publish_dir,
+        )))
+    }
+
+    fn environment_variables(
+        &self,
+        _app: &App,
+        _env: &Environment,
+    ) -> Result<Option<EnvironmentVariables>> {
+        Ok(Some(EnvironmentVariables::from([(
+            "HUGO_ENV".to_string(),
+            "production".to_string(),
+        )])))
+    }
+}
+
+impl HugoProvider {
+    fn get_config_file(app: &App) -> Option<&'static str> {
+        CONFIG_FILES
+            .iter()
+            .find(|file| app.includes_file(file))
+            .copied()
+    }
+
+    /// The `publishDir` of the site config, `public` by default
+    fn get_publish_dir(app: &App) -> Result<String> {
+        let config = match HugoProvider::get_config_file(app) {
+            Some(file) => app.read_file(file)?,
+            None => return Ok(DEFAULT_PUBLISH_DIR.to_string()),
+        };
+
+        let re = Regex::new(r#"(?mi)^\s*"?publishDir"?\s*[=:]\s*["']?([^"'\s,]+)"#)?;
+        Ok(re
+            .captures(&config)
+            .map(|captures| captures[1].trim_end_matches('/').to_string())
+            .unwrap_or_else(|| DEFAULT_PUBLISH_DIR.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_detect() -> Result<()> {
+        let env = Environment::default();
+        assert!(HugoProvider {}.detect(&App::new("./examples/hugo")?, &env)?);
+        assert!(!HugoProvider {}.detect(&App::new("./examples/zola")?, &env)?);
+
+        // Any app config next to a `layouts` directory
+        let dir = tempdir::TempDir::new("nixpacks-hugo")?;
+        fs::create_dir(dir.path().join("layouts"))?;
+        fs::write(dir.path().join("config.toml"), "[server]\nport = 8080\n")?;
+        assert!(!HugoProvider {}.detect(&App::new(dir.path().to_str().unwrap())?, &env)?);
+
+        Ok(())
+    }
+}
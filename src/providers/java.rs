@@ -5,7 +5,13 @@ use crate::nixpacks::{
     nix::pkg::Pkg,
     phase::{BuildPhase, SetupPhase, StartPhase},
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
+use regex::Regex;
+
+const DEFAULT_JDK_VERSION: u32 = 8;
+const AVAILABLE_JDK_VERSIONS: &[(u32, &str)] =
+    &[(8, "jdk8"), (11, "jdk11"), (17, "jdk17"), (18, "jdk18")];
+
 pub struct JavaProvider {}
 
 impl Provider for JavaProvider {
@@ -13,67 +19,417 @@ impl Provider for JavaProvider {
         "Java"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["pom.xml", "build.gradle", "build.gradle.kts", "gradlew"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.java", "**/*.kt"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
-        Ok(app.includes_file("pom.xml")
-            || app.includes_directory("pom.atom")
-            || app.includes_directory("pom.clj")
-            || app.includes_directory("pom.groovy")
-            || app.includes_file("pom.rb")
-            || app.includes_file("pom.scala")
-            || app.includes_file("pom.yaml")
-            || app.includes_file("pom.yml"))
+        Ok(JavaProvider::is_maven(app) || JavaProvider::is_gradle(app))
     }
 
-    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
-        Ok(Some(SetupPhase::new(vec![
-            Pkg::new("maven"),
-            Pkg::new("jdk8"),
-        ])))
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        let jdk_pkg = JavaProvider::get_jdk_pkg(app, env)?;
+        let is_default_jdk = jdk_pkg.name == JavaProvider::default_jdk_pkg_name();
+
+        let mut pkgs = Vec::new();
+        if JavaProvider::is_gradle(app) {
+            // The wrapper downloads its own copy of Gradle
+            if !JavaProvider::has_gradle_wrapper(app) {
+                let mut gradle_pkg = Pkg::new("gradle");
+                if !is_default_jdk {
+                    gradle_pkg = gradle_pkg.set_override("java", &jdk_pkg.name);
+                }
+                pkgs.push(gradle_pkg);
+            }
+        } else {
+            let mut maven_pkg = Pkg::new("maven");
+            if !is_default_jdk {
+                maven_pkg = maven_pkg.set_override("jdk", &jdk_pkg.name);
+            }
+            pkgs.push(maven_pkg);
+        }
+        pkgs.push(jdk_pkg);
+
+        Ok(Some(SetupPhase::new(pkgs)))
     }
 
     fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
-        let mvn_exe = self.get_maven_exe(app);
-        Ok(Some(BuildPhase::new(format!("{mvn_exe} -DoutputFile=target/mvn-dependency-list.log -B -DskipTests clean dependency:list install", 
+        if JavaProvider::is_gradle(app) {
+            let gradle_exe = JavaProvider::get_gradle_exe(app);
+            return Ok(Some(BuildPhase::new(format!(
+                "{gradle_exe} clean build -x check",
+                gradle_exe = gradle_exe
+            ))));
+        }
+
+        let mvn_exe = JavaProvider::get_maven_exe(app);
+        Ok(Some(BuildPhase::new(format!("{mvn_exe} -DoutputFile=target/mvn-dependency-list.log -B -DskipTests clean dependency:list install",
             mvn_exe=mvn_exe
         ))))
     }
 
     fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
-        let start_cmd = self.get_start_cmd(app)?;
-        Ok(Some(StartPhase::new(start_cmd)))
+        let start_cmd = JavaProvider::get_start_cmd(app);
+        let mut start_phase = StartPhase::new(start_cmd);
+        // Spring Boot, Quarkus and Micronaut listen on 8080 when $PORT is not set
+        if JavaProvider::get_framework(app).is_some() {
+            start_phase.add_port(8080);
+        }
+        if let Some(path) = JavaProvider::get_healthcheck_path(app) {
+            start_phase.set_healthcheck_path(path.to_string());
+        }
+        Ok(Some(start_phase))
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum JavaFramework {
+    SpringBoot,
+    Quarkus,
+    Micronaut,
+    WildflySwarm,
+}
+
 impl JavaProvider {
-    fn get_maven_exe(&self, app: &App) -> String {
+    fn is_maven(app: &App) -> bool {
+        app.includes_file("pom.xml")
+            || app.includes_file("pom.atom")
+            || app.includes_file("pom.clj")
+            || app.includes_file("pom.groovy")
+            || app.includes_file("pom.rb")
+            || app.includes_file("pom.scala")
+            || app.includes_file("pom.yaml")
+            || app.includes_file("pom.yml")
+    }
+
+    fn is_gradle(app: &App) -> bool {
+        !JavaProvider::is_maven(app)
+            && (app.includes_file("build.gradle")
+                || app.includes_file("build.gradle.kts")
+                || app.includes_file("gradlew"))
+    }
+
+    fn has_maven_wrapper(app: &App) -> bool {
+        app.includes_file("mvnw") && app.includes_file(".mvn/wrapper/maven-wrapper.properties")
+    }
+
+    /// The wrapper only runs with its jar, which is sometimes left out of the repo
+    fn has_gradle_wrapper(app: &App) -> bool {
+        app.includes_file("gradlew")
+            && app.includes_file("gradle/wrapper/gradle-wrapper.properties")
+            && app.includes_file("gradle/wrapper/gradle-wrapper.jar")
+    }
+
+    fn get_maven_exe(app: &App) -> String {
         // App has a maven wrapper
-        if app.includes_file("mvnw") && app.includes_file(".mvn/wrapper/maven-wrapper.properties") {
+        if JavaProvider::has_maven_wrapper(app) {
             "./mvnw".to_string()
         } else {
             "mvn".to_string()
         }
     }
 
-    fn get_start_cmd(&self, app: &App) -> Result<String> {
-        if app.includes_file("pom.xml") {
-            Ok(format!(
-                "java {} $JAVA_OPTS -jar target/*jar",
-                self.get_port_config(app)
-            ))
+    fn get_gradle_exe(app: &App) -> String {
+        // App has a gradle wrapper
+        if JavaProvider::has_gradle_wrapper(app) {
+            "./gradlew".to_string()
+        } else {
+            "gradle".to_string()
+        }
+    }
+
+    /// Concatenated contents of the build files, used to sniff out frameworks and settings
+    fn read_build_files(app: &App) -> String {
+        ["pom.xml", "build.gradle", "build.gradle.kts"]
+            .iter()
+            .map(|file| app.read_file(file).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn get_framework(app: &App) -> Option<JavaFramework> {
+        let build_files = JavaProvider::read_build_files(app);
+        if build_files.contains("io.quarkus") {
+            Some(JavaFramework::Quarkus)
+        } else if build_files.contains("io.micronaut") {
+            Some(JavaFramework::Micronaut)
+        } else if build_files.contains("org.springframework.boot") {
+            Some(JavaFramework::SpringBoot)
+        } else if build_files.contains("<groupId>org.wildfly.swarm") {
+            Some(JavaFramework::WildflySwarm)
         } else {
-            Ok("java $JAVA_OPTS -jar target/*jar".to_string())
+            None
         }
     }
-    fn get_port_config(&self, app: &App) -> String {
-        let pom_file = app.read_file("pom.xml").unwrap_or_default();
-        if pom_file.contains("<groupId>org.wildfly.swarm") {
-            "-Dswarm.http.port=$PORT".to_string()
-        } else if pom_file.contains("<groupId>org.springframework.boot")
-            && pom_file.contains("<artifactId>spring-boot")
-        {
-            "-Dserver.port=$PORT".to_string()
+
+    /// The health endpoint of the framework's monitoring module, when it is a dependency
+    fn get_healthcheck_path(app: &App) -> Option<&'static str> {
+        let build_files = JavaProvider::read_build_files(app);
+        match JavaProvider::get_framework(app)? {
+            JavaFramework::SpringBoot if build_files.contains("spring-boot-starter-actuator") => {
+                Some("/actuator/health")
+            }
+            JavaFramework::Quarkus if build_files.contains("quarkus-smallrye-health") => {
+                Some("/q/health")
+            }
+            JavaFramework::Micronaut if build_files.contains("micronaut-management") => {
+                Some("/health")
+            }
+            _ => None,
+        }
+    }
+
+    fn get_start_cmd(app: &App) -> String {
+        let framework = JavaProvider::get_framework(app);
+        let jar = JavaProvider::get_jar_path(app, &framework);
+
+        match JavaProvider::get_port_config(app, &framework) {
+            Some(port_config) => format!("java {} $JAVA_OPTS -jar {}", port_config, jar),
+            None => format!("java $JAVA_OPTS -jar {}", jar),
+        }
+    }
+
+    fn get_jar_path(app: &App, framework: &Option<JavaFramework>) -> String {
+        let build_dir = if JavaProvider::is_gradle(app) {
+            "build"
         } else {
-            "".to_string()
+            "target"
+        };
+
+        match (framework, JavaProvider::is_gradle(app)) {
+            // Quarkus uses the fast-jar layout by default
+            (Some(JavaFramework::Quarkus), _) => {
+                format!("{}/quarkus-app/quarkus-run.jar", build_dir)
+            }
+            // Micronaut uses the shadow plugin to build a fat jar with Gradle
+            (Some(JavaFramework::Micronaut), true) => "build/libs/*-all.jar".to_string(),
+            // Skip the `-plain.jar` that Gradle builds next to the Spring Boot fat jar
+            (_, true) => "$(ls -1 build/libs/*jar | grep -v plain)".to_string(),
+            (_, false) => "target/*jar".to_string(),
+        }
+    }
+
+    fn get_port_config(app: &App, framework: &Option<JavaFramework>) -> Option<String> {
+        if !app.includes_file("pom.xml") && !JavaProvider::is_gradle(app) {
+            return None;
         }
+
+        match framework {
+            Some(JavaFramework::WildflySwarm) => Some("-Dswarm.http.port=$PORT".to_string()),
+            Some(JavaFramework::SpringBoot) => Some("-Dserver.port=$PORT".to_string()),
+            Some(JavaFramework::Quarkus) => Some("-Dquarkus.http.port=$PORT".to_string()),
+            Some(JavaFramework::Micronaut) => Some("-Dmicronaut.server.port=$PORT".to_string()),
+            None => None,
+        }
+    }
+
+    pub fn default_jdk_pkg_name() -> String {
+        format!("jdk{}", DEFAULT_JDK_VERSION)
+    }
+
+    /// Returns the JDK nix package matching the version requested by the app
+    pub fn get_jdk_pkg(app: &App, env: &Environment) -> Result<Pkg> {
+        let version = match JavaProvider::get_jdk_version(app, env)? {
+            Some(version) => version,
+            None => return Ok(Pkg::new(&JavaProvider::default_jdk_pkg_name())),
+        };
+
+        // Newer JDKs build and run code for older versions
+        let matched_version = AVAILABLE_JDK_VERSIONS.iter().find(|(v, _)| *v >= version);
+        match matched_version {
+            Some((available, pkg)) => {
+                if *available != version {
+                    eprintln!(
+                        "Warning: JDK {} is not available, using JDK {}",
+                        version, available
+                    );
+                }
+                Ok(Pkg::new(pkg))
+            }
+            None => bail!(
+                "JDK {} is not available, the newest available JDK is {}. Set NIXPACKS_JDK_VERSION to build with another version",
+                version,
+                AVAILABLE_JDK_VERSIONS.last().map(|(v, _)| *v).unwrap_or(DEFAULT_JDK_VERSION)
+            ),
+        }
+    }
+
+    // The JDK version is taken from the first of
+    // - `NIXPACKS_JDK_VERSION`
+    // - `.java-version`
+    // - `system.properties`
+    // - `pom.xml` properties
+    // - Gradle toolchain or source compatibility
+    fn get_jdk_version(app: &App, env: &Environment) -> Result<Option<u32>> {
+        if let Some(version) = env.get_config_variable("JDK_VERSION") {
+            return Ok(parse_java_version(version));
+        }
+
+        if app.includes_file(".java-version") {
+            return Ok(parse_java_version(&app.read_file(".java-version")?));
+        }
+
+        if app.includes_file("system.properties") {
+            let re = Regex::new(r"(?m)^java\.runtime\.version\s*=\s*(\S+)")?;
+            if let Some(version) = capture_java_version(&re, &app.read_file("system.properties")?) {
+                return Ok(Some(version));
+            }
+        }
+
+        if app.includes_file("pom.xml") {
+            let pom_file = app.read_file("pom.xml")?;
+            for property in [
+                "maven.compiler.release",
+                "java.version",
+                "maven.compiler.target",
+                "maven.compiler.source",
+            ] {
+                let re = Regex::new(&format!(
+                    r"<{property}>\s*([^<\s]+)\s*</{property}>",
+                    property = regex::escape(property)
+                ))?;
+                if let Some(version) = capture_java_version(&re, &pom_file) {
+                    return Ok(Some(version));
+                }
+            }
+        }
+
+        for gradle_file in ["build.gradle", "build.gradle.kts"] {
+            if !app.includes_file(gradle_file) {
+                continue;
+            }
+
+            let contents = app.read_file(gradle_file)?;
+            for re in [
+                // java { toolchain { languageVersion = JavaLanguageVersion.of(17) } }
+                r"JavaLanguageVersion\.of\(\s*(\d+)\s*\)",
+                // sourceCompatibility = JavaVersion.VERSION_17 or JavaVersion.VERSION_1_8
+                r"sourceCompatibility\s*=\s*JavaVersion\.VERSION_([\d_]+)",
+                // sourceCompatibility = '17'
+                r#"sourceCompatibility\s*=\s*['"]?([\d.]+)['"]?"#,
+            ] {
+                let re = Regex::new(re)?;
+                if let Some(version) = capture_java_version(&re, &contents) {
+                    return Ok(Some(version));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn capture_java_version(re: &Regex, contents: &str) -> Option<u32> {
+    re.captures(contents)
+        .and_then(|captures| captures.get(1))
+        .and_then(|m| parse_java_version(&m.as_str().replace('_', ".")))
+}
+
+/// Parse `17`, `17.0.2` or the legacy `1.8` into the major version number
+fn parse_java_version(version: &str) -> Option<u32> {
+    let mut parts = version.trim().split('.');
+    let major = parts.next()?.parse::<u32>().ok()?;
+    if major == 1 {
+        parts.next()?.parse::<u32>().ok()
+    } else {
+        Some(major)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{collections::HashMap, fs};
+
+    #[test]
+    fn test_parse_java_version() {
+        assert_eq!(parse_java_version("1.8"), Some(8));
+        assert_eq!(parse_java_version("11"), Some(11));
+        assert_eq!(parse_java_version("17.0.2\n"), Some(17));
+        assert_eq!(parse_java_version("latest"), None);
+    }
+
+    #[test]
+    fn test_maven_legacy_version() -> Result<()> {
+        assert_eq!(
+            JavaProvider::get_jdk_pkg(
+                &App::new("./examples/java-maven")?,
+                &Environment::default()
+            )?,
+            Pkg::new("jdk8")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gradle_toolchain_version() -> Result<()> {
+        assert_eq!(
+            JavaProvider::get_jdk_pkg(
+                &App::new("./examples/java-gradle-kotlin")?,
+                &Environment::default()
+            )?,
+            Pkg::new("jdk17")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_gradle_wrapper_without_jar() -> Result<()> {
+        let dir = tempdir::TempDir::new("nixpacks-gradle")?;
+        fs::create_dir_all(dir.path().join("gradle/wrapper"))?;
+        fs::write(dir.path().join("build.gradle"), "")?;
+        fs::write(dir.path().join("gradlew"), "")?;
+        fs::write(
+            dir.path().join("gradle/wrapper/gradle-wrapper.properties"),
+            "",
+        )?;
+        let app = App::new(dir.path().to_str().unwrap())?;
+        assert_eq!(JavaProvider::get_gradle_exe(&app), "gradle");
+
+        fs::write(dir.path().join("gradle/wrapper/gradle-wrapper.jar"), "")?;
+        let app = App::new(dir.path().to_str().unwrap())?;
+        assert_eq!(JavaProvider::get_gradle_exe(&app), "./gradlew");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unavailable_versions() -> Result<()> {
+        let get_jdk_pkg = |version: &str| {
+            JavaProvider::get_jdk_pkg(
+                &App::new("./examples/java-maven")?,
+                &Environment::new(HashMap::from([(
+                    "NIXPACKS_JDK_VERSION".to_string(),
+                    version.to_string(),
+                )])),
+            )
+        };
+
+        assert_eq!(get_jdk_pkg("16")?, Pkg::new("jdk17"));
+        assert!(get_jdk_pkg("21").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_from_environment_variable() -> Result<()> {
+        assert_eq!(
+            JavaProvider::get_jdk_pkg(
+                &App::new("./examples/java-maven")?,
+                &Environment::new(HashMap::from([(
+                    "NIXPACKS_JDK_VERSION".to_string(),
+                    "11".to_string()
+                )]))
+            )?,
+            Pkg::new("jdk11")
+        );
+
+        Ok(())
     }
 }
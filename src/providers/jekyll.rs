@@ -0,0 +1,112 @@
+use super::{ruby::RubyProvider, staticfile::StaticfileProvider, Provider};
+use crate::nixpacks::{
+    app::{App, StaticAssets},
+    environment::{Environment, EnvironmentVariables},
+    nix::pkg::Pkg,
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+use regex::Regex;
+
+const DEFAULT_DESTINATION: &str = "_site";
+
+pub struct JekyllProvider {}
+
+impl Provider for JekyllProvider {
+    fn name(&self) -> &str {
+        "jekyll"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["_config.yml"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        let has_config = app.includes_file("_config.yml") || app.includes_file("_config.yaml");
+        if !has_config {
+            return Ok(false);
+        }
+
+        // A Gemfile without Jekyll belongs to some other Ruby app
+        Ok(!app.includes_file("Gemfile") || JekyllProvider::is_bundled(app))
+    }
+
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        if JekyllProvider::is_bundled(app) {
+            return RubyProvider {}.setup(app, env);
+        }
+        Ok(Some(SetupPhase::new(vec![Pkg::new("jekyll")])))
+    }
+
+    fn install(&self, app: &App, env: &Environment) -> Result<Option<InstallPhase>> {
+        if JekyllProvider::is_bundled(app) {
+            return RubyProvider {}.install(app, env);
+        }
+        Ok(None)
+    }
+
+    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        if JekyllProvider::is_bundled(app) {
+            Ok(Some(BuildPhase::new(
+                "bundle exec jekyll build".to_string(),
+            )))
+        } else {
+            Ok(Some(BuildPhase::new("jekyll build".to_string())))
+        }
+    }
+
+    fn static_assets(&self, app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        StaticfileProvider::get_static_site_assets(app, env)
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let destination = JekyllProvider::get_destination(app)?;
+        Ok(Some(StaticfileProvider::get_static_site_start_phase(
+            app,
+            &destination,
+        )))
+    }
+
+    fn environment_variables(
+        &self,
+        app: &App,
+        env: &Environment,
+    ) -> Result<Option<EnvironmentVariables>> {
+        let mut variables = if JekyllProvider::is_bundled(app) {
+            RubyProvider {}
+                .environment_variables(app, env)?
+                .unwrap_or_default()
+        } else {
+            EnvironmentVariables::default()
+        };
+        variables.insert("JEKYLL_ENV".to_string(), "production".to_string());
+
+        Ok(Some(variables))
+    }
+}
+
+impl JekyllProvider {
+    /// Jekyll and its plugins are installed with Bundler
+    fn is_bundled(app: &App) -> bool {
+        app.read_file("Gemfile")
+            .map(|gemfile| gemfile.contains("jekyll"))
+            .unwrap_or_default()
+    }
+
+    /// The `destination` of `_config.yml`, `_site` by default
+    fn get_destination(app: &App) -> Result<String> {
+        let config = app
+            .read_file("_config.yml")
+            .or_else(|_| app.read_file("_config.yaml"))?;
+        let re = Regex::new(r#"(?m)^destination\s*:\s*["']?([^"'\s#]+)"#)?;
+        Ok(re
+            .captures(&config)
+            .map(|captures| {
+                captures[1]
+                    .trim_start_matches("./")
+                    .trim_end_matches('/')
+                    .to_string()
+            })
+            .unwrap_or_else(|| DEFAULT_DESTINATION.to_string()))
+    }
+}
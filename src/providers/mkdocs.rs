@@ -0,0 +1,99 @@
+use super::{python::PythonProvider, staticfile::StaticfileProvider, Provider};
+use crate::nixpacks::{
+    app::{App, StaticAssets},
+    environment::Environment,
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+use regex::Regex;
+
+const DEFAULT_SITE_DIR: &str = "site";
+const VENV_LOCATION: &str = "/opt/venv";
+
+pub struct MkDocsProvider {}
+
+impl Provider for MkDocsProvider {
+    fn name(&self) -> &str {
+        "mkdocs"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["mkdocs.yml"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        // Python apps often keep their documentation next to the code
+        Ok(MkDocsProvider::get_config_file(app).is_some()
+            && !app.includes_file("main.py")
+            && !app.includes_file("manage.py"))
+    }
+
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        PythonProvider {}.setup(app, env)
+    }
+
+    fn install(&self, app: &App, env: &Environment) -> Result<Option<InstallPhase>> {
+        if app.includes_file("requirements.txt") || app.includes_file("pyproject.toml") {
+            return PythonProvider {}.install(app, env);
+        }
+
+        // Without requirements, install MkDocs and the theme it uses
+        let mut packages = vec!["mkdocs"];
+        if MkDocsProvider::read_config(app)?.contains("name: material") {
+            packages.push("mkdocs-material");
+        }
+
+        let mut install_phase = InstallPhase::new(format!(
+            "python -m venv {venv} && . {venv}/bin/activate && pip install {packages}",
+            venv = VENV_LOCATION,
+            packages = packages.join(" ")
+        ));
+        install_phase.add_path(format!("{}/bin", VENV_LOCATION));
+        Ok(Some(install_phase))
+    }
+
+    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        Ok(Some(BuildPhase::new("mkdocs build".to_string())))
+    }
+
+    fn static_assets(&self, app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        StaticfileProvider::get_static_site_assets(app, env)
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let site_dir = MkDocsProvider::get_site_dir(app)?;
+        Ok(Some(StaticfileProvider::get_static_site_start_phase(
+            app, &site_dir,
+        )))
+    }
+}
+
+impl MkDocsProvider {
+    fn get_config_file(app: &App) -> Option<&'static str> {
+        ["mkdocs.yml", "mkdocs.yaml"]
+            .into_iter()
+            .find(|file| app.includes_file(file))
+    }
+
+    fn read_config(app: &App) -> Result<String> {
+        match MkDocsProvider::get_config_file(app) {
+            Some(file) => app.read_file(file),
+            None => Ok("".to_string()),
+        }
+    }
+
+    /// The `site_dir` of `mkdocs.yml`, `site` by default
+    fn get_site_dir(app: &App) -> Result<String> {
+        let config = MkDocsProvider::read_config(app)?;
+        let re = Regex::new(r#"(?m)^site_dir\s*:\s*["']?([^"'\s#]+)"#)?;
+        Ok(re
+            .captures(&config)
+            .map(|captures| {
+                captures[1]
+                    .trim_start_matches("./")
+                    .trim_end_matches('/')
+                    .to_string()
+            })
+            .unwrap_or_else(|| DEFAULT_SITE_DIR.to_string()))
+    }
+}
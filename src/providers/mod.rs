@@ -5,26 +5,46 @@ use crate::nixpacks::{
 };
 use anyhow::Result;
 
+pub mod clojure;
 pub mod crystal;
 pub mod csharp;
 pub mod dart;
 pub mod deno;
+pub mod dockerfile;
+pub mod docusaurus;
+pub mod dotnet;
+pub mod elixir;
+pub mod elm;
 pub mod fsharp;
+pub mod gleam;
 pub mod go;
 pub mod haskell;
+pub mod hugo;
 pub mod java;
+pub mod jekyll;
+pub mod mkdocs;
 pub mod node;
 pub mod php;
 pub mod python;
 pub mod ruby;
 pub mod rust;
+pub mod scala;
 pub mod staticfile;
 pub mod swift;
 pub mod zig;
+pub mod zola;
 
-pub trait Provider {
+pub trait Provider: Sync {
     fn name(&self) -> &str;
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool>;
+    /// Files the app is detected by, listed when no provider matches
+    fn detect_files(&self) -> &[&str] {
+        &[]
+    }
+    /// Source files of the language, to suggest the closest provider when none matches
+    fn source_files(&self) -> &[&str] {
+        &[]
+    }
     fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
         Ok(None)
     }
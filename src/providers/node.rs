@@ -29,6 +29,14 @@ impl Provider for NodeProvider {
         "node"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["package.json"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.js", "**/*.ts"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("package.json"))
     }
@@ -87,7 +95,7 @@ impl NodeProvider {
     pub fn has_script(app: &App, script: &str) -> Result<bool> {
         let package_json: PackageJson = app.read_json("package.json")?;
         if let Some(scripts) = package_json.scripts {
-            if scripts.get(script).is_some() {
+            if scripts.contains_key(script) {
                 return Ok(true);
             }
         }
@@ -222,7 +230,7 @@ fn version_number_to_pkg(version: &u32) -> String {
 
 fn parse_regex_into_pkg(re: &Regex, node_version: &str) -> Option<String> {
     let matches: Vec<_> = re.captures_iter(node_version).collect();
-    if let Some(m) = matches.get(0) {
+    if let Some(m) = matches.first() {
         match m[1].parse::<u32>() {
             Ok(version) => return Some(version_number_to_pkg(&version)),
             Err(_e) => {}
@@ -13,14 +13,33 @@ use super::{node::NodeProvider, Provider};
 use anyhow::Result;
 
 const DEFAULT_PHP_VERSION: &str = "8.1";
+const AVAILABLE_PHP_VERSIONS: &[&str] = &["7.4", "8.0", "8.1"];
+
+// Settings that can't be changed from the php-fpm pool config
+const UNSUPPORTED_INI_SETTINGS: &[&str] = &["extension", "zend_extension"];
 
 pub struct PhpProvider;
 
+#[derive(Debug, PartialEq, Eq)]
+enum PhpFramework {
+    Laravel,
+    Symfony,
+    WordPress,
+}
+
 impl Provider for PhpProvider {
     fn name(&self) -> &str {
         "php"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["composer.json", "index.php"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.php"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("composer.json") || app.includes_file("index.php"))
     }
@@ -74,20 +93,57 @@ impl Provider for PhpProvider {
         Ok(None)
     }
 
-    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
-        Ok(Some(StartPhase::new(format!(
-            "([ -e /app/storage ] && chmod -R ugo+w /app/storage); perl {} {} /nginx.conf && echo \"Server starting on port $PORT\" && (php-fpm -y {} & nginx -c /nginx.conf)",
+    fn start(&self, app: &App, env: &Environment) -> Result<Option<StartPhase>> {
+        let mut processes = vec![
+            format!("php-fpm -y {}", app.asset_path("php-fpm.conf")),
+            "nginx -c /nginx.conf".to_string(),
+        ];
+
+        // Laravel config and routes are cached at start so that they see the runtime environment
+        let mut prepare = "".to_string();
+        if self.get_framework(app) == Some(PhpFramework::Laravel) {
+            prepare = "php artisan optimize && ".to_string();
+
+            let queue_connection = env.get_variable("QUEUE_CONNECTION").map(|c| c.as_str());
+            if matches!(queue_connection, Some(connection) if connection != "sync") {
+                processes.push("php artisan queue:work".to_string());
+            }
+        }
+
+        let mut start_phase = StartPhase::new(format!(
+            "perl {} {} /nginx.conf && {}echo \"Server starting on port $PORT\" && ({})",
             app.asset_path("transform-config.pl"),
             app.asset_path("nginx.template.conf"),
-            app.asset_path("php-fpm.conf"),
-        ))))
+            prepare,
+            processes.join(" & "),
+        ));
+
+        // php-fpm workers write logs, sessions and caches
+        for path in ["storage", "bootstrap/cache"] {
+            if app.includes_directory(path) {
+                start_phase.add_writable_path(path.to_string());
+            }
+        }
+
+        Ok(Some(start_phase))
     }
 
-    fn static_assets(&self, _app: &App, _env: &Environment) -> Result<Option<StaticAssets>> {
+    fn static_assets(&self, app: &App, _env: &Environment) -> Result<Option<StaticAssets>> {
+        let mut php_fpm_conf = include_str!("php/php-fpm.conf").to_string();
+        for (name, value) in self.get_ini_overrides(app)? {
+            // php-fpm only accepts booleans with php_flag
+            let directive = if is_ini_boolean(&value) {
+                "php_flag"
+            } else {
+                "php_value"
+            };
+            php_fpm_conf.push_str(&format!("\n{}[{}] = {}", directive, name, value));
+        }
+
         Ok(Some(static_asset_list! {
             "nginx.template.conf" => include_str!("php/nginx.template.conf"),
             "transform-config.pl" => include_str!("php/transform-config.pl"),
-            "php-fpm.conf" => include_str!("php/php-fpm.conf")
+            "php-fpm.conf" => php_fpm_conf
         }))
     }
 
@@ -98,43 +154,85 @@ impl Provider for PhpProvider {
     ) -> Result<Option<EnvironmentVariables>> {
         let mut vars = EnvironmentVariables::new();
         vars.insert("PORT".to_string(), "80".to_string());
-        if app.includes_file("artisan") {
-            vars.insert("IS_LARAVEL".to_string(), "yes".to_string());
+
+        let framework = self.get_framework(app);
+        // The app is in a subdirectory of /app when planned with `--root`
+        let app_dir = app.image_dir();
+        let (root_dir, fallback_path) = match framework {
+            Some(PhpFramework::Laravel) | Some(PhpFramework::Symfony) => {
+                (format!("{}/public", app_dir), "/index.php")
+            }
+            Some(PhpFramework::WordPress) => (app_dir, "/index.php"),
+            None => (app_dir, ""),
+        };
+        vars.insert("NIXPACKS_PHP_ROOT_DIR".to_string(), root_dir);
+        vars.insert(
+            "NIXPACKS_PHP_FALLBACK_PATH".to_string(),
+            fallback_path.to_string(),
+        );
+
+        match framework {
+            Some(PhpFramework::Laravel) => {
+                vars.insert("IS_LARAVEL".to_string(), "yes".to_string());
+            }
+            Some(PhpFramework::Symfony) => {
+                vars.insert("APP_ENV".to_string(), "prod".to_string());
+            }
+            _ => {}
         }
+
         Ok(Some(vars))
     }
 }
 
 impl PhpProvider {
+    fn get_framework(&self, app: &App) -> Option<PhpFramework> {
+        let composer_json: ComposerJson = app.read_json("composer.json").unwrap_or_default();
+
+        if app.includes_file("artisan") {
+            Some(PhpFramework::Laravel)
+        } else if app.includes_file("symfony.lock")
+            || composer_json
+                .require
+                .contains_key("symfony/framework-bundle")
+        {
+            Some(PhpFramework::Symfony)
+        } else if app.includes_file("wp-config.php")
+            || app.includes_file("wp-config-sample.php")
+            || app.includes_file("wp-login.php")
+        {
+            Some(PhpFramework::WordPress)
+        } else {
+            None
+        }
+    }
+
     fn get_php_package(&self, app: &App) -> Result<String> {
         let version = self.get_php_version(app)?;
         Ok(format!("php{}", version.replace('.', "")))
     }
+
     fn get_php_version(&self, app: &App) -> Result<String> {
         let composer_json: ComposerJson = app.read_json("composer.json")?;
-        let version = composer_json.require.get("php").map(|v| v.to_string());
-        Ok(match version {
-            Some(v) => {
-                if v.contains("8.0") {
-                    "8.0".to_string()
-                } else if v.contains("8.1") {
-                    "8.1".to_string()
-                } else if v.contains("7.4") {
-                    "7.4".to_string()
-                } else {
-                    println!(
+        let constraint = composer_json.require.get("php").map(|v| v.to_string());
+        Ok(match constraint {
+            Some(constraint) => match resolve_php_version(&constraint) {
+                Some(version) => version.to_string(),
+                None => {
+                    eprintln!(
                         "Warning: PHP version {} is not available, using PHP {}",
-                        v, DEFAULT_PHP_VERSION
+                        constraint, DEFAULT_PHP_VERSION
                     );
                     DEFAULT_PHP_VERSION.to_string()
                 }
-            }
+            },
             None => {
-                println!("Warning: No PHP version specified, using PHP {}; see https://getcomposer.org/doc/04-schema.md#package-links for how to specify a PHP version.", DEFAULT_PHP_VERSION);
+                eprintln!("Warning: No PHP version specified, using PHP {}; see https://getcomposer.org/doc/04-schema.md#package-links for how to specify a PHP version.", DEFAULT_PHP_VERSION);
                 DEFAULT_PHP_VERSION.to_string()
             }
         })
     }
+
     fn get_php_extensions(&self, app: &App) -> Result<Vec<String>> {
         let composer_json: ComposerJson = app.read_json("composer.json")?;
         let mut extensions = Vec::new();
@@ -150,9 +248,191 @@ impl PhpProvider {
         }
         Ok(extensions)
     }
+
+    /// Settings from the project's `php.ini` and `.user.ini`, with `.user.ini` taking precedence
+    fn get_ini_overrides(&self, app: &App) -> Result<Vec<(String, String)>> {
+        let mut overrides: Vec<(String, String)> = Vec::new();
+        for file in ["php.ini", ".user.ini"] {
+            if !app.includes_file(file) {
+                continue;
+            }
+
+            for (name, value) in parse_ini(&app.read_file(file)?) {
+                if UNSUPPORTED_INI_SETTINGS.contains(&name.as_str()) {
+                    eprintln!(
+                        "Warning: `{}` in {} is not supported, add the extension to composer.json instead",
+                        name, file
+                    );
+                    continue;
+                }
+
+                overrides.retain(|(existing, _)| *existing != name);
+                overrides.push((name, value));
+            }
+        }
+
+        Ok(overrides)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Parse the `name = value` pairs out of an ini file, ignoring sections and comments
+fn parse_ini(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with(';') && !line.starts_with('['))
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), parse_ini_value(value)))
+        .collect()
+}
+
+/// Quoted values are used up to the closing quote, otherwise `;` starts a comment
+fn parse_ini_value(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(quoted) = value.strip_prefix(quote) {
+            return match quoted.split_once(quote) {
+                Some((quoted, _)) => quoted.to_string(),
+                None => quoted.to_string(),
+            };
+        }
+    }
+
+    match value.split_once(';') {
+        Some((value, _)) => value.trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn is_ini_boolean(value: &str) -> bool {
+    ["on", "off", "true", "false", "yes", "no"]
+        .iter()
+        .any(|boolean| value.eq_ignore_ascii_case(boolean))
+}
+
+/// Returns the newest available PHP version that satisfies a composer constraint such as `^7.4 || ^8.0`
+fn resolve_php_version(constraint: &str) -> Option<&'static str> {
+    AVAILABLE_PHP_VERSIONS
+        .iter()
+        .rev()
+        .find(|version| {
+            constraint
+                .split('|')
+                .map(|alternative| alternative.trim())
+                .filter(|alternative| !alternative.is_empty())
+                .any(|alternative| {
+                    alternative
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|condition| !condition.is_empty())
+                        .all(|condition| satisfies_condition(version, condition))
+                })
+        })
+        .copied()
+}
+
+/// Check a single condition (e.g. `>=7.4`, `^8.0` or `8.1.*`) against a `major.minor` release line
+fn satisfies_condition(version: &str, condition: &str) -> bool {
+    if condition == "*" {
+        return true;
+    }
+
+    let op_len = condition
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(condition.len());
+    let (op, constraint_version) = condition.split_at(op_len);
+
+    let constraint_parts = constraint_version
+        .split('.')
+        .take_while(|part| *part != "*" && *part != "x")
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>();
+    let constraint_parts = match constraint_parts {
+        Some(parts) if !parts.is_empty() => parts,
+        _ => return false,
+    };
+    let lower = (
+        constraint_parts[0],
+        *constraint_parts.get(1).unwrap_or(&0),
+        *constraint_parts.get(2).unwrap_or(&0),
+    );
+
+    let (major, minor) = match version.split_once('.') {
+        Some((major, minor)) => (
+            major.parse::<u32>().unwrap_or_default(),
+            minor.parse::<u32>().unwrap_or_default(),
+        ),
+        None => return false,
+    };
+    // Compare against the newest patch release of the line
+    let candidate = (major, minor, u32::MAX);
+
+    match op {
+        "^" => candidate >= lower && major == lower.0,
+        "~" if constraint_parts.len() > 2 => {
+            candidate >= lower && (major, minor) == (lower.0, lower.1)
+        }
+        "~" => candidate >= lower && major == lower.0,
+        ">=" => candidate >= lower,
+        ">" => candidate > lower,
+        "<=" => (major, minor) <= (lower.0, lower.1),
+        "<" => (major, minor, 0) < lower,
+        "!=" => (major, minor) != (lower.0, lower.1) || constraint_parts.len() > 2,
+        // Exact versions and wildcards only need to match the parts that are specified
+        "" | "=" | "==" => major == lower.0 && (constraint_parts.len() < 2 || minor == lower.1),
+        _ => false,
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
 struct ComposerJson {
+    #[serde(default)]
     require: HashMap<String, String>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_php_version() {
+        assert_eq!(resolve_php_version("^8.1"), Some("8.1"));
+        assert_eq!(resolve_php_version("^7.4"), Some("7.4"));
+        assert_eq!(resolve_php_version("^7.4 || ^8.0"), Some("8.1"));
+        assert_eq!(resolve_php_version(">=7.4 <8.1"), Some("8.0"));
+        assert_eq!(resolve_php_version(">=7.2, <8.0"), Some("7.4"));
+        assert_eq!(resolve_php_version("~8.0.2"), Some("8.0"));
+        assert_eq!(resolve_php_version("8.0.*"), Some("8.0"));
+        assert_eq!(resolve_php_version("7.4.3"), Some("7.4"));
+        assert_eq!(resolve_php_version("*"), Some("8.1"));
+        assert_eq!(resolve_php_version("^5.6"), None);
+    }
+
+    #[test]
+    fn test_parse_ini() {
+        assert_eq!(
+            parse_ini("; comment\n[PHP]\nmemory_limit = 256M\n\nupload_max_filesize=20M"),
+            vec![
+                ("memory_limit".to_string(), "256M".to_string()),
+                ("upload_max_filesize".to_string(), "20M".to_string())
+            ]
+        );
+        assert_eq!(
+            parse_ini(
+                "display_errors = \"On\" ; comment\nerror_log = /tmp/php.log ; log\ndate.timezone = 'Europe/Berlin'"
+            ),
+            vec![
+                ("display_errors".to_string(), "On".to_string()),
+                ("error_log".to_string(), "/tmp/php.log".to_string()),
+                ("date.timezone".to_string(), "Europe/Berlin".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_ini_boolean() {
+        assert!(is_ini_boolean("On"));
+        assert!(is_ini_boolean("false"));
+        assert!(!is_ini_boolean("0"));
+        assert!(!is_ini_boolean("256M"));
+    }
+}
@@ -7,7 +7,8 @@ sub if_stmt {
     my $value = $_[1];
     my $else = $_[2];
 
-    if($ENV{$condition} eq "yes") {
+    # Any value other than empty or "no" is truthy, e.g. `yes` or a path
+    if(defined $ENV{$condition} && $ENV{$condition} ne "" && $ENV{$condition} ne "no") {
         return $value;
     } else {
         return $else;
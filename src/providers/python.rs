@@ -29,6 +29,14 @@ impl Provider for PythonProvider {
         "python"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["main.py", "requirements.txt", "pyproject.toml"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.py"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("main.py")
             || app.includes_file("requirements.txt")
@@ -225,7 +233,7 @@ impl PythonProvider {
         let matches = matches.unwrap();
 
         // Fetch python versions into tuples with defaults
-        fn as_default(v: Option<Match>) -> &str {
+        fn as_default(v: Option<Match<'_>>) -> &str {
             match v {
                 Some(m) => m.as_str(),
                 None => "_",
@@ -267,11 +275,11 @@ impl PythonProvider {
         let module_name = chain!(project.project.clone() =>
             (
                 |proj| proj.packages,
-                |pkgs| pkgs.get(0).cloned()
+                |pkgs| pkgs.first().cloned()
             );
             (
                 |proj| proj.py_modules,
-                |mods| mods.get(0).cloned()
+                |mods| mods.first().cloned()
             );
             (
                 |_| project_name.to_owned()
@@ -1,12 +1,39 @@
 use super::{node::NodeProvider, Provider};
 use crate::nixpacks::{
     app::App,
-    environment::Environment,
-    phase::{InstallPhase, SetupPhase, StartPhase},
+    environment::{Environment, EnvironmentVariables},
+    nix::pkg::Pkg,
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
 };
-use anyhow::{bail, Ok, Result};
+use anyhow::{Ok, Result};
 use regex::Regex;
 
+const DEFAULT_RUBY_PKG_NAME: &str = "ruby";
+
+// Overlay with every released Ruby version, used when nixpkgs doesn't ship the exact patch version
+static RUBY_OVERLAY: &str = "https://github.com/bobvanderlinden/nixpkgs-ruby/archive/master.tar.gz";
+
+// (minor version, nix package, patch version shipped by nixpkgs)
+const AVAILABLE_RUBY_VERSIONS: &[(&str, &str, &str)] = &[
+    ("2.7", "ruby_2_7", "2.7.6"),
+    ("3.0", "ruby_3_0", "3.0.4"),
+    ("3.1", "ruby_3_1", "3.1.2"),
+];
+
+// Toolchain for the native extensions most Gemfiles have, e.g. puma, nio4r, bootsnap or ffi
+const BUILD_PKGS: &[&str] = &["gcc", "gnumake", "pkg-config"];
+
+// Gems with native extensions and the (nix packages, libraries) they need to build and run
+const NATIVE_GEM_DEPENDENCIES: &[(&str, &[&str], &[&str])] = &[
+    ("pg", &["postgresql"], &["postgresql"]),
+    ("mysql2", &["libmysqlclient"], &["libmysqlclient"]),
+    ("sqlite3", &["sqlite"], &["sqlite"]),
+    ("nokogiri", &["libxml2", "libxslt"], &["libxml2", "libxslt"]),
+    ("sassc", &["gnumake"], &[]),
+];
+
+const GEM_HOME: &str = "/usr/local/bundle";
+
 pub struct RubyProvider {}
 
 impl Provider for RubyProvider {
@@ -14,54 +41,139 @@ impl Provider for RubyProvider {
         "Ruby"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["Gemfile"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.rb"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("Gemfile"))
     }
 
     fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
-        let mut pkgs = vec![];
+        let mut pkgs = vec![RubyProvider::get_ruby_pkg(app, env)?];
         if app.includes_file("package.json") {
-            pkgs = NodeProvider::get_nix_packages(app, env)?
+            pkgs.append(&mut NodeProvider::get_nix_packages(app, env)?);
         }
+
+        let (mut native_pkgs, native_libs) = RubyProvider::get_native_dependencies(app);
+        pkgs.append(&mut native_pkgs);
+
         let mut setup_phase = SetupPhase::new(pkgs);
-        setup_phase.add_apt_pkgs(vec!["procps".to_string()]);
-        setup_phase.add_cmd(
-            "curl -sSL https://get.rvm.io | bash -s stable && source /etc/profile.d/rvm.sh"
-                .to_string(),
-        );
-        setup_phase.add_cmd("rvm install ".to_string() + &self.get_ruby_version(app).unwrap());
-        setup_phase.add_cmd("gem install ".to_string() + &self.get_bundler_version(app));
+        if !native_libs.is_empty() {
+            setup_phase.add_libraries(native_libs);
+        }
+
         Ok(Some(setup_phase))
     }
 
     fn install(&self, app: &App, _env: &Environment) -> Result<Option<InstallPhase>> {
-        let mut install_phase = InstallPhase::new("bundle install".to_string());
-        install_phase.add_file_dependency("Gemfile*".to_string());
+        let mut install_phase = InstallPhase::default();
         if app.includes_file("package.json") {
-            install_phase.add_file_dependency("package.json".to_string());
-            install_phase
-                .cmds
-                .clone()
-                .unwrap_or_default()
-                .insert(0, NodeProvider::get_install_command(app));
-
-            for file in ["package.json", "package-lock.json"].iter() {
+            install_phase.add_cmd(NodeProvider::get_install_command(app));
+            for file in [
+                "package.json",
+                "package-lock.json",
+                "yarn.lock",
+                "pnpm-lock.yaml",
+                ".yarnrc.yml",
+            ] {
                 if app.includes_file(file) {
                     install_phase.add_file_dependency(file.to_string());
                 }
             }
         }
+
+        if let Some(bundler_version) = RubyProvider::get_bundler_version(app) {
+            install_phase.add_cmd(format!("gem install bundler -v {}", bundler_version));
+        }
+        install_phase.add_cmd("bundle install".to_string());
+        install_phase.add_file_dependency("Gemfile*".to_string());
+        install_phase.add_path(format!("{}/bin", GEM_HOME));
+
         Ok(Some(install_phase))
     }
 
+    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        if !RubyProvider::is_rails_app(app) {
+            return Ok(None);
+        }
+
+        let mut build_phase = BuildPhase::default();
+        if RubyProvider::uses_asset_pipeline(app) {
+            // Production apps need a secret key to boot, which isn't available at build time.
+            // Rails 7.1 uses a generated key with SECRET_KEY_BASE_DUMMY, older versions need one set
+            build_phase.add_cmd(
+                "SECRET_KEY_BASE_DUMMY=1 SECRET_KEY_BASE=${SECRET_KEY_BASE:-precompile_placeholder} bundle exec rake assets:precompile"
+                    .to_string(),
+            );
+        }
+        if RubyProvider::uses_gem(app, "bootsnap") {
+            build_phase.add_cmd("bundle exec bootsnap precompile --gemfile app/ lib/".to_string());
+        }
+
+        Ok(Some(build_phase))
+    }
+
     fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
-        Ok(Some(StartPhase::new(self.get_start_command(app))))
+        let mut start_phase = StartPhase::new(RubyProvider::get_start_command(app));
+        if RubyProvider::is_rails_app(app) && app.includes_file("config/database.yml") {
+            start_phase.set_release_cmd("bundle exec rake db:migrate".to_string());
+        }
+
+        // The servers default to ${PORT:-3000}
+        if start_phase.cmd.as_deref() != Some("bundle exec rake") {
+            start_phase.add_port(3000);
+        }
+        if RubyProvider::is_rails_app(app) {
+            for path in ["tmp", "log", "storage"] {
+                if app.includes_directory(path) {
+                    start_phase.add_writable_path(path.to_string());
+                }
+            }
+        }
+        // Rails 7.1 generates a health check route
+        if RubyProvider::is_rails_app(app)
+            && app
+                .read_file("config/routes.rb")
+                .unwrap_or_default()
+                .contains("rails/health#show")
+        {
+            start_phase.set_healthcheck_path("/up".to_string());
+        }
+
+        Ok(Some(start_phase))
+    }
+
+    fn environment_variables(
+        &self,
+        app: &App,
+        _env: &Environment,
+    ) -> Result<Option<EnvironmentVariables>> {
+        // The nix store is read-only, so gems are installed outside of it
+        let mut variables = EnvironmentVariables::from([
+            ("GEM_HOME".to_string(), GEM_HOME.to_string()),
+            ("GEM_PATH".to_string(), GEM_HOME.to_string()),
+            ("BUNDLE_PATH".to_string(), GEM_HOME.to_string()),
+        ]);
+
+        if RubyProvider::is_rails_app(app) {
+            variables.insert("RAILS_ENV".to_string(), "production".to_string());
+            // There is no reverse proxy in front of the app to serve the compiled assets
+            variables.insert("RAILS_SERVE_STATIC_FILES".to_string(), "1".to_string());
+            variables.insert("RAILS_LOG_TO_STDOUT".to_string(), "enabled".to_string());
+        }
+
+        Ok(Some(variables))
     }
 }
 
 impl RubyProvider {
-    fn get_start_command(&self, app: &App) -> String {
-        if self.is_rails_app(app) {
+    fn get_start_command(app: &App) -> String {
+        if RubyProvider::is_rails_app(app) {
             if app.includes_file("rails") {
                 "bundle exec rails server -b 0.0.0.0 -p ${PORT:-3000}".to_string()
             } else {
@@ -76,41 +188,152 @@ impl RubyProvider {
         }
     }
 
-    fn get_ruby_version(&self, app: &App) -> Result<String> {
+    /// Returns the Ruby nix package, using the overlay for patch versions nixpkgs doesn't ship
+    fn get_ruby_pkg(app: &App, env: &Environment) -> Result<Pkg> {
+        let version = match RubyProvider::get_ruby_version(app, env)? {
+            Some(version) => version,
+            None => return Ok(Pkg::new(DEFAULT_RUBY_PKG_NAME)),
+        };
+
+        let re = Regex::new(r"^(\d+)\.(\d+)(?:\.(\d+))?(-(?:preview|rc)\d+)?")?;
+        let captures = match re.captures(&version) {
+            Some(captures) => captures,
+            None => {
+                eprintln!(
+                    "Warning: Ruby version {} could not be parsed, using the default Ruby",
+                    version
+                );
+                return Ok(Pkg::new(DEFAULT_RUBY_PKG_NAME));
+            }
+        };
+
+        let minor_version = format!("{}.{}", &captures[1], &captures[2]);
+        // Prereleases are only in the overlay
+        let is_patch_version = captures.get(3).is_some() || captures.get(4).is_some();
+
+        let available = AVAILABLE_RUBY_VERSIONS
+            .iter()
+            .find(|(minor, _, _)| *minor == minor_version);
+
+        match available {
+            Some((_, pkg, patch)) if !is_patch_version || *patch == &captures[0] => {
+                Ok(Pkg::new(pkg))
+            }
+            _ if is_patch_version => {
+                Ok(Pkg::new(&format!("pkgs.\"ruby-{}\"", &captures[0])).from_overlay(RUBY_OVERLAY))
+            }
+            _ => {
+                eprintln!(
+                    "Warning: Ruby {} is not available, using the default Ruby",
+                    version
+                );
+                Ok(Pkg::new(DEFAULT_RUBY_PKG_NAME))
+            }
+        }
+    }
+
+    // The Ruby version is taken from the first of
+    // - `NIXPACKS_RUBY_VERSION`
+    // - `.ruby-version`
+    // - the `ruby` directive in the Gemfile
+    // - the `RUBY VERSION` section of the Gemfile.lock
+    fn get_ruby_version(app: &App, env: &Environment) -> Result<Option<String>> {
+        if let Some(version) = env.get_config_variable("RUBY_VERSION") {
+            return Ok(Some(normalize_ruby_version(version)));
+        }
+
         if app.includes_file(".ruby-version") {
-            return Ok(app.read_file(".ruby-version")?.trim().to_string());
+            return Ok(Some(normalize_ruby_version(
+                &app.read_file(".ruby-version")?,
+            )));
         }
-        let re_gemfile = Regex::new(r#"ruby (?:'|")(.*)(?:'|")[^>]"#).unwrap();
+
+        let re_gemfile = Regex::new(r#"(?m)^\s*ruby (?:'|")([^'"]*)(?:'|")"#)?;
         let gemfile = app.read_file("Gemfile").unwrap_or_default();
         if let Some(value) = re_gemfile.captures(&gemfile) {
-            return Ok(format!("ruby-{}", value.get(1).unwrap().as_str()));
+            return Ok(Some(normalize_ruby_version(&value[1])));
         }
+
         let re_gemfile_lock =
-            Regex::new(r#"ruby ((?:0|[1-9]\d*)\.(?:0|[1-9]\d*)\.(?:0|[1-9]\d*))[^>]"#).unwrap();
+            Regex::new(r#"ruby ((?:0|[1-9]\d*)\.(?:0|[1-9]\d*)\.(?:0|[1-9]\d*))[^>]"#)?;
         let gemfile_lock = app.read_file("Gemfile.lock").unwrap_or_default();
         if let Some(value) = re_gemfile_lock.captures(&gemfile_lock) {
-            return Ok(format!("ruby-{}", value.get(1).unwrap().as_str()));
+            return Ok(Some(value[1].to_string()));
         }
-        bail!("Please specify ruby's version in .ruby-version file")
+
+        Ok(None)
     }
 
     // Loop through Gemfile.lock and find bundler's version (Line below BUNDLED WITH)
-    fn get_bundler_version(&self, app: &App) -> String {
-        if app.includes_file("Gemfile.lock") {
-            let gemfile_lock = app.read_file("Gemfile.lock").unwrap_or_default();
-            let array_lock: Vec<&str> = gemfile_lock.split('\n').collect();
-            for line in 0..array_lock.len() {
-                if array_lock[line].contains("BUNDLED WITH") && line + 1 < array_lock.len() {
-                    return format!("bundler:{}", array_lock[line + 1].trim());
+    fn get_bundler_version(app: &App) -> Option<String> {
+        let gemfile_lock = app.read_file("Gemfile.lock").ok()?;
+        let array_lock: Vec<&str> = gemfile_lock.split('\n').collect();
+        for line in 0..array_lock.len() {
+            if array_lock[line].contains("BUNDLED WITH") && line + 1 < array_lock.len() {
+                return Some(array_lock[line + 1].trim().to_string());
+            }
+        }
+
+        None
+    }
+
+    /// Returns the nix packages and libraries needed by gems with native extensions
+    fn get_native_dependencies(app: &App) -> (Vec<Pkg>, Vec<String>) {
+        // Native extensions are compiled during `bundle install`
+        let mut pkgs: Vec<Pkg> = BUILD_PKGS.iter().map(|pkg| Pkg::new(pkg)).collect();
+        let mut libs: Vec<String> = Vec::new();
+
+        for (gem, gem_pkgs, gem_libs) in NATIVE_GEM_DEPENDENCIES {
+            if !RubyProvider::uses_gem(app, gem) {
+                continue;
+            }
+
+            for pkg in gem_pkgs.iter() {
+                if !pkgs.iter().any(|p| p.name == *pkg) {
+                    pkgs.push(Pkg::new(pkg));
+                }
+            }
+            for lib in gem_libs.iter() {
+                if !libs.iter().any(|l| l == lib) {
+                    libs.push(lib.to_string());
                 }
             }
-            "bundler".to_string()
-        } else {
-            "bundler".to_string()
         }
+
+        (pkgs, libs)
     }
 
-    fn is_rails_app(&self, app: &App) -> bool {
+    fn uses_gem(app: &App, gem: &str) -> bool {
+        let (file, pattern) = if app.includes_file("Gemfile.lock") {
+            (
+                "Gemfile.lock",
+                format!(r"(?m)^\s{{4}}{} \(", regex::escape(gem)),
+            )
+        } else {
+            (
+                "Gemfile",
+                format!(r#"(?m)^\s*gem\s+['"]{}['"]"#, regex::escape(gem)),
+            )
+        };
+
+        Regex::new(&pattern)
+            .map(|re| re.is_match(&app.read_file(file).unwrap_or_default()))
+            .unwrap_or(false)
+    }
+
+    fn uses_asset_pipeline(app: &App) -> bool {
+        [
+            "sprockets-rails",
+            "sprockets",
+            "propshaft",
+            "jsbundling-rails",
+            "cssbundling-rails",
+        ]
+        .iter()
+        .any(|gem| RubyProvider::uses_gem(app, gem))
+    }
+
+    fn is_rails_app(app: &App) -> bool {
         app.includes_file("config/application.rb")
             && app
                 .read_file("config/application.rb")
@@ -119,18 +342,33 @@ impl RubyProvider {
     }
 }
 
+/// Strip the `ruby-` prefix, patch level suffix and constraint operators from a version string
+///
+/// Prereleases keep their suffix, e.g. `3.2.0-preview1`.
+fn normalize_ruby_version(version: &str) -> String {
+    let version = version
+        .trim()
+        .trim_start_matches("ruby-")
+        .trim_start_matches(|c: char| c == '~' || c == '>' || c == '=' || c.is_whitespace());
+
+    // `2.7.2p137` or `2.7.2-p137`
+    let re_patch_level = Regex::new(r"-?p\d+$").unwrap();
+    re_patch_level.replace(version, "").to_string()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_gemfile_version() -> Result<()> {
         assert_eq!(
             RubyProvider::get_ruby_version(
-                &RubyProvider {},
-                &App::new("./examples/ruby-gemfile")?
+                &App::new("./examples/ruby-gemfile")?,
+                &Environment::default()
             )?,
-            "ruby-2.7.2"
+            Some("2.7.2".to_string())
         );
 
         Ok(())
@@ -140,10 +378,10 @@ mod test {
     fn test_gemfile_lock_version() -> Result<()> {
         assert_eq!(
             RubyProvider::get_ruby_version(
-                &RubyProvider {},
-                &App::new("./examples/ruby-gemfile-lock")?
+                &App::new("./examples/ruby-gemfile-lock")?,
+                &Environment::default()
             )?,
-            "ruby-2.7.2"
+            Some("2.7.2".to_string())
         );
 
         Ok(())
@@ -151,11 +389,65 @@ mod test {
 
     #[test]
     fn test_no_version() -> Result<()> {
-        assert!(RubyProvider::get_ruby_version(
-            &RubyProvider {},
-            &App::new("./examples/ruby-no-version")?,
-        )
-        .is_err());
+        assert_eq!(
+            RubyProvider::get_ruby_pkg(
+                &App::new("./examples/ruby-no-version")?,
+                &Environment::default()
+            )?,
+            Pkg::new(DEFAULT_RUBY_PKG_NAME)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_patch_version_from_overlay() -> Result<()> {
+        assert_eq!(
+            RubyProvider::get_ruby_pkg(
+                &App::new("./examples/ruby-gemfile")?,
+                &Environment::default()
+            )?,
+            Pkg::new("pkgs.\"ruby-2.7.2\"").from_overlay(RUBY_OVERLAY)
+        );
         Ok(())
     }
+
+    #[test]
+    fn test_preview_version_from_overlay() -> Result<()> {
+        assert_eq!(
+            RubyProvider::get_ruby_pkg(
+                &App::new("./examples/ruby-gemfile")?,
+                &Environment::new(HashMap::from([(
+                    "NIXPACKS_RUBY_VERSION".to_string(),
+                    "3.2.0-preview1".to_string()
+                )]))
+            )?,
+            Pkg::new("pkgs.\"ruby-3.2.0-preview1\"").from_overlay(RUBY_OVERLAY)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_from_environment_variable() -> Result<()> {
+        assert_eq!(
+            RubyProvider::get_ruby_pkg(
+                &App::new("./examples/ruby-gemfile")?,
+                &Environment::new(HashMap::from([(
+                    "NIXPACKS_RUBY_VERSION".to_string(),
+                    "3.1".to_string()
+                )]))
+            )?,
+            Pkg::new("ruby_3_1")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_ruby_version() {
+        assert_eq!(normalize_ruby_version("ruby-3.0.3\n"), "3.0.3");
+        assert_eq!(normalize_ruby_version("3.0.3p157"), "3.0.3");
+        assert_eq!(normalize_ruby_version("~> 3.1"), "3.1");
+        assert_eq!(normalize_ruby_version("2.7.2-p137"), "2.7.2");
+        assert_eq!(normalize_ruby_version("3.2.0-preview1"), "3.2.0-preview1");
+        assert_eq!(normalize_ruby_version("ruby-3.2.0-rc1"), "3.2.0-rc1");
+    }
 }
@@ -5,25 +5,139 @@ use crate::nixpacks::{
     app::App,
     environment::{Environment, EnvironmentVariables},
     nix::pkg::Pkg,
-    phase::{BuildPhase, SetupPhase, StartPhase},
+    phase::{BuildPhase, InstallPhase, SetupPhase, StartPhase},
 };
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use globset::GlobBuilder;
 use serde::{Deserialize, Serialize};
 
 static RUST_OVERLAY: &str = "https://github.com/oxalica/rust-overlay/archive/master.tar.gz";
 static DEFAULT_RUST_PACKAGE: &str = "rust-bin.stable.latest.default";
 
+// Crates linking to native libraries, the (nix packages, libraries) they need,
+// and whether they can be statically linked against musl
+const NATIVE_CRATE_DEPENDENCIES: &[(&str, &[&str], &[&str], bool)] = &[
+    (
+        "openssl-sys",
+        &["openssl", "pkg-config"],
+        &["openssl"],
+        false,
+    ),
+    (
+        "libsqlite3-sys",
+        &["sqlite", "pkg-config"],
+        &["sqlite"],
+        false,
+    ),
+    (
+        "pq-sys",
+        &["postgresql", "pkg-config"],
+        &["postgresql"],
+        false,
+    ),
+    (
+        "mysqlclient-sys",
+        &["libmysqlclient", "pkg-config"],
+        &["libmysqlclient"],
+        false,
+    ),
+    (
+        "rdkafka-sys",
+        &["cmake", "pkg-config", "openssl", "zlib"],
+        &["openssl", "zlib"],
+        false,
+    ),
+    ("prost-build", &["protobuf"], &[], true),
+    ("cmake", &["cmake"], &[], true),
+];
+
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CargoTomlPackage {
     pub name: String,
-    pub version: String,
+    /// Either a version string or `{ workspace = true }`
+    #[serde(rename = "rust-version")]
+    pub rust_version: Option<toml::Value>,
+    #[serde(rename = "default-run")]
+    pub default_run: Option<String>,
+}
+
+/// A `[[bin]]`, `[[example]]`, `[[test]]` or `[[bench]]` target
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CargoTomlTarget {
+    pub name: String,
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CargoTomlLib {
+    pub path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CargoTomlWorkspacePackage {
     #[serde(rename = "rust-version")]
     pub rust_version: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CargoTomlWorkspace {
+    #[serde(default)]
+    pub members: Vec<String>,
+    pub package: Option<CargoTomlWorkspacePackage>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CargoToml {
-    pub package: CargoTomlPackage,
+    pub package: Option<CargoTomlPackage>,
+    pub workspace: Option<CargoTomlWorkspace>,
+    pub lib: Option<CargoTomlLib>,
+    pub bin: Option<Vec<CargoTomlTarget>>,
+    pub example: Option<Vec<CargoTomlTarget>>,
+    pub test: Option<Vec<CargoTomlTarget>>,
+    pub bench: Option<Vec<CargoTomlTarget>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CargoLockPackage {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CargoLock {
+    #[serde(default)]
+    pub package: Vec<CargoLockPackage>,
+}
+
+/// A binary target and the package it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RustBinary {
+    pub package: String,
+    pub name: String,
+}
+
+/// A placeholder source file used to build the dependencies on their own
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StubFile {
+    path: String,
+    is_main: bool,
+}
+
+impl StubFile {
+    fn main(path: String) -> Self {
+        StubFile {
+            path,
+            is_main: true,
+        }
+    }
+
+    fn lib(path: String) -> Self {
+        StubFile {
+            path,
+            is_main: false,
+        }
+    }
 }
 
 pub struct RustProvider {}
@@ -33,6 +147,14 @@ impl Provider for RustProvider {
         "rust"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["Cargo.toml"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.rs"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("Cargo.toml"))
     }
@@ -44,8 +166,14 @@ impl Provider for RustProvider {
             rust_pkg = rust_pkg.set_override("targets", format!("[\"{target}\"]").as_str());
         }
 
-        let mut setup_phase =
-            SetupPhase::new(vec![Pkg::new("gcc"), rust_pkg.from_overlay(RUST_OVERLAY)]);
+        let mut pkgs = vec![Pkg::new("gcc"), rust_pkg.from_overlay(RUST_OVERLAY)];
+        let (mut native_pkgs, native_libs) = RustProvider::get_native_dependencies(app)?;
+        pkgs.append(&mut native_pkgs);
+
+        let mut setup_phase = SetupPhase::new(pkgs);
+        if !native_libs.is_empty() {
+            setup_phase.add_libraries(native_libs);
+        }
 
         // Include the rust toolchain file so we can install that rust version with Nix
         if let Some(toolchain_file) = RustProvider::get_rust_toolchain_file(app)? {
@@ -53,42 +181,100 @@ impl Provider for RustProvider {
         }
 
         if !env.is_config_variable_truthy("NO_MUSL") {
-            setup_phase.add_apt_pkgs(vec!["musl-tools".to_string()]);
+            let incompatible_crates = RustProvider::get_musl_incompatible_crates(app)?;
+            if incompatible_crates.is_empty() {
+                setup_phase.add_apt_pkgs(vec!["musl-tools".to_string()]);
+            } else {
+                // Printed to stderr to keep the JSON of `nixpacks plan` valid
+                eprintln!(
+                    "Warning: Building without musl because {} cannot be statically linked. Set NIXPACKS_NO_MUSL=1 to silence this warning.",
+                    incompatible_crates.join(", ")
+                );
+            }
         }
 
         Ok(Some(setup_phase))
     }
 
+    fn install(&self, app: &App, env: &Environment) -> Result<Option<InstallPhase>> {
+        // Build only the dependencies against stub sources, so that the layer
+        // is cached until the manifests change
+        // Only the manifests of the workspace are copied, so crates from other
+        // paths would be missing and are built with the app instead
+        if RustProvider::has_external_path_dependencies(app)? {
+            return Ok(None);
+        }
+
+        let manifests = RustProvider::get_manifest_files(app)?;
+        let stubs = RustProvider::get_stub_files(app)?;
+        if stubs.is_empty() {
+            return Ok(None);
+        }
+
+        let mut install_phase = InstallPhase::new(RustProvider::get_stub_cmd(&stubs));
+        install_phase.add_cmd(RustProvider::get_build_cmd(app, env)?);
+        install_phase.add_cmd(format!(
+            "rm {}",
+            stubs
+                .iter()
+                .map(|stub| stub.path.clone())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ));
+
+        for file in manifests {
+            install_phase.add_file_dependency(file);
+        }
+
+        Ok(Some(install_phase))
+    }
+
     fn build(&self, app: &App, env: &Environment) -> Result<Option<BuildPhase>> {
-        let build_phase = match RustProvider::get_target(app, env)? {
-            Some(target) => BuildPhase::new(format!("cargo build --release --target {target}")),
-            None => BuildPhase::new("cargo build --release".to_string()),
-        };
+        let build_cmd = RustProvider::get_build_cmd(app, env)?;
+
+        // The sources may be older than the dependency build, so make sure Cargo rebuilds them
+        let mut build_phase = BuildPhase::new(
+            "find . -name \"*.rs\" -not -path \"./target/*\" -exec touch {} +".to_string(),
+        );
+        build_phase.add_cmd(build_cmd);
 
         Ok(Some(build_phase))
     }
 
     fn start(&self, app: &App, env: &Environment) -> Result<Option<StartPhase>> {
-        if let Some(toml_file) = RustProvider::parse_cargo_toml(app)? {
-            let name = toml_file.package.name;
+        let binaries = RustProvider::find_binaries(app)?;
+        let name = match RustProvider::select_binary(app, env, &binaries)? {
+            Some((binary, guessed)) => {
+                // Install and build select the same binary, so only warn once
+                if guessed {
+                    eprintln!(
+                        "Warning: Found multiple binaries ({}), building {}. Set NIXPACKS_RUST_BIN to choose another.",
+                        RustProvider::binary_names(&binaries),
+                        binary.name
+                    );
+                }
+                binary.name
+            }
+            None => match RustProvider::parse_cargo_toml(app)?.and_then(|toml| toml.package) {
+                Some(package) => package.name,
+                None => return Ok(None),
+            },
+        };
 
-            let start_phase = match RustProvider::get_target(app, env)? {
-                Some(target) => {
-                    let binary_file = format!("./target/{target}/release/{name}");
-                    let mut start_phase = StartPhase::new(format!("./{name}"));
+        let start_phase = match RustProvider::get_target(app, env)? {
+            Some(target) => {
+                let binary_file = format!("./target/{target}/release/{name}");
+                let mut start_phase = StartPhase::new(format!("./{name}"));
 
-                    start_phase.run_in_slim_image();
-                    start_phase.add_file_dependency(binary_file);
+                start_phase.run_in_slim_image();
+                start_phase.add_file_dependency(binary_file);
 
-                    start_phase
-                }
-                None => StartPhase::new(format!("./target/release/{name}")),
-            };
+                start_phase
+            }
+            None => StartPhase::new(format!("./target/release/{name}")),
+        };
 
-            Ok(Some(start_phase))
-        } else {
-            Ok(None)
-        }
+        Ok(Some(start_phase))
     }
 
     fn environment_variables(
@@ -103,25 +289,452 @@ impl Provider for RustProvider {
 }
 
 impl RustProvider {
-    fn get_target(_app: &App, env: &Environment) -> Result<Option<String>> {
+    fn get_target(app: &App, env: &Environment) -> Result<Option<String>> {
         // All the user to use the default target instead of compiling with musl
-        if !env.is_config_variable_truthy("NO_MUSL") {
-            Ok(Some(format!("{}-unknown-linux-musl", ARCH)))
-        } else {
+        if env.is_config_variable_truthy("NO_MUSL")
+            || !RustProvider::get_musl_incompatible_crates(app)?.is_empty()
+        {
             Ok(None)
+        } else {
+            Ok(Some(format!("{}-unknown-linux-musl", ARCH)))
+        }
+    }
+
+    /// Names of all the crates in `Cargo.lock`
+    fn get_locked_crates(app: &App) -> Result<Vec<String>> {
+        if !app.includes_file("Cargo.lock") {
+            return Ok(Vec::new());
+        }
+
+        let cargo_lock: CargoLock = app.read_toml("Cargo.lock").context("Reading Cargo.lock")?;
+        Ok(cargo_lock
+            .package
+            .into_iter()
+            .map(|package| package.name)
+            .collect())
+    }
+
+    /// Returns the nix packages and libraries needed by crates linking to native libraries
+    fn get_native_dependencies(app: &App) -> Result<(Vec<Pkg>, Vec<String>)> {
+        let crates = RustProvider::get_locked_crates(app)?;
+        let mut pkgs: Vec<Pkg> = Vec::new();
+        let mut libs: Vec<String> = Vec::new();
+
+        for (name, crate_pkgs, crate_libs, _) in NATIVE_CRATE_DEPENDENCIES {
+            if !crates.iter().any(|c| c == name) {
+                continue;
+            }
+
+            for pkg in crate_pkgs.iter() {
+                if !pkgs.iter().any(|p| p.name == *pkg) {
+                    pkgs.push(Pkg::new(pkg));
+                }
+            }
+            for lib in crate_libs.iter() {
+                if !libs.iter().any(|l| l == lib) {
+                    libs.push(lib.to_string());
+                }
+            }
         }
+
+        Ok((pkgs, libs))
+    }
+
+    fn get_musl_incompatible_crates(app: &App) -> Result<Vec<String>> {
+        let crates = RustProvider::get_locked_crates(app)?;
+        Ok(NATIVE_CRATE_DEPENDENCIES
+            .iter()
+            .filter(|(name, _, _, musl_compatible)| {
+                !musl_compatible && crates.iter().any(|c| c == name)
+            })
+            .map(|(name, _, _, _)| name.to_string())
+            .collect())
     }
 
     fn parse_cargo_toml(app: &App) -> Result<Option<CargoToml>> {
-        if app.includes_file("Cargo.toml") {
-            let cargo_toml: CargoToml =
-                app.read_toml("Cargo.toml").context("Reading Cargo.toml")?;
+        RustProvider::parse_manifest(app, ".")
+    }
+
+    fn get_manifest_path(dir: &str) -> String {
+        if dir == "." {
+            "Cargo.toml".to_string()
+        } else {
+            format!("{}/Cargo.toml", dir)
+        }
+    }
+
+    fn parse_manifest(app: &App, dir: &str) -> Result<Option<CargoToml>> {
+        let manifest = RustProvider::get_manifest_path(dir);
+        if app.includes_file(&manifest) {
+            let cargo_toml: CargoToml = app
+                .read_toml(&manifest)
+                .with_context(|| format!("Reading {}", manifest))?;
             return Ok(Some(cargo_toml));
         }
 
         Ok(None)
     }
 
+    /// Directories of the workspace members, with globs like `crates/*` expanded
+    pub fn get_workspace_members(app: &App) -> Result<Vec<String>> {
+        let workspace = match RustProvider::parse_cargo_toml(app)?.and_then(|toml| toml.workspace) {
+            Some(workspace) => workspace,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut members = Vec::new();
+        for member in workspace.members {
+            let member = member.trim_start_matches("./").trim_end_matches('/');
+            if member.contains('*') {
+                for manifest in app.find_files(&format!("{}/Cargo.toml", member))? {
+                    let manifest = app.strip_source_path(&manifest)?;
+                    if let Some(dir) = manifest.parent().and_then(|dir| dir.to_str()) {
+                        members.push(dir.to_string());
+                    }
+                }
+            } else if app.includes_file(&format!("{}/Cargo.toml", member)) {
+                members.push(member.to_string());
+            }
+        }
+
+        Ok(members)
+    }
+
+    /// `path` dependencies and `[patch]` entries, as written in the manifest in `dir`
+    fn get_path_dependencies(app: &App, dir: &str) -> Result<Vec<String>> {
+        let manifest = RustProvider::get_manifest_path(dir);
+        if !app.includes_file(&manifest) {
+            return Ok(Vec::new());
+        }
+        let cargo_toml: toml::Value = app
+            .read_toml(&manifest)
+            .with_context(|| format!("Reading {}", manifest))?;
+
+        let mut tables = DEPENDENCY_TABLES
+            .iter()
+            .map(|name| cargo_toml.get(name))
+            .collect::<Vec<_>>();
+        if let Some(targets) = cargo_toml.get("target").and_then(|t| t.as_table()) {
+            for target in targets.values() {
+                tables.extend(DEPENDENCY_TABLES.iter().map(|name| target.get(name)));
+            }
+        }
+        tables.push(
+            cargo_toml
+                .get("workspace")
+                .and_then(|workspace| workspace.get("dependencies")),
+        );
+        if let Some(patches) = cargo_toml.get("patch").and_then(|p| p.as_table()) {
+            tables.extend(patches.values().map(Some));
+        }
+
+        Ok(tables
+            .into_iter()
+            .flatten()
+            .filter_map(|table| table.as_table())
+            .flat_map(|table| table.values())
+            .filter_map(|dependency| dependency.get("path")?.as_str())
+            .map(|path| path.to_string())
+            .collect())
+    }
+
+    /// Whether a crate is used from a path that is not the root package or a workspace member
+    fn has_external_path_dependencies(app: &App) -> Result<bool> {
+        let mut dirs = vec![".".to_string()];
+        dirs.append(&mut RustProvider::get_workspace_members(app)?);
+
+        for dir in &dirs {
+            for path in RustProvider::get_path_dependencies(app, dir)? {
+                match join_relative_path(dir, &path) {
+                    Some(path) if dirs.contains(&path) => {}
+                    _ => return Ok(true),
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// All binary targets of the root package and the workspace members
+    ///
+    /// Explicit `[[bin]]` entries are used as is, otherwise `src/main.rs` and
+    /// `src/bin/*.rs` are discovered the same way Cargo does.
+    pub fn find_binaries(app: &App) -> Result<Vec<RustBinary>> {
+        let mut dirs = vec![".".to_string()];
+        dirs.append(&mut RustProvider::get_workspace_members(app)?);
+
+        let mut binaries = Vec::new();
+        for dir in dirs {
+            let (package, bins) = match RustProvider::parse_manifest(app, &dir)? {
+                Some(CargoToml {
+                    package: Some(package),
+                    bin,
+                    ..
+                }) => (package, bin),
+                _ => continue,
+            };
+
+            let mut names = match bins {
+                Some(bins) => bins.into_iter().map(|bin| bin.name).collect(),
+                None => RustProvider::discover_binaries(app, &dir, &package.name),
+            };
+            names.dedup();
+
+            binaries.extend(names.into_iter().map(|name| RustBinary {
+                package: package.name.clone(),
+                name,
+            }));
+        }
+
+        Ok(binaries)
+    }
+
+    fn discover_binaries(app: &App, dir: &str, package_name: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        if app.includes_file(&format!("{}/src/main.rs", dir)) {
+            names.push(package_name.to_string());
+        }
+
+        let bin_dir = app.source.join(dir).join("src/bin");
+        if let Ok(entries) = std::fs::read_dir(bin_dir) {
+            let mut bins = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter_map(|path| {
+                    if path.is_file() && path.extension().is_some_and(|ext| ext == "rs") {
+                        path.file_stem()?.to_str().map(|name| name.to_string())
+                    } else if path.join("main.rs").is_file() {
+                        path.file_name()?.to_str().map(|name| name.to_string())
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            bins.sort();
+            names.append(&mut bins);
+        }
+
+        names
+    }
+
+    /// The binary to build and run
+    ///
+    /// Taken from the first of
+    /// - `NIXPACKS_RUST_BIN`
+    /// - `default-run` of the root package
+    /// - the only binary
+    /// - the binary named after the root package
+    pub fn get_binary(app: &App, env: &Environment) -> Result<Option<RustBinary>> {
+        let binaries = RustProvider::find_binaries(app)?;
+        Ok(RustProvider::select_binary(app, env, &binaries)?.map(|(binary, _)| binary))
+    }
+
+    /// The binary from `get_binary`, and whether it was guessed from several binaries
+    fn select_binary(
+        app: &App,
+        env: &Environment,
+        binaries: &[RustBinary],
+    ) -> Result<Option<(RustBinary, bool)>> {
+        if let Some(name) = env.get_config_variable("RUST_BIN") {
+            return match binaries.iter().find(|binary| &binary.name == name) {
+                Some(binary) => Ok(Some((binary.clone(), false))),
+                None => bail!(
+                    "No binary found for NIXPACKS_RUST_BIN={}. Available binaries: {}",
+                    name,
+                    RustProvider::binary_names(binaries)
+                ),
+            };
+        }
+
+        let root_package = RustProvider::parse_cargo_toml(app)?.and_then(|toml| toml.package);
+        if let Some(package) = &root_package {
+            if let Some(default_run) = &package.default_run {
+                if let Some(binary) = binaries
+                    .iter()
+                    .find(|binary| binary.package == package.name && &binary.name == default_run)
+                {
+                    return Ok(Some((binary.clone(), false)));
+                }
+            }
+        }
+
+        if binaries.len() <= 1 {
+            return Ok(binaries.first().map(|binary| (binary.clone(), false)));
+        }
+
+        if let Some(package) = &root_package {
+            if let Some(binary) = binaries.iter().find(|binary| binary.name == package.name) {
+                return Ok(Some((binary.clone(), false)));
+            }
+        }
+
+        Ok(binaries.first().map(|binary| (binary.clone(), true)))
+    }
+
+    fn get_build_cmd(app: &App, env: &Environment) -> Result<String> {
+        let mut build_cmd = "cargo build --release".to_string();
+
+        // Only select a binary when building everything would be ambiguous
+        let binaries = RustProvider::find_binaries(app)?;
+        if RustProvider::should_select_binary(app, env, &binaries)? {
+            if let Some((binary, _)) = RustProvider::select_binary(app, env, &binaries)? {
+                build_cmd.push_str(&format!(" -p {} --bin {}", binary.package, binary.name));
+            }
+        }
+
+        if let Some(target) = RustProvider::get_target(app, env)? {
+            build_cmd.push_str(&format!(" --target {target}"));
+        }
+
+        Ok(build_cmd)
+    }
+
+    /// Files needed to resolve and build the dependencies of the workspace
+    fn get_manifest_files(app: &App) -> Result<Vec<String>> {
+        let mut files = vec!["Cargo.toml".to_string()];
+        for file in ["Cargo.lock", ".cargo/config.toml", ".cargo/config"] {
+            if app.includes_file(file) {
+                files.push(file.to_string());
+            }
+        }
+
+        for member in RustProvider::get_workspace_members(app)? {
+            files.push(format!("{}/Cargo.toml", member));
+        }
+
+        Ok(files)
+    }
+
+    /// Placeholder sources for the targets of the root package and the workspace members
+    ///
+    /// Only targets the manifests declare or Cargo discovers in the app are stubbed,
+    /// stubbing others would add targets that don't exist.
+    fn get_stub_files(app: &App) -> Result<Vec<StubFile>> {
+        let mut dirs = vec![".".to_string()];
+        dirs.append(&mut RustProvider::get_workspace_members(app)?);
+
+        let mut stubs = Vec::new();
+        for dir in dirs {
+            let manifest = match RustProvider::parse_manifest(app, &dir)? {
+                Some(manifest) => manifest,
+                None => continue,
+            };
+            let package = match manifest.package {
+                Some(package) => package,
+                None => continue,
+            };
+
+            let prefix = if dir == "." {
+                "".to_string()
+            } else {
+                format!("{}/", dir)
+            };
+            let in_package = |path: &str| format!("{}{}", prefix, path);
+
+            let lib_path = manifest.lib.and_then(|lib| lib.path);
+            if lib_path.is_some() || app.includes_file(&in_package("src/lib.rs")) {
+                stubs.push(StubFile::lib(in_package(
+                    &lib_path.unwrap_or_else(|| "src/lib.rs".to_string()),
+                )));
+            }
+            if app.includes_file(&in_package("src/main.rs")) {
+                stubs.push(StubFile::main(in_package("src/main.rs")));
+            }
+
+            // Binaries Cargo discovers in `src/bin`, but not their modules
+            for pattern in ["src/bin/*.rs", "src/bin/*/main.rs"] {
+                let glob = GlobBuilder::new(&in_package(pattern))
+                    .literal_separator(true)
+                    .build()?
+                    .compile_matcher();
+                for file in app.find_files(&in_package(pattern))? {
+                    let path = app.strip_source_path(&file)?;
+                    if glob.is_match(&path) {
+                        stubs.push(StubFile::main(path.to_string_lossy().replace('\\', "/")));
+                    }
+                }
+            }
+
+            let targets = [
+                (manifest.bin, "src/bin"),
+                (manifest.example, "examples"),
+                (manifest.test, "tests"),
+                (manifest.bench, "benches"),
+            ];
+            for (targets, dir) in targets {
+                for target in targets.unwrap_or_default() {
+                    let path = match target.path {
+                        Some(path) => path,
+                        None if dir == "src/bin" && target.name == package.name => {
+                            "src/main.rs".to_string()
+                        }
+                        None => format!("{}/{}.rs", dir, target.name),
+                    };
+                    // Tests and benches may not use the default harness, so all get a `main`
+                    stubs.push(StubFile::main(in_package(&path)));
+                }
+            }
+
+            if app.includes_file(&in_package("build.rs")) {
+                stubs.push(StubFile::main(in_package("build.rs")));
+            }
+        }
+
+        stubs.sort_by(|a, b| a.path.cmp(&b.path));
+        stubs.dedup_by(|a, b| a.path == b.path);
+        Ok(stubs)
+    }
+
+    fn get_stub_cmd(stubs: &[StubFile]) -> String {
+        let mut dirs = stubs
+            .iter()
+            .filter_map(|stub| {
+                let (dir, _) = stub.path.rsplit_once('/')?;
+                Some(dir.to_string())
+            })
+            .collect::<Vec<_>>();
+        dirs.sort();
+        dirs.dedup();
+
+        let join_paths = |is_main: bool| {
+            stubs
+                .iter()
+                .filter(|stub| stub.is_main == is_main)
+                .map(|stub| stub.path.clone())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut cmds = Vec::new();
+        if !dirs.is_empty() {
+            cmds.push(format!("mkdir -p {}", dirs.join(" ")));
+        }
+        let mains = join_paths(true);
+        if !mains.is_empty() {
+            cmds.push(format!("echo 'fn main() {{}}' | tee {} > /dev/null", mains));
+        }
+        let libs = join_paths(false);
+        if !libs.is_empty() {
+            cmds.push(format!("touch {}", libs));
+        }
+
+        cmds.join(" && ")
+    }
+
+    fn binary_names(binaries: &[RustBinary]) -> String {
+        binaries
+            .iter()
+            .map(|binary| binary.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn should_select_binary(app: &App, env: &Environment, binaries: &[RustBinary]) -> Result<bool> {
+        Ok(env.get_config_variable("RUST_BIN").is_some()
+            || RustProvider::parse_cargo_toml(app)?.is_some_and(|toml| toml.workspace.is_some())
+            || binaries.len() > 1)
+    }
+
     fn get_rust_toolchain_file(app: &App) -> Result<Option<String>> {
         if app.includes_file("rust-toolchain") {
             Ok(Some("rust-toolchain".to_string()))
@@ -146,21 +759,56 @@ impl RustProvider {
             ));
         }
 
-        let pkg = match RustProvider::parse_cargo_toml(app)? {
-            Some(toml_file) => {
-                let version = toml_file.package.rust_version;
-
-                version
-                    .map(|version| {
-                        Pkg::new(format!("rust-bin.stable.\"{}\".default", version).as_str())
-                    })
-                    .unwrap_or_else(|| Pkg::new(DEFAULT_RUST_PACKAGE))
-            }
+        let pkg = match RustProvider::get_rust_version(app)? {
+            Some(version) => Pkg::new(format!("rust-bin.stable.\"{}\".default", version).as_str()),
             None => Pkg::new(DEFAULT_RUST_PACKAGE),
         };
 
         Ok(pkg)
     }
+
+    /// The `rust-version` of the root package, falling back to `[workspace.package]`
+    fn get_rust_version(app: &App) -> Result<Option<String>> {
+        let toml_file = match RustProvider::parse_cargo_toml(app)? {
+            Some(toml_file) => toml_file,
+            None => return Ok(None),
+        };
+
+        let package_version = toml_file
+            .package
+            .and_then(|package| package.rust_version)
+            .and_then(|version| version.as_str().map(|version| version.to_string()));
+
+        Ok(package_version.or_else(|| {
+            toml_file
+                .workspace
+                .and_then(|workspace| workspace.package)
+                .and_then(|package| package.rust_version)
+        }))
+    }
+}
+
+/// `dir` joined with a relative `path`, `None` if it leaves the app
+fn join_relative_path(dir: &str, path: &str) -> Option<String> {
+    let mut parts = dir
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+
+    if parts.is_empty() {
+        Some(".".to_string())
+    } else {
+        Some(parts.join("/"))
+    }
 }
 
 #[cfg(test)]
@@ -222,4 +870,97 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_workspace_binaries() -> Result<()> {
+        let app = App::new("./examples/rust-workspace")?;
+        assert_eq!(
+            RustProvider::get_workspace_members(&app)?,
+            vec!["crates/api", "crates/shared", "crates/worker"]
+        );
+        assert_eq!(
+            RustProvider::find_binaries(&app)?,
+            vec![
+                RustBinary {
+                    package: "api".to_string(),
+                    name: "api".to_string()
+                },
+                RustBinary {
+                    package: "worker".to_string(),
+                    name: "worker".to_string()
+                }
+            ]
+        );
+        // The workspace has no root package to pick a binary by
+        let (binary, guessed) = RustProvider::select_binary(
+            &app,
+            &Environment::default(),
+            &RustProvider::find_binaries(&app)?,
+        )?
+        .unwrap();
+        assert_eq!(binary.name, "api");
+        assert!(guessed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stub_files() -> Result<()> {
+        let stubs = RustProvider::get_stub_files(&App::new("./examples/rust-workspace")?)?;
+        assert_eq!(
+            stubs,
+            vec![
+                StubFile::main("crates/api/src/main.rs".to_string()),
+                StubFile::main("crates/shared/benches/parse.rs".to_string()),
+                StubFile::lib("crates/shared/src/lib.rs".to_string()),
+                StubFile::main("crates/worker/src/main.rs".to_string()),
+            ]
+        );
+
+        // Modules of a binary in src/bin are not targets
+        let app = App::new("./examples/rust-path-dependency")?;
+        assert_eq!(
+            RustProvider::get_stub_files(&app)?,
+            vec![
+                StubFile::main("src/bin/tool/main.rs".to_string()),
+                StubFile::main("src/main.rs".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_dependencies() -> Result<()> {
+        // Members depend on each other, which is copied with the manifests
+        assert!(!RustProvider::has_external_path_dependencies(&App::new(
+            "./examples/rust-workspace"
+        )?)?);
+        assert!(RustProvider::has_external_path_dependencies(&App::new(
+            "./examples/rust-path-dependency"
+        )?)?);
+
+        assert_eq!(
+            join_relative_path("crates/api", "../shared"),
+            Some("crates/shared".to_string())
+        );
+        assert_eq!(join_relative_path(".", "./"), Some(".".to_string()));
+        assert_eq!(join_relative_path(".", "../other"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_binary() -> Result<()> {
+        assert!(RustProvider::get_binary(
+            &App::new("./examples/rust-workspace")?,
+            &Environment::new(HashMap::from([(
+                "NIXPACKS_RUST_BIN".to_string(),
+                "nope".to_string()
+            )]))
+        )
+        .is_err());
+
+        Ok(())
+    }
 }
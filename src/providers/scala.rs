@@ -0,0 +1,130 @@
+use super::{java::JavaProvider, Provider};
+use crate::nixpacks::{
+    app::App,
+    environment::Environment,
+    nix::pkg::Pkg,
+    phase::{BuildPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+use regex::Regex;
+
+pub struct ScalaProvider {}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SbtPackaging {
+    /// `sbt-native-packager` staging a start script in `target/universal/stage`
+    NativePackager,
+    /// `sbt-assembly` building a fat jar
+    Assembly,
+}
+
+impl Provider for ScalaProvider {
+    fn name(&self) -> &str {
+        "scala"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["build.sbt"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.scala"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        Ok(app.includes_file("build.sbt"))
+    }
+
+    fn setup(&self, app: &App, env: &Environment) -> Result<Option<SetupPhase>> {
+        let jdk_pkg = JavaProvider::get_jdk_pkg(app, env)?;
+
+        let mut sbt_pkg = Pkg::new("sbt");
+        if jdk_pkg.name != JavaProvider::default_jdk_pkg_name() {
+            sbt_pkg = sbt_pkg.set_override("jre", &jdk_pkg.name);
+        }
+
+        Ok(Some(SetupPhase::new(vec![sbt_pkg, jdk_pkg])))
+    }
+
+    fn build(&self, app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        let cmd = match ScalaProvider::get_packaging(app) {
+            Some(SbtPackaging::NativePackager) => "sbt clean stage",
+            Some(SbtPackaging::Assembly) => "sbt clean assembly",
+            None => "sbt clean compile",
+        };
+        Ok(Some(BuildPhase::new(cmd.to_string())))
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let cmd = match ScalaProvider::get_packaging(app) {
+            Some(SbtPackaging::NativePackager) => format!(
+                "target/universal/stage/bin/{}",
+                ScalaProvider::get_executable_name(app)?
+            ),
+            Some(SbtPackaging::Assembly) => {
+                "java $JAVA_OPTS -jar target/scala-*/*-assembly-*.jar".to_string()
+            }
+            None => "sbt run".to_string(),
+        };
+        Ok(Some(StartPhase::new(cmd)))
+    }
+}
+
+impl ScalaProvider {
+    fn get_packaging(app: &App) -> Option<SbtPackaging> {
+        let plugins = app.read_file("project/plugins.sbt").unwrap_or_default();
+        if plugins.contains("sbt-native-packager") || plugins.contains("com.typesafe.play") {
+            Some(SbtPackaging::NativePackager)
+        } else if plugins.contains("sbt-assembly") {
+            Some(SbtPackaging::Assembly)
+        } else {
+            None
+        }
+    }
+
+    /// Native packager names the start script after the project, e.g. `my-app` for `MyApp`
+    fn get_executable_name(app: &App) -> Result<String> {
+        let re = Regex::new(r#"(?m)^\s*name\s*:=\s*"([^"]+)""#)?;
+        let build_sbt = app.read_file("build.sbt")?;
+
+        let name = match re.captures(&build_sbt) {
+            Some(captures) => captures[1].to_string(),
+            // sbt uses the directory name when the project has no name
+            None => app
+                .source
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+                .to_string(),
+        };
+
+        let mut executable = String::new();
+        for (i, c) in name.chars().enumerate() {
+            if c.is_uppercase() && i > 0 && !executable.ends_with('-') {
+                executable.push('-');
+            }
+            if c.is_whitespace() || c == '_' {
+                executable.push('-');
+            } else {
+                executable.extend(c.to_lowercase());
+            }
+        }
+
+        Ok(executable)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_executable_name() -> Result<()> {
+        assert_eq!(
+            ScalaProvider::get_executable_name(&App::new("./examples/scala-sbt")?)?,
+            "hello-scala".to_string()
+        );
+
+        Ok(())
+    }
+}
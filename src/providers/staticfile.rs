@@ -2,12 +2,14 @@ use super::Provider;
 use crate::nixpacks::{
     app::{App, StaticAssets},
     environment::Environment,
+    images::NGINX_IMAGE,
     nix::pkg::Pkg,
     phase::{BuildPhase, SetupPhase, StartPhase},
 };
 use anyhow::Result;
 use indoc::formatdoc;
 use serde::{Deserialize, Serialize};
+use serde_yaml::Mapping;
 use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Default, Debug)]
@@ -15,7 +17,45 @@ pub struct Staticfile {
     pub root: Option<String>,
     pub directory: Option<String>,
     pub gzip: Option<String>,
+    pub brotli: Option<String>,
     pub status_code: Option<HashMap<u32, String>>,
+    /// `enabled` serves `index.html` for paths without a file, for single page apps
+    pub pushstate: Option<String>,
+    /// Path pattern to response headers
+    pub headers: Option<Mapping>,
+    /// Path pattern to `Cache-Control` value
+    pub cache_control: Option<Mapping>,
+    pub redirects: Option<Vec<Redirect>>,
+}
+
+/// Response headers for the paths matching a pattern like `/assets/*`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderRule {
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+    /// 301 and 302 redirect, 200 rewrites. Defaults to 301
+    pub status: Option<u16>,
+    /// Apply even if a file exists at the path
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Where and how nginx serves a site
+pub struct NginxSite {
+    /// Directory of the site in the app, where `_headers` and `_redirects` are looked up
+    pub source_dir: String,
+    /// Absolute path nginx serves files from
+    pub root: String,
+    /// Path of the `mime.types` shipped with nginx
+    pub mime_types: String,
+    /// Whether nginx is built with the brotli module
+    pub brotli_available: bool,
 }
 
 pub struct StaticfileProvider {}
@@ -25,6 +65,14 @@ impl Provider for StaticfileProvider {
         "staticfile"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["Staticfile", "index.html", "public/", "dist/"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.html"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("Staticfile")
             || app.includes_directory("public")
@@ -33,15 +81,90 @@ impl Provider for StaticfileProvider {
             || app.includes_file("index.html"))
     }
 
-    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
-        let pkg = Pkg::new("nginx");
+    fn setup(&self, app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
+        let staticfile: Staticfile = app.read_yaml("Staticfile").unwrap_or_default();
+        let pkg = if StaticfileProvider::is_brotli_enabled(&staticfile) {
+            Pkg::new("nginx").set_override("modules", "[ nginxModules.brotli ]")
+        } else {
+            Pkg::new("nginx")
+        };
         Ok(Some(SetupPhase::new(vec![pkg])))
     }
 
     fn static_assets(&self, app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        let staticfile: Staticfile = app.read_yaml("Staticfile").unwrap_or_default();
+        let root = StaticfileProvider::get_root(app, env, staticfile.root.unwrap_or_default());
+        let assets = StaticfileProvider::get_nginx_assets(
+            app,
+            env,
+            &NginxSite {
+                source_dir: root.clone(),
+                root: format!("/app/{}", root),
+                mime_types: "/nix/store/*-user-environment/conf/mime.types".to_string(),
+                brotli_available: true,
+            },
+        )?;
+        Ok(Some(assets))
+    }
+
+    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        Ok(Some(BuildPhase::new(
+            "mkdir /etc/nginx/ /var/log/nginx/ /var/cache/nginx/".to_string(),
+        )))
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let mut start_phase = StartPhase::new(StaticfileProvider::get_start_cmd(app));
+        start_phase.add_port(80);
+        Ok(Some(start_phase))
+    }
+}
+
+impl StaticfileProvider {
+    pub fn get_start_cmd(app: &App) -> String {
+        // shell command to edit 0.0.0.0:80 to $PORT, POSIX so it runs in the sh of alpine images
+        let shell_cmd = "[ -z \"${PORT}\" ] && echo \"Environment variable PORT not found. Using PORT 80\" || sed -i \"s/0.0.0.0:80/$PORT/g\"";
+        format!(
+            "{shell_cmd} {conf_location} && nginx -c {conf_location}",
+            shell_cmd = shell_cmd,
+            conf_location = app.asset_path("nginx.conf"),
+        )
+    }
+
+    /// Serve the output of a static site generator with nginx in a slim image
+    pub fn get_static_site_start_phase(app: &App, output_dir: &str) -> StartPhase {
+        let mut start_phase = StartPhase::new(StaticfileProvider::get_start_cmd(app));
+        start_phase.run_in_image(NGINX_IMAGE.to_string());
+        start_phase.add_port(80);
+        // The contents of the output directory are copied to /app
+        start_phase.add_file_dependency(format!("./{}", output_dir));
+        start_phase
+    }
+
+    /// nginx config for the output of a static site generator, see `get_static_site_start_phase`
+    pub fn get_static_site_assets(app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        let assets = StaticfileProvider::get_nginx_assets(
+            app,
+            env,
+            &NginxSite {
+                source_dir: "".to_string(),
+                root: "/app".to_string(),
+                mime_types: "/etc/nginx/mime.types".to_string(),
+                brotli_available: false,
+            },
+        )?;
+        Ok(Some(assets))
+    }
+
+    /// The nginx config for serving a site, along with the files it includes
+    pub fn get_nginx_assets(
+        app: &App,
+        env: &Environment,
+        site: &NginxSite,
+    ) -> Result<StaticAssets> {
         let mut assets = StaticAssets::new();
 
-        let mut mime_types = "include /nix/store/*-user-environment/conf/mime.types;".to_string();
+        let mut mime_types = format!("include {};", site.mime_types);
         if app.includes_file("mime.types") {
             assets.insert("mime.types".to_string(), app.read_file("mime.types")?);
             mime_types = "include\tmime.types;".to_string();
@@ -57,19 +180,58 @@ impl Provider for StaticfileProvider {
         }
 
         let staticfile: Staticfile = app.read_yaml("Staticfile").unwrap_or_default();
-        let root = StaticfileProvider::get_root(
-            app,
-            env,
-            staticfile.root.unwrap_or_else(|| "".to_string()),
-        );
-        let gzip = staticfile.gzip.unwrap_or_else(|| "on".to_string());
-        let directory = staticfile.directory.unwrap_or_else(|| "off".to_string());
-        let status_code = staticfile.status_code.unwrap_or_default();
+        let root = &site.root;
+        let gzip = staticfile.gzip.clone().unwrap_or_else(|| "on".to_string());
+        let brotli = if site.brotli_available && StaticfileProvider::is_brotli_enabled(&staticfile)
+        {
+            "brotli on;\n\t\tbrotli_static on;\n\t\tbrotli_types text/plain text/css application/javascript application/json image/svg+xml;".to_string()
+        } else {
+            "".to_string()
+        };
+        let directory = staticfile
+            .directory
+            .clone()
+            .unwrap_or_else(|| "off".to_string());
+        let status_code = staticfile.status_code.clone().unwrap_or_default();
         let mut error_page = "".to_string();
         for (key, value) in status_code {
             error_page += &format!("\terror_page {} {};\n", key, value);
         }
 
+        let header_rules =
+            StaticfileProvider::get_header_rules(app, &staticfile, &site.source_dir)?;
+        let (header_maps, add_headers) = StaticfileProvider::get_header_config(&header_rules);
+
+        let redirects = StaticfileProvider::get_redirects(app, &staticfile, &site.source_dir)?;
+        let forced_rewrites = redirects
+            .iter()
+            .filter(|redirect| redirect.force)
+            .filter_map(redirect_to_rewrite)
+            .collect::<Vec<_>>();
+        let mut fallback_rewrites = redirects
+            .iter()
+            .filter(|redirect| !redirect.force)
+            .filter_map(redirect_to_rewrite)
+            .collect::<Vec<_>>();
+
+        // Paths without a file go through the rewrites, then the single page app's index
+        let is_spa = StaticfileProvider::is_spa(env, &staticfile);
+        if is_spa {
+            fallback_rewrites.push("rewrite ^ /index.html last;".to_string());
+        }
+        let (try_files, fallback_location) = if fallback_rewrites.is_empty() {
+            ("".to_string(), "".to_string())
+        } else {
+            fallback_rewrites.push("return 404;".to_string());
+            (
+                "try_files $uri $uri/ @fallback;".to_string(),
+                format!(
+                    "location @fallback {{\n\t\t\t{}\n\t\t}}",
+                    fallback_rewrites.join("\n\t\t\t")
+                ),
+            )
+        };
+
         let nginx_conf = formatdoc! {"
         daemon off;
         error_log /dev/stdout info;
@@ -85,47 +247,158 @@ impl Provider for StaticfileProvider {
             sendfile       on;
             keepalive_timeout  60;
             types_hash_max_size 4096;
+            {header_maps}
             server {{
                 listen    0.0.0.0:80;
                 gzip  	  {gzip};
-                root	  /app/{root};
+                {brotli}
+                root	  {root};
+                {forced_rewrites}
+                {add_headers}
                 location / {{
                     {auth_basic}
                     autoindex {directory};
+                    {try_files}
                 }}
+                {fallback_location}
         {error_page}
             }}
         }}
         ", 
         mime_types = mime_types,
+        header_maps = header_maps,
         gzip = gzip,
+        brotli = brotli,
         root = root,
+        forced_rewrites = forced_rewrites.join("\n\t\t"),
+        add_headers = add_headers,
         auth_basic = auth_basic,
         directory = directory,
+        try_files = try_files,
+        fallback_location = fallback_location,
         error_page = error_page
         };
         assets.insert("nginx.conf".to_string(), nginx_conf);
-        Ok(Some(assets))
+        Ok(assets)
     }
 
-    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
-        Ok(Some(BuildPhase::new(
-            "mkdir /etc/nginx/ /var/log/nginx/ /var/cache/nginx/".to_string(),
-        )))
+    fn is_spa(env: &Environment, staticfile: &Staticfile) -> bool {
+        env.is_config_variable_truthy("SPA") || staticfile.pushstate.as_deref() == Some("enabled")
     }
 
-    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
-        // shell command to edit 0.0.0.0:80 to $PORT
-        let shell_cmd = "[[ -z \"${PORT}\" ]] && echo \"Environment variable PORT not found. Using PORT 80\" || sed -i \"s/0.0.0.0:80/$PORT/g\"";
-        Ok(Some(StartPhase::new(format!(
-            "{shell_cmd} {conf_location} && nginx -c {conf_location}",
-            shell_cmd = shell_cmd,
-            conf_location = app.asset_path("nginx.conf"),
-        ))))
+    fn is_brotli_enabled(staticfile: &Staticfile) -> bool {
+        matches!(
+            staticfile.brotli.as_deref(),
+            Some("on" | "true" | "enabled")
+        )
+    }
+
+    /// Reads a Netlify style file from the site root, falling back to the app root
+    fn read_site_file(app: &App, root: &str, name: &str) -> Result<Option<String>> {
+        let site_file = format!("{}/{}", root.trim_end_matches('/'), name);
+        if !root.is_empty() && app.includes_file(&site_file) {
+            Ok(Some(app.read_file(&site_file)?))
+        } else if app.includes_file(name) {
+            Ok(Some(app.read_file(name)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Header rules from `_headers`, then the `headers` and `cache_control` of the `Staticfile`
+    pub fn get_header_rules(
+        app: &App,
+        staticfile: &Staticfile,
+        root: &str,
+    ) -> Result<Vec<HeaderRule>> {
+        let mut rules = match StaticfileProvider::read_site_file(app, root, "_headers")? {
+            Some(contents) => parse_headers_file(&contents),
+            None => Vec::new(),
+        };
+
+        for (path, headers) in staticfile.headers.clone().unwrap_or_default() {
+            if let (Some(path), Some(headers)) = (path.as_str(), headers.as_mapping()) {
+                rules.push(HeaderRule {
+                    path: path.to_string(),
+                    headers: headers
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            Some((name.as_str()?.to_string(), yaml_to_string(value)?))
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        for (path, value) in staticfile.cache_control.clone().unwrap_or_default() {
+            if let (Some(path), Some(value)) = (path.as_str(), yaml_to_string(&value)) {
+                rules.push(HeaderRule {
+                    path: path.to_string(),
+                    headers: vec![("Cache-Control".to_string(), value)],
+                });
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Redirects from `_redirects`, then the `redirects` of the `Staticfile`
+    pub fn get_redirects(app: &App, staticfile: &Staticfile, root: &str) -> Result<Vec<Redirect>> {
+        let mut redirects = match StaticfileProvider::read_site_file(app, root, "_redirects")? {
+            Some(contents) => parse_redirects_file(&contents),
+            None => Vec::new(),
+        };
+        redirects.append(&mut staticfile.redirects.clone().unwrap_or_default());
+        Ok(redirects)
+    }
+
+    /// nginx doesn't combine `add_header` from several locations, so each header
+    /// gets a variable mapped from the request path. Empty values are not sent.
+    /// `map` uses the first regex that matches, so more specific paths go first.
+    fn get_header_config(rules: &[HeaderRule]) -> (String, String) {
+        let mut names: Vec<String> = Vec::new();
+        for rule in rules {
+            for (name, _) in &rule.headers {
+                if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                    names.push(name.clone());
+                }
+            }
+        }
+
+        let mut by_specificity = rules.iter().collect::<Vec<_>>();
+        by_specificity.sort_by_key(|rule| std::cmp::Reverse(path_specificity(&rule.path)));
+
+        let mut maps = Vec::new();
+        let mut add_headers = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            let variable = format!("$nixpacks_header_{}", i);
+            let entries = by_specificity
+                .iter()
+                .flat_map(|rule| {
+                    rule.headers
+                        .iter()
+                        .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+                        .map(|(_, value)| {
+                            format!(
+                                "\t\t\"~{}\" \"{}\";",
+                                path_to_regex(&rule.path),
+                                value.replace('"', "\\\"")
+                            )
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            maps.push(format!(
+                "map $uri {} {{\n\t\tdefault \"\";\n{}\n\t}}",
+                variable,
+                entries.join("\n")
+            ));
+            add_headers.push(format!("add_header {} {};", name, variable));
+        }
+
+        (maps.join("\n\t"), add_headers.join("\n\t\t"))
     }
-}
 
-impl StaticfileProvider {
     pub fn get_root(app: &App, env: &Environment, staticfile_root: String) -> String {
         let mut root = "";
         if let Some(staticfile_root) = env.get_config_variable("STATICFILE_ROOT") {
@@ -142,3 +415,166 @@ impl StaticfileProvider {
         root.to_string()
     }
 }
+
+/// Parses a Netlify `_headers` file
+///
+/// ```text
+/// /assets/*
+///   Cache-Control: public, max-age=31536000
+/// ```
+pub fn parse_headers_file(contents: &str) -> Vec<HeaderRule> {
+    let mut rules: Vec<HeaderRule> = Vec::new();
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if !line.starts_with(char::is_whitespace) {
+            rules.push(HeaderRule {
+                path: trimmed.to_string(),
+                headers: Vec::new(),
+            });
+        } else if let (Some(rule), Some((name, value))) =
+            (rules.last_mut(), trimmed.split_once(':'))
+        {
+            rule.headers
+                .push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    rules
+}
+
+/// Parses a Netlify `_redirects` file with lines like `/old/* /new/:splat 301!`
+pub fn parse_redirects_file(contents: &str) -> Vec<Redirect> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            let (from, to) = (parts.first()?, parts.get(1)?);
+            let status = parts.get(2).unwrap_or(&"301");
+            let force = status.ends_with('!');
+
+            Some(Redirect {
+                from: from.to_string(),
+                to: to.to_string(),
+                status: status.trim_end_matches('!').parse().ok(),
+                force,
+            })
+        })
+        .collect()
+}
+
+/// `/assets/*` -> `^/assets/(.*)$`
+/// Exact paths before wildcards, then the longest literal part first
+fn path_specificity(path: &str) -> (bool, usize) {
+    (!path.contains('*'), path.replace('*', "").len())
+}
+
+fn path_to_regex(path: &str) -> String {
+    format!("^{}$", regex::escape(path).replace("\\*", "(.*)"))
+}
+
+fn redirect_to_rewrite(redirect: &Redirect) -> Option<String> {
+    let flag = match redirect.status.unwrap_or(301) {
+        301 => "permanent",
+        302 => "redirect",
+        200 if !redirect.to.starts_with("http") => "last",
+        status => {
+            eprintln!(
+                "Warning: Skipping {} -> {}, status {} is not supported",
+                redirect.from, redirect.to, status
+            );
+            return None;
+        }
+    };
+
+    Some(format!(
+        "rewrite \"{}\" \"{}\" {};",
+        path_to_regex(&redirect.from),
+        redirect.to.replace(":splat", "$1"),
+        flag
+    ))
+}
+
+fn yaml_to_string(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_redirects_file() {
+        assert_eq!(
+            parse_redirects_file("# comment\n/blog/*  /posts/:splat\n/*  /index.html  200!\n"),
+            vec![
+                Redirect {
+                    from: "/blog/*".to_string(),
+                    to: "/posts/:splat".to_string(),
+                    status: Some(301),
+                    force: false,
+                },
+                Redirect {
+                    from: "/*".to_string(),
+                    to: "/index.html".to_string(),
+                    status: Some(200),
+                    force: true,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_headers_file() {
+        assert_eq!(
+            parse_headers_file(
+                "/*\n  X-Frame-Options: DENY\n/assets/*\n  Cache-Control: max-age=60\n"
+            ),
+            vec![
+                HeaderRule {
+                    path: "/*".to_string(),
+                    headers: vec![("X-Frame-Options".to_string(), "DENY".to_string())],
+                },
+                HeaderRule {
+                    path: "/assets/*".to_string(),
+                    headers: vec![("Cache-Control".to_string(), "max-age=60".to_string())],
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_rules_ordered_by_specificity() {
+        let rules = parse_headers_file(
+            "/*\n  Cache-Control: no-cache\n/assets/*\n  Cache-Control: max-age=60\n/assets/app.js\n  Cache-Control: max-age=0\n",
+        );
+        let (maps, _) = StaticfileProvider::get_header_config(&rules);
+        let exact = maps.find("^/assets/app\\.js$").unwrap();
+        let assets = maps.find("^/assets/(.*)$").unwrap();
+        let all = maps.find("^/(.*)$").unwrap();
+        assert!(exact < assets && assets < all);
+    }
+
+    #[test]
+    fn test_redirect_to_rewrite() {
+        assert_eq!(
+            redirect_to_rewrite(&Redirect {
+                from: "/blog/*".to_string(),
+                to: "/posts/:splat".to_string(),
+                status: None,
+                force: false,
+            }),
+            Some("rewrite \"^/blog/(.*)$\" \"/posts/$1\" permanent;".to_string())
+        );
+    }
+}
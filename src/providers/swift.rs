@@ -32,6 +32,14 @@ impl Provider for SwiftProvider {
         "swift"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["Package.swift"]
+    }
+
+    fn source_files(&self) -> &[&str] {
+        &["**/*.swift"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.includes_file("Package.swift"))
     }
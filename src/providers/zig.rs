@@ -20,6 +20,10 @@ impl Provider for ZigProvider {
         "zig"
     }
 
+    fn detect_files(&self) -> &[&str] {
+        &["*.zig", "gyro.zzz"]
+    }
+
     fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
         Ok(app.has_match("*.zig") || app.has_match("**/*.zig") || app.has_match("gyro.zzz"))
     }
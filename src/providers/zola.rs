@@ -0,0 +1,98 @@
+use super::{staticfile::StaticfileProvider, Provider};
+use crate::nixpacks::{
+    app::{App, StaticAssets},
+    environment::Environment,
+    nix::pkg::Pkg,
+    phase::{BuildPhase, SetupPhase, StartPhase},
+};
+use anyhow::Result;
+use regex::Regex;
+
+const DEFAULT_OUTPUT_DIR: &str = "public";
+
+pub struct ZolaProvider {}
+
+impl Provider for ZolaProvider {
+    fn name(&self) -> &str {
+        "zola"
+    }
+
+    fn detect_files(&self) -> &[&str] {
+        &["config.toml with templates/"]
+    }
+
+    fn detect(&self, app: &App, _env: &Environment) -> Result<bool> {
+        Ok(ZolaProvider::is_zola(app))
+    }
+
+    fn setup(&self, _app: &App, _env: &Environment) -> Result<Option<SetupPhase>> {
+        Ok(Some(SetupPhase::new(vec![Pkg::new("zola")])))
+    }
+
+    fn build(&self, _app: &App, _env: &Environment) -> Result<Option<BuildPhase>> {
+        Ok(Some(BuildPhase::new("zola build".to_string())))
+    }
+
+    fn static_assets(&self, app: &App, env: &Environment) -> Result<Option<StaticAssets>> {
+        StaticfileProvider::get_static_site_assets(app, env)
+    }
+
+    fn start(&self, app: &App, _env: &Environment) -> Result<Option<StartPhase>> {
+        let output_dir = ZolaProvider::get_output_dir(app)?;
+        Ok(Some(StaticfileProvider::get_static_site_start_phase(
+            app,
+            &output_dir,
+        )))
+    }
+}
+
+impl ZolaProvider {
+    /// Zola sites have a `config.toml` with a `base_url` key (Hugo uses `baseURL`) next to
+    /// their `templates` or `content`
+    pub fn is_zola(app: &App) -> bool {
+        let config = match app.read_file("config.toml") {
+            Ok(config) => config,
+            Err(_) => return false,
+        };
+
+        (app.includes_directory("templates") || app.includes_directory("content"))
+            && Regex::new(r"(?m)^base_url\s*=")
+                .map(|re| re.is_match(&config))
+                .unwrap_or_default()
+    }
+
+    /// The `output_dir` of `config.toml`, `public` by default
+    fn get_output_dir(app: &App) -> Result<String> {
+        let config = app.read_file("config.toml")?;
+        let re = Regex::new(r#"(?m)^\s*output_dir\s*=\s*["']([^"']+)["']"#)?;
+        Ok(re
+            .captures(&config)
+            .map(|captures| captures[1].trim_end_matches('/').to_string())
+            .unwrap_or_else(|| DEFAULT_OUTPUT_DIR.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_is_zola() -> Result<()> {
+        assert!(ZolaProvider::is_zola(&App::new("./examples/zola")?));
+        assert!(!ZolaProvider::is_zola(&App::new("./examples/hugo")?));
+
+        // `base_url` only in a nested table of some other app's config
+        let dir = tempdir::TempDir::new("nixpacks-zola")?;
+        fs::create_dir(dir.path().join("content"))?;
+        fs::write(
+            dir.path().join("config.toml"),
+            "[api]\n  base_url = \"https://api.example.com\"\n",
+        )?;
+        assert!(!ZolaProvider::is_zola(&App::new(
+            dir.path().to_str().unwrap()
+        )?));
+
+        Ok(())
+    }
+}
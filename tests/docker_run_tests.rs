@@ -3,8 +3,9 @@ use nixpacks::{
     create_docker_image,
     nixpacks::{
         builder::docker::DockerBuilderOptions, environment::EnvironmentVariables, nix::pkg::Pkg,
-        plan::generator::GeneratePlanOptions,
+        plan::generator::GeneratePlanOptions, runner::RunOptions,
     },
+    run_app,
 };
 use std::io::{BufRead, BufReader};
 use std::process::{Command, Stdio};
@@ -228,6 +229,27 @@ fn test_node() {
     assert!(run_image(name, None).contains("Hello from Node"));
 }
 
+#[test]
+fn test_run_app() {
+    let name = Uuid::new_v4().to_string();
+    let status = run_app(
+        "./examples/node",
+        Vec::new(),
+        &GeneratePlanOptions::default(),
+        &DockerBuilderOptions {
+            name: Some(name.clone()),
+            quiet: true,
+            ..Default::default()
+        },
+        &RunOptions::default(),
+    )
+    .unwrap();
+    assert!(status.success());
+
+    // The container is removed when the app exits
+    assert!(get_container_ids_from_image(name).trim().is_empty());
+}
+
 #[test]
 fn test_node_custom_version() {
     let name = simple_build("./examples/node-custom-version");
@@ -1,9 +1,13 @@
 use anyhow::Result;
 use nixpacks::{
-    generate_build_plan,
+    diff_build_plans, generate_build_plan,
     nixpacks::{
         nix::pkg::Pkg,
-        plan::{generator::GeneratePlanOptions, BuildPlan},
+        plan::{
+            diff::{Change, PlanChange},
+            generator::GeneratePlanOptions,
+            BuildPlan,
+        },
     },
 };
 use std::env::consts::ARCH;
@@ -199,6 +203,52 @@ fn test_go_custom_version() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_go_cmd() -> Result<()> {
+    let plan = simple_gen_plan("./examples/go-cmd");
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["go build -o out ./cmd/api".to_string()])
+    );
+    assert_eq!(plan.start.unwrap().cmd, Some("./out".to_string()));
+
+    Ok(())
+}
+
+#[test]
+fn test_go_cmd_custom_binary() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/go-cmd",
+        vec!["NIXPACKS_GO_BIN=worker", "NIXPACKS_GO_LDFLAGS=-s -w"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec![
+            "go build -ldflags=\"-s -w\" -o out ./cmd/worker".to_string()
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_go_workspace() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/go-workspace",
+        vec!["NIXPACKS_GO_BIN=worker"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("go_1_18")]);
+    assert!(plan.install.unwrap().cmds.is_none());
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["go build -o out ./worker".to_string()])
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_deno() -> Result<()> {
     let plan = simple_gen_plan("./examples/deno");
@@ -217,20 +267,41 @@ fn test_deno() -> Result<()> {
 #[test]
 fn test_csharp_api() -> Result<()> {
     let plan = simple_gen_plan("./examples/csharp-api");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("dotnet-sdk_6")]);
     assert_eq!(
         plan.install.unwrap().cmds,
-        Some(vec!["dotnet restore".to_string()])
+        Some(vec!["dotnet restore csharp-api.csproj".to_string()])
     );
     assert_eq!(
         plan.build.unwrap().cmds,
         Some(vec![
-            "dotnet publish --no-restore -c Release -o out".to_string()
+            "dotnet publish csharp-api.csproj --no-restore -c Release -o out".to_string()
         ])
     );
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("dotnet csharp-api.dll".to_string()));
     assert_eq!(
-        plan.start.unwrap().cmd,
-        Some("./out/csharp-api".to_string())
+        start.run_image,
+        Some("mcr.microsoft.com/dotnet/aspnet:6.0".to_string())
+    );
+    assert_eq!(start.only_include_files, Some(vec!["./out".to_string()]));
+    // The Nix .NET is not in the runtime image
+    assert!(!plan.variables.unwrap().contains_key("DOTNET_ROOT"));
+
+    let plan = generate_build_plan(
+        "./examples/csharp-api",
+        vec!["NIXPACKS_RUN_IMAGE=false"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(
+        plan.variables.unwrap().get("DOTNET_ROOT"),
+        Some(&"/nix/var/nix/profiles/default/".to_string())
     );
+    // The app runs from the build stage, without copying the output
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("dotnet out/csharp-api.dll".to_string()));
+    assert_eq!(start.run_image, None);
+    assert_eq!(start.only_include_files, None);
 
     Ok(())
 }
@@ -238,20 +309,24 @@ fn test_csharp_api() -> Result<()> {
 #[test]
 fn test_fsharp_api() -> Result<()> {
     let plan = simple_gen_plan("./examples/fsharp-api");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("dotnet-sdk_6")]);
     assert_eq!(
         plan.install.unwrap().cmds,
-        Some(vec!["dotnet restore".to_string()])
+        Some(vec!["dotnet restore fsharp-api.fsproj".to_string()])
     );
     assert_eq!(
         plan.build.unwrap().cmds,
         Some(vec![
-            "dotnet publish --no-restore -c Release -o out".to_string()
+            "dotnet publish fsharp-api.fsproj --no-restore -c Release -o out".to_string()
         ])
     );
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("dotnet fsharp-api.dll".to_string()));
     assert_eq!(
-        plan.start.unwrap().cmd,
-        Some("./out/fsharp-api".to_string())
+        start.run_image,
+        Some("mcr.microsoft.com/dotnet/aspnet:6.0".to_string())
     );
+    assert_eq!(start.only_include_files, Some(vec!["./out".to_string()]));
 
     Ok(())
 }
@@ -259,19 +334,64 @@ fn test_fsharp_api() -> Result<()> {
 #[test]
 fn test_csharp_cli() -> Result<()> {
     let plan = simple_gen_plan("./examples/csharp-cli");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("dotnet-sdk_6")]);
     assert_eq!(
         plan.install.unwrap().cmds,
-        Some(vec!["dotnet restore".to_string()])
+        Some(vec!["dotnet restore csharp-cli.csproj".to_string()])
     );
     assert_eq!(
         plan.build.unwrap().cmds,
         Some(vec![
-            "dotnet publish --no-restore -c Release -o out".to_string()
+            "dotnet publish csharp-cli.csproj --no-restore -c Release -o out".to_string()
         ])
     );
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("dotnet csharp-cli.dll".to_string()));
     assert_eq!(
-        plan.start.unwrap().cmd,
-        Some("./out/csharp-cli".to_string())
+        start.run_image,
+        Some("mcr.microsoft.com/dotnet/runtime:6.0".to_string())
+    );
+    assert_eq!(start.only_include_files, Some(vec!["./out".to_string()]));
+
+    Ok(())
+}
+
+#[test]
+fn test_csharp_solution() -> Result<()> {
+    let plan = simple_gen_plan("./examples/csharp-solution");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("dotnet-sdk_7")]);
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec![
+            "dotnet publish src/Api/Api.csproj --no-restore -c Release -o out".to_string()
+        ])
+    );
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("dotnet Api.dll".to_string()));
+    assert_eq!(
+        start.run_image,
+        Some("mcr.microsoft.com/dotnet/aspnet:7.0".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_csharp_solution_custom_project() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/csharp-solution",
+        vec!["NIXPACKS_CSPROJ=src/Worker/Worker.csproj"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(
+        plan.install.unwrap().cmds,
+        Some(vec!["dotnet restore src/Worker/Worker.csproj".to_string()])
+    );
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("dotnet BackgroundWorker.dll".to_string()));
+    assert_eq!(
+        start.run_image,
+        Some("mcr.microsoft.com/dotnet/runtime:7.0".to_string())
     );
 
     Ok(())
@@ -308,6 +428,7 @@ fn test_pin_archive() -> Result<()> {
         "./examples/shell-hello",
         Vec::new(),
         &GeneratePlanOptions {
+            custom_start_cmd: Some("./start.sh".to_string()),
             pin_pkgs: true,
             ..Default::default()
         },
@@ -321,7 +442,7 @@ fn test_pin_archive() -> Result<()> {
 fn test_custom_rust_version() -> Result<()> {
     let plan = simple_gen_plan("./examples/rust-custom-version");
     let cmd = format!("cargo build --release --target {}-unknown-linux-musl", ARCH);
-    assert_eq!(plan.build.unwrap().cmds, Some(vec![cmd]));
+    assert_eq!(plan.build.unwrap().cmds.unwrap().last(), Some(&cmd));
     assert_eq!(
         plan.setup
             .unwrap()
@@ -339,7 +460,20 @@ fn test_custom_rust_version() -> Result<()> {
 fn test_rust_rocket() -> Result<()> {
     let plan = simple_gen_plan("./examples/rust-rocket");
     let cmd = format!("cargo build --release --target {}-unknown-linux-musl", ARCH);
-    assert_eq!(plan.build.unwrap().cmds, Some(vec![cmd]));
+    let install = plan.install.unwrap();
+    assert_eq!(
+        install.only_include_files,
+        Some(vec!["Cargo.toml".to_string()])
+    );
+    assert_eq!(
+        install.cmds,
+        Some(vec![
+            "mkdir -p src && echo 'fn main() {}' | tee src/main.rs > /dev/null".to_string(),
+            cmd.clone(),
+            "rm src/main.rs".to_string()
+        ])
+    );
+    assert_eq!(plan.build.unwrap().cmds.unwrap().last(), Some(&cmd));
     assert!(plan.start.clone().unwrap().cmd.is_some());
     assert_eq!(
         plan.start.clone().unwrap().cmd.unwrap(),
@@ -358,8 +492,12 @@ fn test_rust_rocket_no_musl() -> Result<()> {
         &GeneratePlanOptions::default(),
     )?;
     assert_eq!(
-        plan.build.unwrap().cmds,
-        Some(vec!["cargo build --release".to_string()])
+        plan.install.unwrap().cmds.unwrap()[1],
+        "cargo build --release".to_string()
+    );
+    assert_eq!(
+        plan.build.unwrap().cmds.unwrap().last(),
+        Some(&"cargo build --release".to_string())
     );
     assert!(plan
         .start
@@ -373,6 +511,112 @@ fn test_rust_rocket_no_musl() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_rust_openssl() -> Result<()> {
+    let plan = simple_gen_plan("./examples/rust-openssl");
+    let setup = plan.setup.unwrap();
+    let pkg_names = setup
+        .pkgs
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>();
+    assert!(pkg_names.contains(&"openssl"));
+    assert!(pkg_names.contains(&"pkg-config"));
+    assert!(pkg_names.contains(&"protobuf"));
+    assert_eq!(setup.libraries, Some(vec!["openssl".to_string()]));
+
+    // openssl-sys can't be linked statically, so musl is turned off
+    assert!(setup.apt_pkgs.unwrap_or_default().is_empty());
+    assert_eq!(
+        plan.build.unwrap().cmds.unwrap().last(),
+        Some(&"cargo build --release".to_string())
+    );
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("./target/release/rust-openssl".to_string()));
+    assert!(start.run_image.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_rust_path_dependency() -> Result<()> {
+    let plan = simple_gen_plan("./examples/rust-path-dependency");
+    // vendor/helpers is not in a workspace, so the dependencies are built with the app
+    assert!(plan.install.unwrap().cmds.is_none());
+    assert_eq!(
+        plan.build.unwrap().cmds.unwrap().last(),
+        Some(&format!(
+            "cargo build --release -p app --bin app --target {}-unknown-linux-musl",
+            ARCH
+        ))
+    );
+    assert_eq!(plan.start.unwrap().cmd, Some("./app".to_string()));
+
+    Ok(())
+}
+
+#[test]
+fn test_rust_workspace() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/rust-workspace",
+        vec!["NIXPACKS_RUST_BIN=worker"],
+        &GeneratePlanOptions::default(),
+    )?;
+    let cmd = format!(
+        "cargo build --release -p worker --bin worker --target {}-unknown-linux-musl",
+        ARCH
+    );
+    let install = plan.install.unwrap();
+    assert_eq!(
+        install.only_include_files,
+        Some(vec![
+            "Cargo.toml".to_string(),
+            "Cargo.lock".to_string(),
+            "crates/api/Cargo.toml".to_string(),
+            "crates/shared/Cargo.toml".to_string(),
+            "crates/worker/Cargo.toml".to_string()
+        ])
+    );
+    assert_eq!(install.cmds.unwrap()[1], cmd);
+    assert_eq!(plan.build.unwrap().cmds.unwrap().last(), Some(&cmd));
+    let start = plan.start.unwrap();
+    assert_eq!(start.cmd, Some("./worker".to_string()));
+    assert_eq!(
+        start.only_include_files,
+        Some(vec![format!(
+            "./target/{}-unknown-linux-musl/release/worker",
+            ARCH
+        )])
+    );
+    assert!(plan
+        .setup
+        .unwrap()
+        .pkgs
+        .iter()
+        .any(|p| p.name.contains("1.64.0")));
+
+    Ok(())
+}
+
+#[test]
+fn test_rust_multiple_bins_default_run() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/rust-multiple-bins",
+        vec!["NIXPACKS_NO_MUSL=1"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(
+        plan.build.unwrap().cmds.unwrap().last(),
+        Some(&"cargo build --release -p multiple-bins --bin server".to_string())
+    );
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some("./target/release/server".to_string())
+    );
+
+    Ok(())
+}
+
 #[test]
 pub fn test_python() -> Result<()> {
     let plan = simple_gen_plan("./examples/python");
@@ -517,8 +761,128 @@ fn test_staticfile() -> Result<()> {
     );
     assert_eq!(
         plan.start.unwrap().cmd,
-        Some("[[ -z \"${PORT}\" ]] && echo \"Environment variable PORT not found. Using PORT 80\" || sed -i \"s/0.0.0.0:80/$PORT/g\" /assets/nginx.conf && nginx -c /assets/nginx.conf".to_string())
+        Some("[ -z \"${PORT}\" ] && echo \"Environment variable PORT not found. Using PORT 80\" || sed -i \"s/0.0.0.0:80/$PORT/g\" /assets/nginx.conf && nginx -c /assets/nginx.conf".to_string())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_staticfile_spa() -> Result<()> {
+    let plan = simple_gen_plan("./examples/staticfile-spa");
+    let nginx_conf = plan
+        .static_assets
+        .unwrap()
+        .get("nginx.conf")
+        .unwrap()
+        .clone();
+
+    assert!(nginx_conf.contains("root\t  /app/dist;"));
+    assert!(nginx_conf.contains("try_files $uri $uri/ @fallback;"));
+    assert!(nginx_conf.contains("rewrite ^ /index.html last;"));
+    assert!(nginx_conf.contains("rewrite \"^/blog/(.*)$\" \"/posts/$1\" permanent;"));
+    assert!(nginx_conf.contains("rewrite \"^/docs$\" \"https://docs.example.com\" redirect;"));
+    assert!(nginx_conf.contains("\"~^/assets/(.*)$\" \"public, max-age=31536000, immutable\";"));
+    assert!(nginx_conf.contains("add_header X-Content-Type-Options $nixpacks_header_0;"));
+
+    Ok(())
+}
+
+#[test]
+fn test_hugo() -> Result<()> {
+    let plan = simple_gen_plan("./examples/hugo");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("hugo")]);
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["hugo --minify".to_string()])
+    );
+    let start = plan.start.unwrap();
+    assert_eq!(start.run_image, Some("nginx:stable-alpine".to_string()));
+    assert_eq!(start.only_include_files, Some(vec!["./public".to_string()]));
+
+    let nginx_conf = plan
+        .static_assets
+        .unwrap()
+        .get("nginx.conf")
+        .unwrap()
+        .clone();
+    assert!(nginx_conf.contains("root\t  /app;"));
+    assert!(nginx_conf.contains("include /etc/nginx/mime.types;"));
+
+    Ok(())
+}
+
+#[test]
+fn test_zola() -> Result<()> {
+    let plan = simple_gen_plan("./examples/zola");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("zola")]);
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["zola build".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().only_include_files,
+        Some(vec!["./public".to_string()])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_jekyll() -> Result<()> {
+    let plan = simple_gen_plan("./examples/jekyll");
+    assert_eq!(
+        plan.install.unwrap().cmds,
+        Some(vec!["bundle install".to_string()])
+    );
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["bundle exec jekyll build".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().only_include_files,
+        Some(vec!["./_site".to_string()])
+    );
+    assert_eq!(
+        plan.variables.unwrap().get("JEKYLL_ENV"),
+        Some(&"production".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_mkdocs() -> Result<()> {
+    let plan = simple_gen_plan("./examples/mkdocs");
+    assert_eq!(
+        plan.install.unwrap().cmds,
+        Some(vec![
+            "python -m venv /opt/venv && . /opt/venv/bin/activate && pip install mkdocs mkdocs-material"
+                .to_string()
+        ])
+    );
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["mkdocs build".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().only_include_files,
+        Some(vec!["./site".to_string()])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_docusaurus() -> Result<()> {
+    let plan = simple_gen_plan("./examples/docusaurus");
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["npm run build".to_string()])
     );
+    let start = plan.start.unwrap();
+    assert_eq!(start.run_image, Some("nginx:stable-alpine".to_string()));
+    assert_eq!(start.only_include_files, Some(vec!["./build".to_string()]));
+
     Ok(())
 }
 
@@ -565,6 +929,42 @@ fn test_php_laravel() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_php_symfony() -> Result<()> {
+    let plan = simple_gen_plan("./examples/php-symfony");
+    assert_eq!(
+        plan.setup.unwrap().pkgs[0..2],
+        vec![Pkg::new("php81"), Pkg::new("perl")]
+    );
+    let variables = plan.variables.unwrap();
+    assert_eq!(
+        variables.get("NIXPACKS_PHP_ROOT_DIR"),
+        Some(&"/app/public".to_string())
+    );
+    assert_eq!(
+        variables.get("NIXPACKS_PHP_FALLBACK_PATH"),
+        Some(&"/index.php".to_string())
+    );
+    let php_fpm_conf = plan.static_assets.unwrap()["php-fpm.conf"].clone();
+    assert!(php_fpm_conf
+        .ends_with("php_value[upload_max_filesize] = 20M\nphp_value[memory_limit] = 512M\nphp_flag[display_errors] = On"));
+
+    // nginx serves the app root in the image
+    let plan = generate_build_plan(
+        "./examples",
+        Vec::new(),
+        &GeneratePlanOptions {
+            root: Some("php-symfony".to_string()),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(
+        plan.variables.unwrap().get("NIXPACKS_PHP_ROOT_DIR"),
+        Some(&"/app/php-symfony/public".to_string())
+    );
+    Ok(())
+}
+
 #[test]
 fn test_dart() -> Result<()> {
     let plan = simple_gen_plan("./examples/dart");
@@ -645,6 +1045,51 @@ fn test_java_maven_wrapper() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_java_gradle() -> Result<()> {
+    let plan = simple_gen_plan("./examples/java-gradle");
+    assert_eq!(
+        plan.setup.unwrap().pkgs,
+        vec![
+            Pkg::new("gradle").set_override("java", "jdk11"),
+            Pkg::new("jdk11")
+        ]
+    );
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["gradle clean build -x check".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some(
+            "java -Dserver.port=$PORT $JAVA_OPTS -jar $(ls -1 build/libs/*jar | grep -v plain)"
+                .to_string()
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn test_java_gradle_kotlin() -> Result<()> {
+    let plan = simple_gen_plan("./examples/java-gradle-kotlin");
+    assert_eq!(
+        plan.setup.unwrap().pkgs,
+        vec![
+            Pkg::new("gradle").set_override("java", "jdk17"),
+            Pkg::new("jdk17")
+        ]
+    );
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["gradle clean build -x check".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some("java -Dmicronaut.server.port=$PORT $JAVA_OPTS -jar build/libs/*-all.jar".to_string())
+    );
+    Ok(())
+}
+
 #[test]
 fn test_zig() -> Result<()> {
     let plan = simple_gen_plan("./examples/zig");
@@ -659,7 +1104,7 @@ fn test_zig() -> Result<()> {
     Ok(())
 }
 
-#[cfg(any(target_arch = "aarch64", target_arch = "x86_64", target_arch = "i386"))]
+#[cfg(any(target_arch = "aarch64", target_arch = "x86_64", target_arch = "x86"))]
 #[test]
 fn test_zig_gyro() -> Result<()> {
     let plan = simple_gen_plan("./examples/zig-gyro");
@@ -676,7 +1121,7 @@ fn test_zig_gyro() -> Result<()> {
         .unwrap()
         .cmds
         .unwrap()
-        .get(0)
+        .first()
         .unwrap()
         .contains("mkdir /gyro"));
     Ok(())
@@ -685,13 +1130,46 @@ fn test_zig_gyro() -> Result<()> {
 #[test]
 fn test_ruby_rails() -> Result<()> {
     let plan = simple_gen_plan("./examples/ruby-rails");
+    assert_eq!(
+        plan.setup.unwrap().pkgs,
+        vec![
+            Pkg::new("pkgs.\"ruby-3.0.3\"").from_overlay(
+                "https://github.com/bobvanderlinden/nixpkgs-ruby/archive/master.tar.gz"
+            ),
+            Pkg::new("gcc"),
+            Pkg::new("gnumake"),
+            Pkg::new("pkg-config"),
+            Pkg::new("sqlite"),
+            Pkg::new("libxml2"),
+            Pkg::new("libxslt"),
+        ]
+    );
     assert_eq!(
         plan.install.unwrap().cmds,
-        Some(vec!["bundle install".to_string()])
+        Some(vec![
+            "gem install bundler -v 2.3.12".to_string(),
+            "bundle install".to_string()
+        ])
     );
     assert_eq!(
-        plan.start.unwrap().cmd,
-        Some("bundle exec bin/rails server -b 0.0.0.0 -p ${PORT:-3000} -e $RAILS_ENV".to_string())
+        plan.build.unwrap().cmds,
+        Some(vec![
+            "SECRET_KEY_BASE_DUMMY=1 SECRET_KEY_BASE=${SECRET_KEY_BASE:-precompile_placeholder} bundle exec rake assets:precompile".to_string(),
+            "bundle exec bootsnap precompile --gemfile app/ lib/".to_string()
+        ])
+    );
+    assert_eq!(
+        plan.variables.unwrap().get("RAILS_ENV"),
+        Some(&"production".to_string())
+    );
+    let start = plan.start.unwrap();
+    assert_eq!(
+        start.cmd,
+        Some("bundle exec bin/rails server -b 0.0.0.0 -p ${PORT:-3000} -e $RAILS_ENV".to_string())
+    );
+    assert_eq!(
+        start.release_cmd,
+        Some("bundle exec rake db:migrate".to_string())
     );
     Ok(())
 }
@@ -701,8 +1179,564 @@ fn test_ruby_sinatra() -> Result<()> {
     let plan = simple_gen_plan("./examples/ruby-sinatra");
     assert_eq!(
         plan.install.unwrap().cmds,
-        Some(vec!["bundle install".to_string()])
+        Some(vec![
+            "gem install bundler -v 2.2.8".to_string(),
+            "bundle install".to_string()
+        ])
     );
     assert_eq!(plan.start.unwrap().cmd, Some("ruby app.rb".to_string()));
     Ok(())
 }
+
+#[test]
+fn test_ruby_no_version() -> Result<()> {
+    let plan = simple_gen_plan("./examples/ruby-no-version");
+    assert_eq!(
+        plan.setup.unwrap().pkgs,
+        vec![
+            Pkg::new("ruby"),
+            Pkg::new("gcc"),
+            Pkg::new("gnumake"),
+            Pkg::new("pkg-config")
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_elixir_phoenix() -> Result<()> {
+    let plan = simple_gen_plan("./examples/elixir-phoenix");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("elixir_1_13")]);
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec![
+            "mix compile".to_string(),
+            "mix assets.deploy".to_string(),
+            "mix release".to_string()
+        ])
+    );
+
+    let start = plan.start.unwrap();
+    assert_eq!(
+        start.cmd,
+        Some("_build/prod/rel/hello/bin/hello start".to_string())
+    );
+    assert_eq!(
+        start.release_cmd,
+        Some("_build/prod/rel/hello/bin/migrate".to_string())
+    );
+    assert_eq!(
+        plan.variables.unwrap().get("PHX_SERVER"),
+        Some(&"true".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_clojure_lein() -> Result<()> {
+    let plan = simple_gen_plan("./examples/clojure-lein");
+    assert_eq!(
+        plan.setup.unwrap().pkgs,
+        vec![Pkg::new("leiningen"), Pkg::new("jdk8")]
+    );
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["lein uberjar".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some("java $JAVA_OPTS -jar target/uberjar/*standalone.jar".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_clojure_deps() -> Result<()> {
+    let plan = simple_gen_plan("./examples/clojure-deps");
+    assert_eq!(
+        plan.setup.unwrap().pkgs,
+        vec![Pkg::new("clojure"), Pkg::new("jdk8")]
+    );
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some("clojure -M -m hello.core".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_scala_sbt() -> Result<()> {
+    let plan = simple_gen_plan("./examples/scala-sbt");
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["sbt clean stage".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some("target/universal/stage/bin/hello-scala".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_elm() -> Result<()> {
+    let plan = simple_gen_plan("./examples/elm");
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("elmPackages.elm")]);
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec![
+            "elm make src/Main.elm --optimize --output=public/index.html".to_string()
+        ])
+    );
+
+    let start = plan.start.unwrap();
+    assert_eq!(start.run_image, Some("nginx:stable-alpine".to_string()));
+    assert_eq!(start.only_include_files, Some(vec!["./public".to_string()]));
+
+    Ok(())
+}
+
+#[test]
+fn test_gleam() -> Result<()> {
+    let plan = simple_gen_plan("./examples/gleam");
+    assert_eq!(
+        plan.install.unwrap().cmds,
+        Some(vec!["gleam deps download".to_string()])
+    );
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["gleam export erlang-shipment".to_string()])
+    );
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some("./build/erlang-shipment/entrypoint.sh run".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_dockerfile_import() -> Result<()> {
+    let plan = simple_gen_plan("./examples/dockerfile");
+    let setup = plan.setup.unwrap();
+    assert_eq!(setup.pkgs, vec![Pkg::new("python310")]);
+    assert_eq!(setup.apt_pkgs, Some(vec!["curl".to_string()]));
+    assert_eq!(plan.build.unwrap().cmds, None);
+    assert_eq!(
+        plan.start.unwrap().cmd,
+        Some("python server.py".to_string())
+    );
+
+    let variables = plan.variables.unwrap();
+    assert_eq!(
+        variables.get("GREETING"),
+        Some(&"Hello from a Dockerfile".to_string())
+    );
+    assert_eq!(variables.get("PORT"), Some(&"8000".to_string()));
+    assert_eq!(plan.dockerfile, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_dockerfile_build() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/dockerfile",
+        vec!["NIXPACKS_DOCKERFILE=build"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(plan.dockerfile, Some("Dockerfile".to_string()));
+    assert!(plan.setup.is_none());
+    assert!(plan.start.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_dockerfile_ignored() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/dockerfile",
+        vec!["NIXPACKS_DOCKERFILE=false", "NIXPACKS_NO_START_CMD=1"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(plan.start.unwrap().cmd, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_no_provider_and_no_start_cmd() -> Result<()> {
+    let error = generate_build_plan(
+        "./examples/dockerfile",
+        vec!["NIXPACKS_DOCKERFILE=false"],
+        &GeneratePlanOptions::default(),
+    )
+    .unwrap_err()
+    .to_string();
+
+    assert!(error.contains("No provider matched the app"));
+    assert!(error.contains("python: main.py, requirements.txt, pyproject.toml"));
+    assert!(error.contains("closest to python (1 source files)"));
+    assert!(error.contains("NIXPACKS_START_CMD"));
+    assert!(error.contains("Procfile"));
+
+    Ok(())
+}
+
+#[test]
+fn test_tool_only_image() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/shell-hello",
+        vec!["NIXPACKS_PKGS=cowsay", "NIXPACKS_NO_START_CMD=true"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(plan.setup.unwrap().pkgs, vec![Pkg::new("cowsay")]);
+    assert_eq!(plan.start.unwrap().cmd, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_spring_boot_actuator_healthcheck() -> Result<()> {
+    let plan = simple_gen_plan("./examples/java-maven");
+    let start = plan.start.unwrap();
+    assert_eq!(
+        start.healthcheck.unwrap().path,
+        Some("/actuator/health".to_string())
+    );
+    // Spring Boot's default port
+    assert_eq!(start.ports, Some(vec![8080]));
+
+    Ok(())
+}
+
+#[test]
+fn test_inferred_ports() -> Result<()> {
+    assert_eq!(
+        simple_gen_plan("./examples/ruby-rails")
+            .start
+            .unwrap()
+            .ports,
+        Some(vec![3000])
+    );
+    assert_eq!(
+        simple_gen_plan("./examples/staticfile")
+            .start
+            .unwrap()
+            .ports,
+        Some(vec![80])
+    );
+    // From the PORT variable
+    assert_eq!(
+        simple_gen_plan("./examples/php-vanilla")
+            .start
+            .unwrap()
+            .ports,
+        Some(vec![80])
+    );
+    assert_eq!(
+        simple_gen_plan("./examples/dockerfile")
+            .start
+            .unwrap()
+            .ports,
+        Some(vec![8000])
+    );
+    assert_eq!(simple_gen_plan("./examples/go").start.unwrap().ports, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_ports_and_healthcheck_from_environment_variables() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/ruby-rails",
+        vec!["NIXPACKS_PORTS=8080 9090", "NIXPACKS_HEALTHCHECK=/status"],
+        &GeneratePlanOptions::default(),
+    )?;
+    let start = plan.start.unwrap();
+    assert_eq!(start.ports, Some(vec![8080, 9090]));
+    assert_eq!(start.healthcheck.unwrap().path, Some("/status".to_string()));
+
+    let plan = generate_build_plan(
+        "./examples/java-maven",
+        vec!["NIXPACKS_HEALTHCHECK=false"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert!(plan.start.unwrap().healthcheck.is_none());
+
+    let plan = generate_build_plan(
+        "./examples/go",
+        vec!["NIXPACKS_HEALTHCHECK=./out --check"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(
+        plan.start.unwrap().healthcheck.unwrap().cmd,
+        Some("./out --check".to_string())
+    );
+
+    assert!(generate_build_plan(
+        "./examples/go",
+        vec!["NIXPACKS_PORTS=http"],
+        &GeneratePlanOptions::default(),
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_non_root_slim_image() -> Result<()> {
+    let plan = simple_gen_plan("./examples/go");
+    assert_eq!(plan.start.unwrap().user, Some("app".to_string()));
+
+    let plan = generate_build_plan(
+        "./examples/go",
+        vec!["NIXPACKS_NON_ROOT=false"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(plan.start.unwrap().user, None);
+
+    // Root by default in the build image
+    assert_eq!(simple_gen_plan("./examples/node").start.unwrap().user, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_writable_paths() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/php-laravel",
+        vec![
+            "NIXPACKS_NON_ROOT=1",
+            "NIXPACKS_READ_ONLY_APP=1",
+            "NIXPACKS_WRITABLE_PATHS=./uploads",
+        ],
+        &GeneratePlanOptions::default(),
+    )?;
+    let start = plan.start.unwrap();
+    assert_eq!(start.user, Some("app".to_string()));
+    assert_eq!(start.read_only, Some(true));
+    assert_eq!(
+        start.writable_paths,
+        Some(vec![
+            "storage".to_string(),
+            "bootstrap/cache".to_string(),
+            "uploads".to_string()
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_app_root() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/node-monorepo-root",
+        Vec::new(),
+        &GeneratePlanOptions {
+            root: Some("services/api".to_string()),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(plan.start.unwrap().cmd, Some("npm run start".to_string()));
+
+    let plan = generate_build_plan(
+        "./examples/node-monorepo-root",
+        Vec::new(),
+        &GeneratePlanOptions {
+            root: Some("services/web".to_string()),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(
+        plan.build.unwrap().cmds,
+        Some(vec!["go build -o out".to_string()])
+    );
+
+    assert!(generate_build_plan(
+        "./examples/node-monorepo-root",
+        Vec::new(),
+        &GeneratePlanOptions {
+            root: Some("../node".to_string()),
+            ..Default::default()
+        },
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_env_file() -> Result<()> {
+    let plan = generate_build_plan(
+        "./examples/node-env-file",
+        vec!["DATABASE_HOST=db.internal", "TEMPLATE=$${DATABASE_HOST}"],
+        &GeneratePlanOptions {
+            env_files: vec!["./examples/node-env-file/production.env".to_string()],
+            ..Default::default()
+        },
+    )?;
+    let variables = plan.variables.unwrap();
+    assert_eq!(
+        variables.get("DATABASE_URL"),
+        Some(&"postgres://db.internal:5432/app?sslmode=require".to_string())
+    );
+    assert_eq!(
+        variables.get("SECRET_KEY"),
+        Some(&"c2VjcmV0${not interpolated}==".to_string())
+    );
+    assert_eq!(
+        variables.get("NODE_ENV"),
+        Some(&"production-debug".to_string())
+    );
+    assert_eq!(
+        variables.get("TEMPLATE"),
+        Some(&"${DATABASE_HOST}".to_string())
+    );
+
+    assert!(generate_build_plan(
+        "./examples/node-env-file",
+        Vec::new(),
+        &GeneratePlanOptions {
+            env_files: vec!["./examples/node-env-file/missing.env".to_string()],
+            ..Default::default()
+        },
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_plans() -> Result<()> {
+    let changes = diff_build_plans(
+        "./examples/node-custom-version",
+        "./examples/node",
+        Vec::new(),
+        &GeneratePlanOptions::default(),
+    )?;
+    assert_eq!(
+        changes,
+        vec![
+            PlanChange {
+                phase: "Setup",
+                field: "pkgs".to_string(),
+                change: Change::Removed("nodejs".to_string()),
+            },
+            PlanChange {
+                phase: "Setup",
+                field: "pkgs".to_string(),
+                change: Change::Added("nodejs-18_x".to_string()),
+            },
+        ]
+    );
+
+    // Against a saved plan
+    let dir = tempdir::TempDir::new("nixpacks-plan")?;
+    let plan_path = dir.path().join("plan.json");
+    std::fs::write(
+        &plan_path,
+        serde_json::to_string(&simple_gen_plan("./examples/node"))?,
+    )?;
+    assert!(diff_build_plans(
+        "./examples/node",
+        plan_path.to_str().unwrap(),
+        Vec::new(),
+        &GeneratePlanOptions::default(),
+    )?
+    .is_empty());
+
+    // Archives are compared as app sources, not read as plans
+    let archive = dir.path().join("node.tar.gz");
+    let status = std::process::Command::new("tar")
+        .arg("-czf")
+        .arg(&archive)
+        .arg("-C")
+        .arg("./examples")
+        .arg("node")
+        .status()?;
+    assert!(status.success());
+    assert!(diff_build_plans(
+        "./examples/node",
+        archive.to_str().unwrap(),
+        Vec::new(),
+        &GeneratePlanOptions::default(),
+    )?
+    .is_empty());
+
+    // Switching to building the app's Dockerfile as is
+    let plan_path = dir.path().join("dockerfile.json");
+    std::fs::write(
+        &plan_path,
+        serde_json::to_string(&simple_gen_plan("./examples/dockerfile"))?,
+    )?;
+    let changes = diff_build_plans(
+        "./examples/dockerfile",
+        plan_path.to_str().unwrap(),
+        vec!["NIXPACKS_DOCKERFILE=build"],
+        &GeneratePlanOptions::default(),
+    )?;
+    assert!(changes.contains(&PlanChange {
+        phase: "Dockerfile",
+        field: "dockerfile".to_string(),
+        change: Change::Added("Dockerfile".to_string()),
+    }));
+
+    Ok(())
+}
+
+/// Commits the node example to a bare repo at `<dir>/<name>`, returning its `file://` URL
+fn create_node_repo(dir: &std::path::Path, name: &str) -> Result<String> {
+    let work = dir.join("work");
+    std::fs::create_dir(&work)?;
+    for file in ["package.json", "package-lock.json", "index.js"] {
+        std::fs::copy(format!("./examples/node/{}", file), work.join(file))?;
+    }
+
+    let git = |args: &[&str]| -> Result<()> {
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&work)
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "nixpacks")
+            .env("GIT_AUTHOR_EMAIL", "nixpacks@example.com")
+            .env("GIT_COMMITTER_NAME", "nixpacks")
+            .env("GIT_COMMITTER_EMAIL", "nixpacks@example.com")
+            .status()?;
+        assert!(status.success());
+        Ok(())
+    };
+    git(&["init", "--quiet"])?;
+    git(&["add", "-A"])?;
+    git(&["commit", "--quiet", "-m", "Initial commit"])?;
+
+    let bare = dir.join(name);
+    let status = std::process::Command::new("git")
+        .args(["clone", "--quiet", "--bare"])
+        .arg(&work)
+        .arg(&bare)
+        .status()?;
+    assert!(status.success());
+
+    Ok(format!("file://{}", bare.canonicalize()?.display()))
+}
+
+#[test]
+fn test_git_url_source() -> Result<()> {
+    let dir = tempdir::TempDir::new("nixpacks-repo")?;
+
+    let url = create_node_repo(dir.path(), "node.git")?;
+    let plan = simple_gen_plan(&url);
+    assert_eq!(plan.install.unwrap().cmds, Some(vec!["npm ci".to_string()]));
+    assert_eq!(plan.start.unwrap().cmd, Some("npm run start".to_string()));
+
+    std::fs::rename(dir.path().join("node.git"), dir.path().join("node"))?;
+    let plan = simple_gen_plan(&format!("git+{}", url.trim_end_matches(".git")));
+    assert_eq!(plan.start.unwrap().cmd, Some("npm run start".to_string()));
+
+    Ok(())
+}